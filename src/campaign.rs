@@ -0,0 +1,272 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use thiserror::Error;
+
+/// The official campaign boxes, as `(code, name)`.
+pub const CAMPAIGNS: [(&str, &str); 8] = [
+    ("rise_of_red_skull", "The Rise of Red Skull"),
+    ("galaxys_most_wanted", "Galaxy's Most Wanted"),
+    ("mad_titans_shadow", "The Mad Titan's Shadow"),
+    ("sinister_motives", "Sinister Motives"),
+    ("mutant_genesis", "Mutant Genesis"),
+    ("next_evolution", "NeXt Evolution"),
+    ("age_of_apocalypse", "Age of Apocalypse"),
+    ("agents_of_shield", "Agents of S.H.I.E.L.D."),
+];
+
+/// Campaign logs by the id of the channel or thread they're played in.
+pub type CampaignLogs = HashMap<u64, CampaignLog>;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum CampaignError {
+    #[error("No hero named {0} in this campaign")]
+    NoSuchHero(String),
+    #[error("Obligations are recorded for a hero")]
+    HeroRequired,
+    #[error("{0} is already in this campaign")]
+    DuplicateHero(String),
+    #[error("{card} isn't recorded for {hero}")]
+    NotRecorded { hero: String, card: String },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct CampaignHero {
+    pub name: String,
+    #[serde(default)]
+    pub obligations: Vec<String>,
+    #[serde(default)]
+    pub upgrades: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ScenarioResult {
+    pub scenario: String,
+    pub won: bool,
+    #[serde(default)]
+    pub notes: Option<String>,
+}
+
+/// The campaign log sheet of an official campaign box.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct CampaignLog {
+    pub campaign: String,
+    #[serde(default)]
+    pub expert: bool,
+    #[serde(default)]
+    pub heroes: Vec<CampaignHero>,
+    /// Upgrades that belong to the whole team rather than one hero.
+    #[serde(default)]
+    pub upgrades: Vec<String>,
+    #[serde(default)]
+    pub results: Vec<ScenarioResult>,
+    #[serde(default)]
+    pub notes: Vec<String>,
+}
+
+/// What an obligation or upgrade change applies to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Entry {
+    Obligation,
+    Upgrade,
+}
+
+impl CampaignLog {
+    pub fn new(campaign: &str, expert: bool, heroes: &[&str]) -> Self {
+        Self {
+            campaign: campaign_name(campaign).to_string(),
+            expert,
+            heroes: heroes
+                .iter()
+                .map(|name| CampaignHero {
+                    name: name.to_string(),
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    pub fn add_hero(&mut self, name: &str) -> Result<(), CampaignError> {
+        if self.hero_index(name).is_some() {
+            return Err(CampaignError::DuplicateHero(name.to_string()));
+        }
+        self.heroes.push(CampaignHero {
+            name: name.to_string(),
+            ..Default::default()
+        });
+        Ok(())
+    }
+
+    /// Records an obligation or upgrade, for a hero or, for upgrades without one, the team.
+    pub fn add(
+        &mut self,
+        entry: Entry,
+        hero: Option<&str>,
+        card: &str,
+    ) -> Result<(), CampaignError> {
+        self.entries(entry, hero)?.push(card.to_string());
+        Ok(())
+    }
+
+    pub fn remove(
+        &mut self,
+        entry: Entry,
+        hero: Option<&str>,
+        card: &str,
+    ) -> Result<(), CampaignError> {
+        let entries = self.entries(entry, hero)?;
+        match entries
+            .iter()
+            .position(|recorded| recorded.eq_ignore_ascii_case(card))
+        {
+            Some(index) => {
+                entries.remove(index);
+                Ok(())
+            }
+            None => Err(CampaignError::NotRecorded {
+                hero: hero.unwrap_or("the team").to_string(),
+                card: card.to_string(),
+            }),
+        }
+    }
+
+    pub fn record_result(&mut self, scenario: &str, won: bool, notes: Option<&str>) {
+        self.results.push(ScenarioResult {
+            scenario: scenario.to_string(),
+            won,
+            notes: notes.map(str::to_string),
+        });
+    }
+
+    /// The log as plain text, to show and to give the model as context.
+    pub fn describe(&self) -> String {
+        let mut lines = vec![format!(
+            "Campaign: {}{}",
+            self.campaign,
+            if self.expert { " (expert)" } else { "" }
+        )];
+        for hero in &self.heroes {
+            lines.push(format!(
+                "Hero {}: obligations: {}; upgrades: {}",
+                hero.name,
+                list(&hero.obligations),
+                list(&hero.upgrades)
+            ));
+        }
+        if !self.upgrades.is_empty() {
+            lines.push(format!("Team upgrades: {}", list(&self.upgrades)));
+        }
+        if self.results.is_empty() {
+            lines.push("No scenarios played yet.".to_string());
+        }
+        for (number, result) in self.results.iter().enumerate() {
+            lines.push(format!(
+                "Scenario {}: {}, {}{}",
+                number + 1,
+                result.scenario,
+                if result.won { "won" } else { "lost" },
+                result
+                    .notes
+                    .as_ref()
+                    .map(|notes| format!(" ({notes})"))
+                    .unwrap_or_default()
+            ));
+        }
+        for note in &self.notes {
+            lines.push(format!("Note: {note}"));
+        }
+
+        lines.join("\n")
+    }
+
+    fn hero_index(&self, name: &str) -> Option<usize> {
+        self.heroes
+            .iter()
+            .position(|hero| hero.name.eq_ignore_ascii_case(name))
+    }
+
+    fn entries(
+        &mut self,
+        entry: Entry,
+        hero: Option<&str>,
+    ) -> Result<&mut Vec<String>, CampaignError> {
+        let Some(name) = hero else {
+            return match entry {
+                Entry::Upgrade => Ok(&mut self.upgrades),
+                Entry::Obligation => Err(CampaignError::HeroRequired),
+            };
+        };
+
+        let index = self
+            .hero_index(name)
+            .ok_or_else(|| CampaignError::NoSuchHero(name.to_string()))?;
+        let hero = &mut self.heroes[index];
+        Ok(match entry {
+            Entry::Obligation => &mut hero.obligations,
+            Entry::Upgrade => &mut hero.upgrades,
+        })
+    }
+}
+
+/// The name of a campaign code, or the text as given for unofficial campaigns.
+pub fn campaign_name(campaign: &str) -> &str {
+    CAMPAIGNS
+        .iter()
+        .find(|(code, _)| *code == campaign)
+        .map_or(campaign, |(_, name)| name)
+}
+
+fn list(items: &[String]) -> String {
+    if items.is_empty() {
+        "none".to_string()
+    } else {
+        items.join(", ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_log() {
+        let mut log = CampaignLog::new("rise_of_red_skull", true, &["Spider-Man", "Hulk"]);
+        log.add(Entry::Obligation, Some("spider-man"), "Eviction Notice")
+            .unwrap();
+        log.add(Entry::Upgrade, Some("Hulk"), "Mjolnir").unwrap();
+        log.add(Entry::Upgrade, None, "Team Building").unwrap();
+        log.record_result("Crossbones", true, Some("Wasp joined"));
+        log.remove(Entry::Obligation, Some("Spider-Man"), "eviction notice")
+            .unwrap();
+
+        assert_eq!(
+            log.describe(),
+            "Campaign: The Rise of Red Skull (expert)\n\
+             Hero Spider-Man: obligations: none; upgrades: none\n\
+             Hero Hulk: obligations: none; upgrades: Mjolnir\n\
+             Team upgrades: Team Building\n\
+             Scenario 1: Crossbones, won (Wasp joined)"
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut log = CampaignLog::new("mutant_genesis", false, &["Cyclops"]);
+
+        assert_eq!(
+            log.add_hero("cyclops"),
+            Err(CampaignError::DuplicateHero("cyclops".to_string()))
+        );
+        assert_eq!(
+            log.add(Entry::Obligation, Some("Storm"), "Weather Witch"),
+            Err(CampaignError::NoSuchHero("Storm".to_string()))
+        );
+        assert_eq!(
+            log.remove(Entry::Upgrade, Some("Cyclops"), "Optic Blast"),
+            Err(CampaignError::NotRecorded {
+                hero: "Cyclops".to_string(),
+                card: "Optic Blast".to_string()
+            })
+        );
+    }
+}
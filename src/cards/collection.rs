@@ -0,0 +1,68 @@
+use std::collections::{BTreeSet, HashMap};
+
+use super::CardDatabase;
+
+/// The pack codes each Discord user owns, by user id.
+pub type Collections = HashMap<u64, BTreeSet<String>>;
+
+/// Describes a user's packs for the model, or `None` if they haven't recorded any.
+pub fn describe(
+    packs: &BTreeSet<String>,
+    database: &CardDatabase,
+    owned_only: bool,
+) -> Option<String> {
+    if packs.is_empty() {
+        return None;
+    }
+
+    let mut context = format!(
+        "The user owns these packs: {}.",
+        pack_names(packs, database)
+    );
+    if owned_only {
+        context.push_str(
+            " Only recommend cards, heroes and scenarios from these packs. Check a card's pack before recommending it.",
+        );
+    }
+
+    Some(context)
+}
+
+/// Joins the names of the packs with these codes.
+pub fn pack_names<'a>(
+    codes: impl IntoIterator<Item = &'a String>,
+    database: &CardDatabase,
+) -> String {
+    codes
+        .into_iter()
+        .map(|code| {
+            database
+                .pack(code)
+                .map_or(code.as_str(), |pack| pack.name.as_str())
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cards::database::tests::test_database;
+
+    #[test]
+    fn test_describe() {
+        let database = test_database();
+        let packs = BTreeSet::from(["core".to_string(), "hulk".to_string()]);
+
+        assert_eq!(
+            describe(&packs, &database, false),
+            Some("The user owns these packs: Core Set, Hulk.".to_string())
+        );
+        assert!(
+            describe(&packs, &database, true)
+                .unwrap()
+                .contains("Only recommend")
+        );
+        assert_eq!(describe(&BTreeSet::new(), &database, true), None);
+    }
+}
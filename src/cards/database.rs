@@ -0,0 +1,579 @@
+use serde::{Deserialize, de::DeserializeOwned};
+use serde_json::{Map, Value};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+
+use super::{
+    fuzzy,
+    types::{Card, CardSet, CardType, Faction, Pack},
+    validation::Legality,
+};
+
+/// Fuzzy matches scoring below this are dropped.
+const MIN_FUZZY_SCORE: f64 = 0.5;
+
+#[derive(Error, Debug)]
+#[allow(clippy::enum_variant_names)]
+pub enum CardDatabaseError {
+    #[error("IO error reading {0}: {1}")]
+    IoError(PathBuf, std::io::Error),
+    #[error("JSON error reading {0}: {1}")]
+    JsonError(PathBuf, serde_json::Error),
+}
+
+/// Filters for [`CardDatabase::search`]. Every filter that is set has to match.
+#[derive(Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct CardQuery {
+    pub code: Option<String>,
+    /// Matches names containing this, or only the exact name if any card has it.
+    pub name: Option<String>,
+    #[serde(rename = "trait")]
+    pub card_trait: Option<String>,
+    pub set_code: Option<String>,
+    pub pack_code: Option<String>,
+    pub aspect: Option<String>,
+}
+
+impl CardQuery {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// An in-memory, indexed copy of MarvelCDB's card data.
+#[derive(Debug, Default)]
+pub struct CardDatabase {
+    cards: Vec<Card>,
+    packs: Vec<Pack>,
+    sets: Vec<CardSet>,
+    factions: Vec<Faction>,
+    types: Vec<CardType>,
+    legality: Legality,
+    by_code: HashMap<String, usize>,
+    by_name: HashMap<String, Vec<usize>>,
+    by_trait: HashMap<String, Vec<usize>>,
+    by_set: HashMap<String, Vec<usize>>,
+    by_pack: HashMap<String, Vec<usize>>,
+    by_faction: HashMap<String, Vec<usize>>,
+}
+
+impl CardDatabase {
+    /// Loads a MarvelCDB JSON data dump: `packs.json`, `sets.json`, `factions.json` and
+    /// `types.json`, plus the cards of every pack in `pack/*.json`. The ban list is read from an
+    /// optional `legality.json`.
+    pub fn load(dir: &Path) -> Result<Self, CardDatabaseError> {
+        let pack_dir = dir.join("pack");
+        let mut pack_files: Vec<PathBuf> = fs::read_dir(&pack_dir)
+            .map_err(|e| CardDatabaseError::IoError(pack_dir.clone(), e))?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .collect();
+        pack_files.sort();
+
+        let mut cards = Vec::new();
+        for pack_file in pack_files {
+            cards.extend(read_json::<Vec<Card>>(&pack_file)?);
+        }
+
+        let legality_file = dir.join("legality.json");
+        let legality = if legality_file.exists() {
+            read_json(&legality_file)?
+        } else {
+            Legality::default()
+        };
+
+        Ok(Self::new(
+            cards,
+            read_json(&dir.join("packs.json"))?,
+            read_json(&dir.join("sets.json"))?,
+            read_json(&dir.join("factions.json"))?,
+            read_json(&dir.join("types.json"))?,
+        )
+        .with_legality(legality))
+    }
+
+    pub fn new(
+        cards: Vec<Card>,
+        packs: Vec<Pack>,
+        sets: Vec<CardSet>,
+        factions: Vec<Faction>,
+        types: Vec<CardType>,
+    ) -> Self {
+        let mut database = Self {
+            cards,
+            packs,
+            sets,
+            factions,
+            types,
+            ..Default::default()
+        };
+
+        for (index, card) in database.cards.iter().enumerate() {
+            database.by_code.insert(card.code.clone(), index);
+            if card.is_duplicate() {
+                continue;
+            }
+
+            database
+                .by_name
+                .entry(normalize_name(&card.name))
+                .or_default()
+                .push(index);
+            for card_trait in card.traits() {
+                database
+                    .by_trait
+                    .entry(card_trait.to_lowercase())
+                    .or_default()
+                    .push(index);
+            }
+            if let Some(set_code) = &card.set_code {
+                database
+                    .by_set
+                    .entry(set_code.clone())
+                    .or_default()
+                    .push(index);
+            }
+            database
+                .by_faction
+                .entry(card.faction_code.clone())
+                .or_default()
+                .push(index);
+        }
+        // Reprints are indexed under the pack they were reprinted in.
+        for (index, card) in database.cards.iter().enumerate() {
+            database
+                .by_pack
+                .entry(card.pack_code.clone())
+                .or_default()
+                .push(index);
+        }
+
+        database
+    }
+
+    pub fn with_legality(mut self, legality: Legality) -> Self {
+        self.legality = legality;
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cards.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.cards.len()
+    }
+
+    /// Every card that isn't a reprint of another card.
+    pub fn cards(&self) -> impl Iterator<Item = &Card> {
+        self.cards.iter().filter(|card| !card.is_duplicate())
+    }
+
+    pub fn card(&self, code: &str) -> Option<&Card> {
+        self.by_code.get(code).map(|index| &self.cards[*index])
+    }
+
+    /// Cards with exactly this name, ignoring case and punctuation.
+    pub fn cards_named(&self, name: &str) -> Vec<&Card> {
+        self.lookup(&self.by_name, &normalize_name(name))
+    }
+
+    pub fn cards_with_trait(&self, card_trait: &str) -> Vec<&Card> {
+        self.lookup(&self.by_trait, &card_trait.trim().to_lowercase())
+    }
+
+    pub fn cards_in_set(&self, set_code: &str) -> Vec<&Card> {
+        self.lookup(&self.by_set, set_code)
+    }
+
+    pub fn cards_in_pack(&self, pack_code: &str) -> Vec<&Card> {
+        self.lookup(&self.by_pack, pack_code)
+    }
+
+    /// Cards of an aspect, or of the `basic`, `hero`, `pool` and `encounter` factions.
+    pub fn cards_in_aspect(&self, faction_code: &str) -> Vec<&Card> {
+        self.lookup(&self.by_faction, faction_code)
+    }
+
+    pub fn search(&self, query: &CardQuery) -> Vec<&Card> {
+        if let Some(code) = &query.code {
+            return self.card(code).into_iter().collect();
+        }
+
+        let name = query.name.as_deref().map(normalize_name);
+        let card_trait = query.card_trait.as_deref().map(|t| t.trim().to_lowercase());
+        // Start from an index the query narrows the cards down to. Reprints are only indexed under the
+        // pack they were reprinted in, so they only show up when looking at that pack.
+        let candidates: Vec<&Card> = if let Some(pack_code) = &query.pack_code {
+            self.cards_in_pack(pack_code)
+        } else if let Some(card_trait) = &card_trait {
+            self.cards_with_trait(card_trait)
+        } else if let Some(set_code) = &query.set_code {
+            self.cards_in_set(set_code)
+        } else if let Some(aspect) = &query.aspect {
+            self.cards_in_aspect(aspect)
+        } else {
+            self.cards().collect()
+        };
+        let matches: Vec<&Card> = candidates
+            .into_iter()
+            .filter(|card| {
+                name.as_ref()
+                    .is_none_or(|name| normalize_name(&card.name).contains(name.as_str()))
+                    && card_trait.as_ref().is_none_or(|card_trait| {
+                        card.traits()
+                            .iter()
+                            .any(|t| t.to_lowercase() == *card_trait)
+                    })
+                    && query
+                        .set_code
+                        .as_ref()
+                        .is_none_or(|set_code| card.set_code.as_ref() == Some(set_code))
+                    && query
+                        .pack_code
+                        .as_ref()
+                        .is_none_or(|pack_code| card.pack_code == *pack_code)
+                    && query
+                        .aspect
+                        .as_ref()
+                        .is_none_or(|aspect| card.faction_code == *aspect)
+            })
+            .collect();
+
+        match name {
+            Some(name)
+                if matches
+                    .iter()
+                    .any(|card| normalize_name(&card.name) == name) =>
+            {
+                matches
+                    .into_iter()
+                    .filter(|card| normalize_name(&card.name) == name)
+                    .collect()
+            }
+            _ => matches,
+        }
+    }
+
+    /// Cards whose names best match `query`, tolerating typos and partial names. Best matches
+    /// come first.
+    pub fn fuzzy_search(&self, query: &str, limit: usize) -> Vec<&Card> {
+        let query = normalize_name(query);
+        let mut matches: Vec<(f64, &String)> = self
+            .by_name
+            .keys()
+            .map(|name| (fuzzy::score(&query, name), name))
+            .filter(|(score, _)| *score >= MIN_FUZZY_SCORE)
+            .collect();
+        matches.sort_by(|(a_score, a_name), (b_score, b_name)| {
+            b_score
+                .total_cmp(a_score)
+                .then(a_name.len().cmp(&b_name.len()))
+                .then(a_name.cmp(b_name))
+        });
+
+        matches
+            .into_iter()
+            .flat_map(|(_, name)| &self.by_name[name])
+            .take(limit)
+            .map(|index| &self.cards[*index])
+            .collect()
+    }
+
+    pub fn packs(&self) -> &[Pack] {
+        &self.packs
+    }
+
+    pub fn pack(&self, code: &str) -> Option<&Pack> {
+        self.packs.iter().find(|pack| pack.code == code)
+    }
+
+    /// Finds a pack by its code or name.
+    pub fn find_pack(&self, code_or_name: &str) -> Option<&Pack> {
+        self.pack(code_or_name).or_else(|| {
+            let name = normalize_name(code_or_name);
+            self.packs
+                .iter()
+                .find(|pack| normalize_name(&pack.name) == name)
+        })
+    }
+
+    pub fn sets(&self) -> &[CardSet] {
+        &self.sets
+    }
+
+    pub fn set(&self, code: &str) -> Option<&CardSet> {
+        self.sets.iter().find(|set| set.code == code)
+    }
+
+    #[allow(dead_code)]
+    pub fn factions(&self) -> &[Faction] {
+        &self.factions
+    }
+
+    pub fn faction(&self, code: &str) -> Option<&Faction> {
+        self.factions.iter().find(|faction| faction.code == code)
+    }
+
+    pub fn legality(&self) -> &Legality {
+        &self.legality
+    }
+
+    pub fn card_type(&self, code: &str) -> Option<&CardType> {
+        self.types.iter().find(|card_type| card_type.code == code)
+    }
+
+    /// The card as JSON, with the names of its type, faction, pack and set resolved the way
+    /// MarvelCDB's API returns them.
+    pub fn card_json(&self, card: &Card) -> Value {
+        let mut json = match serde_json::to_value(card) {
+            Ok(Value::Object(json)) => json,
+            _ => Map::new(),
+        };
+
+        let names = [
+            (
+                "type_name",
+                self.card_type(&card.type_code).map(|t| &t.name),
+            ),
+            (
+                "faction_name",
+                self.faction(&card.faction_code).map(|f| &f.name),
+            ),
+            ("pack_name", self.pack(&card.pack_code).map(|p| &p.name)),
+            (
+                "card_set_name",
+                card.set_code
+                    .as_deref()
+                    .and_then(|code| self.set(code))
+                    .map(|s| &s.name),
+            ),
+        ];
+        for (key, name) in names {
+            if let Some(name) = name {
+                json.insert(key.to_string(), Value::String(name.clone()));
+            }
+        }
+
+        Value::Object(json)
+    }
+
+    fn lookup(&self, index: &HashMap<String, Vec<usize>>, key: &str) -> Vec<&Card> {
+        index
+            .get(key)
+            .map(|indices| indices.iter().map(|index| &self.cards[*index]).collect())
+            .unwrap_or_default()
+    }
+}
+
+/// Lowercases a card name and drops punctuation, so "Spider-Man" matches "spider man".
+pub fn normalize_name(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+fn read_json<T: DeserializeOwned>(path: &Path) -> Result<T, CardDatabaseError> {
+    let contents =
+        fs::read_to_string(path).map_err(|e| CardDatabaseError::IoError(path.to_path_buf(), e))?;
+    serde_json::from_str(&contents).map_err(|e| CardDatabaseError::JsonError(path.to_path_buf(), e))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use serde_json::json;
+
+    pub(crate) fn test_database() -> CardDatabase {
+        let cards: Vec<Card> = serde_json::from_value(json!([
+            {
+                "code": "01001a", "name": "Spider-Man", "subname": "Peter Parker",
+                "type_code": "hero", "faction_code": "hero", "pack_code": "core",
+                "set_code": "spider_man", "traits": "Avenger.", "health": 10, "hand_size": 5
+            },
+            {
+                "code": "01002", "name": "Black Cat", "type_code": "ally",
+                "faction_code": "hero", "pack_code": "core", "set_code": "spider_man",
+                "cost": 2, "traits": "Hero for Hire.", "deck_limit": 1, "is_unique": true
+            },
+            {
+                "code": "01009", "name": "Eviction Notice", "type_code": "obligation",
+                "faction_code": "encounter", "pack_code": "core", "set_code": "spider_man"
+            },
+            {
+                "code": "01050", "name": "Chase Them Down", "type_code": "event",
+                "faction_code": "justice", "pack_code": "core", "cost": 2, "deck_limit": 3
+            },
+            {
+                "code": "01089", "name": "Haymaker", "type_code": "event",
+                "faction_code": "basic", "pack_code": "core", "cost": 2,
+                "resource_physical": 1, "deck_limit": 3
+            },
+            {
+                "code": "01092", "name": "Avengers Mansion", "type_code": "support",
+                "faction_code": "basic", "pack_code": "core", "cost": 4,
+                "traits": "Avenger. Location.", "deck_limit": 1, "is_unique": true
+            },
+            {
+                "code": "40089", "name": "Haymaker", "type_code": "event",
+                "faction_code": "basic", "pack_code": "hulk", "cost": 2,
+                "resource_physical": 1, "deck_limit": 3, "duplicate_of": "01089"
+            },
+            {
+                "code": "01094", "name": "Rhino", "type_code": "villain",
+                "faction_code": "encounter", "pack_code": "core", "set_code": "rhino",
+                "health": 14
+            }
+        ]))
+        .unwrap();
+
+        CardDatabase::new(
+            cards,
+            serde_json::from_value(json!([
+                { "code": "core", "name": "Core Set", "position": 1 },
+                { "code": "hulk", "name": "Hulk", "position": 20 }
+            ]))
+            .unwrap(),
+            serde_json::from_value(json!([
+                { "code": "spider_man", "name": "Spider-Man", "card_set_type_code": "hero" },
+                { "code": "rhino", "name": "Rhino", "card_set_type_code": "villain" }
+            ]))
+            .unwrap(),
+            serde_json::from_value(json!([
+                { "code": "basic", "name": "Basic", "is_primary": false },
+                { "code": "justice", "name": "Justice", "is_primary": true },
+                { "code": "hero", "name": "Hero", "is_primary": false }
+            ]))
+            .unwrap(),
+            serde_json::from_value(json!([
+                { "code": "event", "name": "Event" },
+                { "code": "ally", "name": "Ally" }
+            ]))
+            .unwrap(),
+        )
+    }
+
+    fn codes(cards: Vec<&Card>) -> Vec<&str> {
+        cards.into_iter().map(|card| card.code.as_str()).collect()
+    }
+
+    #[test]
+    fn test_lookups() {
+        let database = test_database();
+
+        assert_eq!(database.card("01089").unwrap().name, "Haymaker");
+        assert_eq!(database.card("40089").unwrap().pack_code, "hulk");
+        assert_eq!(database.find_pack("core set").unwrap().code, "core");
+        assert_eq!(codes(database.cards_named("spider man")), vec!["01001a"]);
+        assert_eq!(codes(database.cards_named("HAYMAKER")), vec!["01089"]);
+        assert_eq!(
+            codes(database.cards_with_trait("avenger")),
+            vec!["01001a", "01092"]
+        );
+        assert_eq!(
+            codes(database.cards_in_set("spider_man")),
+            vec!["01001a", "01002", "01009"]
+        );
+        assert_eq!(codes(database.cards_in_pack("hulk")), vec!["40089"]);
+        assert_eq!(codes(database.cards_in_aspect("justice")), vec!["01050"]);
+        assert_eq!(database.cards().count(), 7);
+    }
+
+    #[test]
+    fn test_search() {
+        let database = test_database();
+
+        let query = CardQuery {
+            name: Some("hay".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(codes(database.search(&query)), vec!["01089"]);
+
+        let query = CardQuery {
+            card_trait: Some("Avenger".to_string()),
+            aspect: Some("basic".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(codes(database.search(&query)), vec!["01092"]);
+
+        let query = CardQuery {
+            name: Some("haymaker".to_string()),
+            pack_code: Some("hulk".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(codes(database.search(&query)), vec!["40089"]);
+
+        let query = CardQuery {
+            code: Some("40089".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(codes(database.search(&query)), vec!["40089"]);
+    }
+
+    #[test]
+    fn test_search_prefers_exact_name() {
+        let database = test_database();
+        let query = CardQuery {
+            name: Some("Spider-Man".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(codes(database.search(&query)), vec!["01001a"]);
+    }
+
+    #[test]
+    fn test_fuzzy_search() {
+        let database = test_database();
+
+        assert_eq!(codes(database.fuzzy_search("haymakr", 5)), vec!["01089"]);
+        assert_eq!(codes(database.fuzzy_search("spiderman", 1)), vec!["01001a"]);
+        assert_eq!(
+            codes(database.fuzzy_search("cat", 5)).first(),
+            Some(&"01002")
+        );
+        assert!(database.fuzzy_search("zzzzzz", 5).is_empty());
+    }
+
+    #[test]
+    fn test_card_json_resolves_names() {
+        let database = test_database();
+        let json = database.card_json(database.card("01089").unwrap());
+
+        assert_eq!(json["type_name"], json!("Event"));
+        assert_eq!(json["faction_name"], json!("Basic"));
+        assert_eq!(json["pack_name"], json!("Core Set"));
+        assert_eq!(json["cost"], json!(2));
+    }
+
+    #[test]
+    fn test_load() {
+        let dir = std::env::temp_dir().join(format!("karen-cards-{}", std::process::id()));
+        fs::create_dir_all(dir.join("pack")).unwrap();
+        fs::write(
+            dir.join("packs.json"),
+            r#"[{"code": "core", "name": "Core Set"}]"#,
+        )
+        .unwrap();
+        fs::write(dir.join("sets.json"), "[]").unwrap();
+        fs::write(dir.join("factions.json"), "[]").unwrap();
+        fs::write(dir.join("types.json"), "[]").unwrap();
+        fs::write(
+            dir.join("pack").join("core.json"),
+            r#"[{"code": "01089", "name": "Haymaker", "type_code": "event", "faction_code": "basic", "pack_code": "core"}]"#,
+        )
+        .unwrap();
+
+        let database = CardDatabase::load(&dir).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(database.len(), 1);
+        assert_eq!(database.pack("core").unwrap().name, "Core Set");
+    }
+}
@@ -0,0 +1,475 @@
+use std::collections::BTreeMap;
+
+use super::{CardDatabase, types::Card};
+
+/// Copies of a card a pasted list may hold, so sums over a deck's quantities can't overflow.
+const MAX_QUANTITY: u32 = 99;
+const PRIMARY_ASPECTS: [&str; 5] = ["aggression", "justice", "leadership", "protection", "pool"];
+const SECTION_HEADERS: [&str; 14] = [
+    "hero",
+    "alter-ego",
+    "ally",
+    "allies",
+    "event",
+    "events",
+    "support",
+    "supports",
+    "upgrade",
+    "upgrades",
+    "resource",
+    "resources",
+    "player side scheme",
+    "player side schemes",
+];
+
+/// Where to get a deck from: a MarvelCDB deck or a pasted deck list.
+#[derive(Debug, PartialEq)]
+pub enum DeckReference {
+    /// A published MarvelCDB decklist.
+    Decklist(u64),
+    /// A shared, unpublished MarvelCDB deck.
+    Deck(u64),
+    List(String),
+}
+
+impl DeckReference {
+    /// Parses a decklist id, a MarvelCDB deck or decklist URL, or else a pasted list.
+    pub fn parse(input: &str) -> Self {
+        let input = input.trim();
+        if !input.is_empty()
+            && input.chars().all(|c| c.is_ascii_digit())
+            && let Ok(id) = input.parse()
+        {
+            return DeckReference::Decklist(id);
+        }
+
+        if input.contains("marvelcdb.com/") && !input.contains(char::is_whitespace) {
+            for (marker, published) in [("/decklist/view/", true), ("/deck/view/", false)] {
+                if let Some(at) = input.find(marker) {
+                    let id: String = input[at + marker.len()..]
+                        .chars()
+                        .take_while(char::is_ascii_digit)
+                        .collect();
+                    if let Ok(id) = id.parse() {
+                        return if published {
+                            DeckReference::Decklist(id)
+                        } else {
+                            DeckReference::Deck(id)
+                        };
+                    }
+                }
+            }
+        }
+
+        DeckReference::List(input.to_string())
+    }
+}
+
+/// A player deck: a hero, their aspects and the card codes with their quantities.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Deck {
+    pub name: Option<String>,
+    pub hero_code: Option<String>,
+    pub aspects: Vec<String>,
+    pub slots: BTreeMap<String, u32>,
+    /// Entries of a pasted list that didn't match a card.
+    pub unresolved: Vec<String>,
+}
+
+impl Deck {
+    /// Parses a deck list in the common text export format, one `2x Card Name (Pack)` entry per
+    /// line. Lists pasted into a single line are split at each `2x` quantity. Lines without a
+    /// quantity give the deck's name and hero; section headers are skipped.
+    pub fn parse_list(text: &str, database: &CardDatabase) -> Self {
+        let mut deck = Deck::default();
+        let multiline = text.trim().contains('\n');
+
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            let entries = if multiline {
+                line_entry(line).into_iter().collect()
+            } else {
+                inline_entries(line)
+            };
+
+            if entries.is_empty() {
+                if deck.hero_code.is_none()
+                    && let Some(hero) = find_hero(line, database)
+                {
+                    deck.hero_code = Some(hero.code.clone());
+                } else if deck.name.is_none() && !is_section_header(line) {
+                    deck.name = Some(line.to_string());
+                }
+                continue;
+            }
+
+            for (quantity, entry) in entries {
+                match resolve_entry(entry, database) {
+                    Some(card) => {
+                        let slot = deck.slots.entry(card.code.clone()).or_default();
+                        *slot = slot.saturating_add(quantity).min(MAX_QUANTITY);
+                    }
+                    None => deck.unresolved.push(entry.to_string()),
+                }
+            }
+        }
+
+        if deck.hero_code.is_none() {
+            deck.hero_code = infer_hero(&deck, database).map(|hero| hero.code.clone());
+        }
+        deck.aspects = infer_aspects(&deck, database);
+
+        deck
+    }
+
+    pub fn card_count(&self) -> u32 {
+        self.slots
+            .values()
+            .fold(0, |count, quantity| count.saturating_add(*quantity))
+    }
+
+    pub fn hero<'a>(&self, database: &'a CardDatabase) -> Option<&'a Card> {
+        self.hero_code
+            .as_deref()
+            .and_then(|code| database.card(code))
+    }
+
+    /// The deck's cards with their quantities, skipping codes missing from the database.
+    pub fn cards<'a>(&self, database: &'a CardDatabase) -> Vec<(&'a Card, u32)> {
+        self.slots
+            .iter()
+            .filter_map(|(code, quantity)| database.card(code).map(|card| (card, *quantity)))
+            .collect()
+    }
+
+    /// Describes the deck and its statistics as plain text, to give the model as context.
+    pub fn describe(&self, database: &CardDatabase) -> String {
+        let mut lines = Vec::new();
+        if let Some(name) = &self.name {
+            lines.push(format!("Deck: {name}"));
+        }
+        if let Some(hero) = self.hero(database) {
+            lines.push(format!(
+                "Hero: {} (set {})",
+                title(hero),
+                hero.set_code.as_deref().unwrap_or("unknown")
+            ));
+        }
+        if !self.aspects.is_empty() {
+            lines.push(format!("Aspects: {}", self.aspects.join(", ")));
+        }
+        lines.push(format!("Cards: {}", self.card_count()));
+
+        let stats = DeckStats::new(self, database);
+        lines.push(format!(
+            "Cost curve: {}",
+            stats
+                .curve
+                .iter()
+                .map(|(cost, count)| format!("{cost}: {count}"))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+        lines.push(format!(
+            "Resources: {} energy, {} mental, {} physical, {} wild",
+            stats.energy, stats.mental, stats.physical, stats.wild
+        ));
+        lines.push(format!(
+            "By aspect: {}",
+            stats
+                .by_faction
+                .iter()
+                .map(|(faction, count)| format!("{faction}: {count}"))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+
+        lines.push("Card list:".to_string());
+        for (card, quantity) in self.cards(database) {
+            let cost = card
+                .cost
+                .map_or_else(|| "-".to_string(), |cost| cost.to_string());
+            let signature = if card.faction_code == "hero" {
+                ", signature"
+            } else {
+                ""
+            };
+            lines.push(format!(
+                "{quantity}x {} [{}] ({}, {}, cost {cost}{signature})",
+                title(card),
+                card.code,
+                card.type_code,
+                card.faction_code
+            ));
+        }
+        if !self.unresolved.is_empty() {
+            lines.push(format!(
+                "Unrecognized entries: {}",
+                self.unresolved.join("; ")
+            ));
+        }
+
+        lines.join("\n")
+    }
+}
+
+/// Cost curve, resource and aspect counts of a deck.
+#[derive(Debug, Default, PartialEq)]
+pub struct DeckStats {
+    pub curve: BTreeMap<i32, u32>,
+    pub energy: u32,
+    pub mental: u32,
+    pub physical: u32,
+    pub wild: u32,
+    pub by_faction: BTreeMap<String, u32>,
+}
+
+impl DeckStats {
+    pub fn new(deck: &Deck, database: &CardDatabase) -> Self {
+        let mut stats = DeckStats::default();
+        for (card, quantity) in deck.cards(database) {
+            if let Some(cost) = card.cost {
+                *stats.curve.entry(cost).or_default() += quantity;
+            }
+            stats.energy += card.resource_energy.unwrap_or(0) * quantity;
+            stats.mental += card.resource_mental.unwrap_or(0) * quantity;
+            stats.physical += card.resource_physical.unwrap_or(0) * quantity;
+            stats.wild += card.resource_wild.unwrap_or(0) * quantity;
+            *stats
+                .by_faction
+                .entry(card.faction_code.clone())
+                .or_default() += quantity;
+        }
+
+        stats
+    }
+}
+
+fn title(card: &Card) -> String {
+    match &card.subname {
+        Some(subname) => format!("{} ({subname})", card.name),
+        None => card.name.clone(),
+    }
+}
+
+/// Parses `2x Card Name`, `2 Card Name` or `2× Card Name`.
+fn line_entry(line: &str) -> Option<(u32, &str)> {
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+    if digits == 0 {
+        return None;
+    }
+
+    let quantity = line[..digits].parse().ok()?;
+    let rest = &line[digits..];
+    let rest = rest
+        .strip_prefix('x')
+        .or_else(|| rest.strip_prefix('×'))
+        .unwrap_or(rest);
+    if !rest.starts_with(char::is_whitespace) {
+        return None;
+    }
+
+    Some((quantity, rest.trim()))
+}
+
+/// Splits `1x Black Cat (Core Set) 3x Haymaker` into its entries. Only the first entry may leave
+/// out the `x`, since names can contain numbers.
+fn inline_entries(text: &str) -> Vec<(u32, &str)> {
+    let mut starts = Vec::new();
+    let mut previous = None;
+    for (index, c) in text.char_indices() {
+        if c.is_ascii_digit() && previous.is_none_or(char::is_whitespace) {
+            let rest = text[index..].trim_start_matches(|c: char| c.is_ascii_digit());
+            let marked = rest.starts_with('x') || rest.starts_with('×');
+            if (marked || starts.is_empty()) && line_entry(&text[index..]).is_some() {
+                starts.push(index);
+            }
+        }
+        previous = Some(c);
+    }
+
+    starts
+        .iter()
+        .enumerate()
+        .filter_map(|(i, start)| {
+            let end = starts.get(i + 1).copied().unwrap_or(text.len());
+            line_entry(text[*start..end].trim())
+        })
+        .collect()
+}
+
+fn is_section_header(line: &str) -> bool {
+    let header = line.trim_end_matches(':').trim();
+    let header = match header.rfind('(') {
+        Some(at) if header.ends_with(')') => header[..at].trim(),
+        _ => header,
+    };
+
+    SECTION_HEADERS.contains(&header.to_lowercase().as_str())
+}
+
+/// Resolves `Card Name (Pack or Subname)` to a card, preferring player cards when a name is
+/// shared, such as an ally and a hero.
+fn resolve_entry<'a>(entry: &str, database: &'a CardDatabase) -> Option<&'a Card> {
+    let (name, qualifier) = match entry.rfind('(') {
+        Some(at) if entry.ends_with(')') => {
+            (entry[..at].trim(), Some(&entry[at + 1..entry.len() - 1]))
+        }
+        _ => (entry, None),
+    };
+
+    let candidates = database.cards_named(name);
+    let candidates: Vec<&Card> = match qualifier {
+        Some(qualifier) => {
+            let qualified: Vec<&Card> = candidates
+                .iter()
+                .copied()
+                .filter(|card| {
+                    card.subname.as_deref() == Some(qualifier)
+                        || database
+                            .pack(&card.pack_code)
+                            .is_some_and(|pack| pack.name == qualifier)
+                })
+                .collect();
+            if qualified.is_empty() {
+                candidates
+            } else {
+                qualified
+            }
+        }
+        None => candidates,
+    };
+
+    candidates
+        .iter()
+        .copied()
+        .find(|card| is_player_card(card))
+        .or_else(|| candidates.first().copied())
+}
+
+fn is_player_card(card: &Card) -> bool {
+    !matches!(
+        card.type_code.as_str(),
+        "hero" | "alter_ego" | "villain" | "main_scheme" | "obligation"
+    )
+}
+
+/// Matches `Spider-Man` or `Spider-Man (Peter Parker)` to a hero card.
+fn find_hero<'a>(line: &str, database: &'a CardDatabase) -> Option<&'a Card> {
+    let (name, subname) = match line.rfind('(') {
+        Some(at) if line.ends_with(')') => (line[..at].trim(), Some(&line[at + 1..line.len() - 1])),
+        _ => (line, None),
+    };
+
+    database.cards_named(name).into_iter().find(|card| {
+        card.type_code == "hero"
+            && subname.is_none_or(|subname| card.subname.as_deref() == Some(subname))
+    })
+}
+
+/// Finds the hero whose signature cards the deck holds.
+fn infer_hero<'a>(deck: &Deck, database: &'a CardDatabase) -> Option<&'a Card> {
+    let set_code = deck
+        .cards(database)
+        .into_iter()
+        .filter(|(card, _)| card.faction_code == "hero")
+        .find_map(|(card, _)| card.set_code.clone())?;
+
+    database
+        .cards_in_set(&set_code)
+        .into_iter()
+        .find(|card| card.type_code == "hero")
+}
+
+/// The aspects of the deck's aspect cards, most common first.
+fn infer_aspects(deck: &Deck, database: &CardDatabase) -> Vec<String> {
+    let stats = DeckStats::new(deck, database);
+    let mut aspects: Vec<(&String, &u32)> = stats
+        .by_faction
+        .iter()
+        .filter(|(faction, _)| PRIMARY_ASPECTS.contains(&faction.as_str()))
+        .collect();
+    aspects.sort_by(|(_, a), (_, b)| b.cmp(a));
+
+    aspects
+        .into_iter()
+        .map(|(aspect, _)| aspect.clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cards::database::tests::test_database;
+
+    #[test]
+    fn test_parse_reference() {
+        assert_eq!(
+            DeckReference::parse("12345"),
+            DeckReference::Decklist(12345)
+        );
+        assert_eq!(
+            DeckReference::parse("https://marvelcdb.com/decklist/view/40123/spidey-justice-1.0"),
+            DeckReference::Decklist(40123)
+        );
+        assert_eq!(
+            DeckReference::parse("https://marvelcdb.com/deck/view/987"),
+            DeckReference::Deck(987)
+        );
+        assert_eq!(
+            DeckReference::parse("3x Haymaker"),
+            DeckReference::List("3x Haymaker".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_list() {
+        let database = test_database();
+        let list = "Web Justice\n\nSpider-Man (Peter Parker)\n\nAlly (1):\n1x Black Cat (Core Set)\n\nEvent (5):\n3x Haymaker (Core Set)\n2x Chase Them Down\n1x Not A Card";
+
+        let deck = Deck::parse_list(list, &database);
+        assert_eq!(deck.name, Some("Web Justice".to_string()));
+        assert_eq!(deck.hero_code, Some("01001a".to_string()));
+        assert_eq!(deck.aspects, vec!["justice".to_string()]);
+        assert_eq!(deck.slots.get("01002"), Some(&1));
+        assert_eq!(deck.slots.get("01089"), Some(&3));
+        assert_eq!(deck.slots.get("01050"), Some(&2));
+        assert_eq!(deck.unresolved, vec!["Not A Card".to_string()]);
+        assert_eq!(deck.card_count(), 6);
+    }
+
+    #[test]
+    fn test_parse_single_line_list() {
+        let database = test_database();
+        let deck = Deck::parse_list(
+            "1x Black Cat (Core Set) 3x Haymaker 2x Chase Them Down",
+            &database,
+        );
+
+        assert_eq!(deck.hero_code, Some("01001a".to_string()));
+        assert_eq!(deck.card_count(), 6);
+        assert!(deck.unresolved.is_empty());
+    }
+
+    #[test]
+    fn test_parse_list_caps_quantities() {
+        let database = test_database();
+        let deck = Deck::parse_list("4000000000x Haymaker\n4000000000x Haymaker", &database);
+
+        assert_eq!(deck.slots.get("01089"), Some(&MAX_QUANTITY));
+        assert_eq!(deck.card_count(), MAX_QUANTITY);
+    }
+
+    #[test]
+    fn test_stats() {
+        let database = test_database();
+        let deck = Deck::parse_list(
+            "3x Haymaker 2x Chase Them Down 1x Avengers Mansion",
+            &database,
+        );
+        let stats = DeckStats::new(&deck, &database);
+
+        assert_eq!(stats.curve, BTreeMap::from([(2, 5), (4, 1)]));
+        assert_eq!(stats.physical, 3);
+        assert_eq!(stats.by_faction.get("basic"), Some(&4));
+    }
+}
@@ -0,0 +1,111 @@
+//! Typo-tolerant matching of card names, for autocomplete and lookups.
+
+/// Scores how well a normalized query matches a normalized card name, from 0 to 1.
+///
+/// Exact, compacted (ignoring spaces), prefix and substring matches rank first. Anything else
+/// is scored by edit distance against the whole name, the name's prefix of the same length as
+/// the query and each word of the name, so both typos and partially typed names match.
+pub fn score(query: &str, name: &str) -> f64 {
+    if query.is_empty() || name.is_empty() {
+        return 0.0;
+    }
+    if name == query {
+        return 1.0;
+    }
+    if compact(name) == compact(query) {
+        return 0.98;
+    }
+    if name.starts_with(query) {
+        return 0.95;
+    }
+    if name.split(' ').any(|word| word.starts_with(query)) {
+        return 0.9;
+    }
+    if name.contains(query) {
+        return 0.85;
+    }
+
+    let query_length = query.chars().count();
+    let name_prefix: String = name.chars().take(query_length).collect();
+    let whole = similarity(query, name);
+    let prefix = similarity(query, &name_prefix) * 0.95;
+    let word = name
+        .split(' ')
+        .map(|word| similarity(query, word) * 0.9)
+        .fold(0.0, f64::max);
+
+    0.8 * whole.max(prefix).max(word)
+}
+
+fn compact(text: &str) -> String {
+    text.chars().filter(|c| !c.is_whitespace()).collect()
+}
+
+/// One minus the edit distance relative to the longer string.
+fn similarity(a: &str, b: &str) -> f64 {
+    let length = a.chars().count().max(b.chars().count());
+    if length == 0 {
+        return 1.0;
+    }
+
+    1.0 - edit_distance(a, b) as f64 / length as f64
+}
+
+/// Optimal string alignment distance: Levenshtein distance that also counts swapping two
+/// adjacent characters as a single edit.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut distances = vec![vec![0; b.len() + 1]; a.len() + 1];
+
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, distance) in distances[0].iter_mut().enumerate() {
+        *distance = j;
+    }
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = (distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1)
+                .min(distances[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(distances[i - 2][j - 2] + 1);
+            }
+            distances[i][j] = distance;
+        }
+    }
+
+    distances[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("haymaker", "haymaker"), 0);
+        assert_eq!(edit_distance("haymakr", "haymaker"), 1);
+        assert_eq!(edit_distance("hyamaker", "haymaker"), 1);
+        assert_eq!(edit_distance("", "abc"), 3);
+    }
+
+    #[test]
+    fn test_score_ranks_exact_matches_first() {
+        assert_eq!(score("haymaker", "haymaker"), 1.0);
+        assert!(score("spiderman", "spider man") > score("spiderman", "spider woman"));
+        assert!(score("hay", "haymaker") > score("hay", "heimdall"));
+        assert!(score("cat", "black cat") > score("cat", "chase them down"));
+    }
+
+    #[test]
+    fn test_score_tolerates_typos() {
+        assert!(score("haymakr", "haymaker") > 0.6);
+        assert!(score("blak cat", "black cat") > 0.6);
+        assert!(score("avengers manson", "avengers mansion") > 0.6);
+        assert!(score("haymakr", "black cat") < 0.3);
+    }
+}
@@ -0,0 +1,185 @@
+use std::{
+    collections::{HashMap, HashSet},
+    ops::Range,
+};
+
+use super::CardDatabase;
+
+const CARD_URL: &str = "https://marvelcdb.com/card";
+/// Shorter names are too likely to be ordinary words or abbreviations.
+const MIN_NAME_LENGTH: usize = 4;
+
+/// Links the card names in answers to their MarvelCDB pages, so what the model says about a
+/// card is one click from being checked.
+#[derive(Debug, Default, Clone)]
+pub struct CardLinker {
+    /// Names and the code of the card they link to, longest first so that `Spider-Man` doesn't
+    /// take the place of `Spider-Man 2099`.
+    names: Vec<(String, String)>,
+}
+
+impl CardLinker {
+    pub fn new(database: &CardDatabase) -> Self {
+        let mut codes: HashMap<&str, &str> = HashMap::new();
+        for card in database.cards() {
+            codes.entry(&card.name).or_insert(&card.code);
+        }
+
+        let mut names: Vec<(String, String)> = codes
+            .into_iter()
+            .filter(|(name, _)| {
+                name.chars().count() >= MIN_NAME_LENGTH && name.chars().any(char::is_uppercase)
+            })
+            .map(|(name, code)| (name.to_string(), code.to_string()))
+            .collect();
+        names.sort_by(|a, b| b.0.len().cmp(&a.0.len()).then_with(|| a.0.cmp(&b.0)));
+
+        Self { names }
+    }
+
+    /// Every whole-word mention of a card name, in order, outside code, links, URLs and
+    /// mentions. Names must match the card's capitalization.
+    pub fn mentions(&self, text: &str) -> Vec<Mention<'_>> {
+        let mut taken = protected_ranges(text);
+        let mut mentions = Vec::new();
+
+        for (name, code) in &self.names {
+            for (start, _) in text.match_indices(name.as_str()) {
+                let range = start..start + name.len();
+                if is_whole_word(text, &range) && !taken.iter().any(|other| overlaps(other, &range))
+                {
+                    taken.push(range.clone());
+                    mentions.push(Mention { range, name, code });
+                }
+            }
+        }
+        mentions.sort_by_key(|mention| mention.range.start);
+
+        mentions
+    }
+
+    /// Turns the first mention of each card into a Markdown link.
+    pub fn link(&self, text: &str) -> String {
+        let mut linked_names = HashSet::new();
+        let mut linked = String::with_capacity(text.len());
+        let mut end = 0;
+        for mention in self.mentions(text) {
+            if !linked_names.insert(mention.name) {
+                continue;
+            }
+            linked.push_str(&text[end..mention.range.start]);
+            linked.push_str(&format!(
+                "[{}](<{CARD_URL}/{}>)",
+                mention.name, mention.code
+            ));
+            end = mention.range.end;
+        }
+        linked.push_str(&text[end..]);
+
+        linked
+    }
+}
+
+/// A card name found in a text.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mention<'a> {
+    /// The byte range of the name.
+    pub range: Range<usize>,
+    pub name: &'a str,
+    /// The code of the first card with the name.
+    pub code: &'a str,
+}
+
+/// Byte ranges of code blocks, inline code, Markdown links, URLs and `<…>` mentions.
+fn protected_ranges(text: &str) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut in_fence = false;
+    let mut offset = 0;
+
+    for line in text.split_inclusive('\n') {
+        let start = offset;
+        offset += line.len();
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+            ranges.push(start..offset);
+            continue;
+        }
+        if in_fence {
+            ranges.push(start..offset);
+            continue;
+        }
+
+        let mut rest = 0;
+        while let Some(position) = line[rest..].find(['`', '[', '<', 'h']) {
+            let from = rest + position;
+            let end = match line.as_bytes()[from] {
+                b'`' => line[from + 1..].find('`').map(|end| from + end + 2),
+                b'<' => line[from + 1..].find('>').map(|end| from + end + 2),
+                b'[' => line[from..].find("](").and_then(|middle| {
+                    line[from + middle..]
+                        .find(')')
+                        .map(|end| from + middle + end + 1)
+                }),
+                _ if line[from..].starts_with("http://")
+                    || line[from..].starts_with("https://") =>
+                {
+                    Some(
+                        line[from..]
+                            .find(char::is_whitespace)
+                            .map_or(line.len(), |end| from + end),
+                    )
+                }
+                _ => None,
+            };
+            match end {
+                Some(end) => {
+                    ranges.push(start + from..start + end);
+                    rest = end;
+                }
+                None => rest = from + 1,
+            }
+        }
+    }
+
+    ranges
+}
+
+fn is_whole_word(text: &str, range: &Range<usize>) -> bool {
+    let before = text[..range.start].chars().next_back();
+    let after = text[range.end..].chars().next();
+    !before.is_some_and(char::is_alphanumeric) && !after.is_some_and(char::is_alphanumeric)
+}
+
+fn overlaps(a: &Range<usize>, b: &Range<usize>) -> bool {
+    a.start < b.end && b.start < a.end
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cards::database::tests::test_database;
+
+    #[test]
+    fn test_link() {
+        let linker = CardLinker::new(&test_database());
+
+        assert_eq!(
+            linker.link("Play **Haymaker** with Black Cat, then Haymaker again."),
+            "Play **[Haymaker](<https://marvelcdb.com/card/01089>)** with \
+             [Black Cat](<https://marvelcdb.com/card/01002>), then Haymaker again."
+        );
+        assert_eq!(
+            linker.link("Rhinoceros and haymaker aren't cards."),
+            "Rhinoceros and haymaker aren't cards."
+        );
+    }
+
+    #[test]
+    fn test_link_skips_code_and_links() {
+        let linker = CardLinker::new(&test_database());
+        let text = "```\nHaymaker x3\n```\n\
+                    `Rhino` and [Black Cat](https://example.com) at <https://marvelcdb.com/Rhino>";
+
+        assert_eq!(linker.link(text), text);
+    }
+}
@@ -0,0 +1,109 @@
+use reqwest::{Client as ReqwestClient, StatusCode};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use thiserror::Error;
+
+use super::deck::{Deck, DeckReference};
+
+const MARVELCDB_API_URL: &str = "https://marvelcdb.com/api/public";
+
+#[derive(Error, Debug)]
+#[allow(clippy::enum_variant_names)]
+pub enum MarvelCdbError {
+    #[error("Network error: {0}")]
+    ReqwestError(#[from] reqwest::Error),
+    #[error("No MarvelCDB deck with id {0}, or it isn't shared")]
+    NotFoundError(u64),
+}
+
+/// A deck as returned by MarvelCDB's public deck and decklist endpoints.
+#[derive(Deserialize, Debug)]
+struct MarvelCdbDeck {
+    name: String,
+    hero_code: Option<String>,
+    #[serde(default)]
+    slots: BTreeMap<String, u32>,
+    /// A JSON object encoded as a string, holding the deck's `aspect` and `aspect2`.
+    meta: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct DeckMeta {
+    aspect: Option<String>,
+    aspect2: Option<String>,
+}
+
+impl From<MarvelCdbDeck> for Deck {
+    fn from(deck: MarvelCdbDeck) -> Self {
+        let meta: DeckMeta = deck
+            .meta
+            .as_deref()
+            .and_then(|meta| serde_json::from_str(meta).ok())
+            .unwrap_or_default();
+
+        Deck {
+            name: Some(deck.name),
+            hero_code: deck.hero_code,
+            aspects: meta.aspect.into_iter().chain(meta.aspect2).collect(),
+            slots: deck.slots,
+            unresolved: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Client {
+    reqwest_client: ReqwestClient,
+}
+
+impl Client {
+    /// Fetches a published decklist or a shared deck. Pasted lists have nothing to fetch and
+    /// return `None`.
+    pub async fn fetch_deck(
+        &self,
+        reference: &DeckReference,
+    ) -> Result<Option<Deck>, MarvelCdbError> {
+        let (path, id) = match reference {
+            DeckReference::Decklist(id) => ("decklist", *id),
+            DeckReference::Deck(id) => ("deck", *id),
+            DeckReference::List(_) => return Ok(None),
+        };
+
+        let response = self
+            .reqwest_client
+            .get(format!("{MARVELCDB_API_URL}/{path}/{id}"))
+            .send()
+            .await?;
+        // MarvelCDB answers unknown and private decks with an empty body or a redirect to HTML.
+        if response.status() == StatusCode::NOT_FOUND {
+            return Err(MarvelCdbError::NotFoundError(id));
+        }
+        let body = response.error_for_status()?.text().await?;
+
+        serde_json::from_str::<MarvelCdbDeck>(&body)
+            .map(|deck| Some(deck.into()))
+            .map_err(|_| MarvelCdbError::NotFoundError(id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deck_from_marvelcdb() {
+        let json = r#"{
+            "id": 40123,
+            "name": "Spidey Justice",
+            "hero_code": "01001a",
+            "slots": {"01002": 1, "01089": 3},
+            "meta": "{\"aspect\":\"justice\"}"
+        }"#;
+
+        let deck: Deck = serde_json::from_str::<MarvelCdbDeck>(json).unwrap().into();
+        assert_eq!(deck.name, Some("Spidey Justice".to_string()));
+        assert_eq!(deck.hero_code, Some("01001a".to_string()));
+        assert_eq!(deck.aspects, vec!["justice".to_string()]);
+        assert_eq!(deck.card_count(), 4);
+    }
+}
@@ -0,0 +1,13 @@
+pub mod collection;
+pub mod database;
+pub mod deck;
+pub mod fuzzy;
+pub mod linker;
+pub mod marvelcdb;
+pub mod odds;
+pub mod randomizer;
+pub mod types;
+pub mod validation;
+pub mod verification;
+
+pub use database::CardDatabase;
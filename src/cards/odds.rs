@@ -0,0 +1,302 @@
+use serde::Deserialize;
+use thiserror::Error;
+
+pub const DEFAULT_DECK_SIZE: u32 = 40;
+pub const DEFAULT_HAND_SIZE: u32 = 5;
+/// Largest deck size, hand size, number of turns, cards per turn or copies worked out.
+pub const MAX_VALUE: u32 = 60;
+pub const MAX_CATEGORIES: usize = 2;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum OddsError {
+    #[error("At least one kind of card is needed")]
+    NoCategories,
+    #[error("At most {MAX_CATEGORIES} kinds of cards are supported")]
+    TooManyCategories,
+    #[error("Deck size, hand size, turns and copies can be at most {MAX_VALUE}")]
+    TooLarge,
+    #[error("The hand size has to be between 1 and the deck size")]
+    InvalidHandSize,
+    #[error("The deck holds {0} cards, fewer than the copies asked about")]
+    TooManyCopies(u32),
+    #[error("Can't need more copies than there are in the deck")]
+    NeedMoreThanCopies,
+}
+
+/// A kind of card to draw: `need` of the deck's `copies`.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Category {
+    pub copies: u32,
+    #[serde(default = "default_need")]
+    pub need: u32,
+}
+
+fn default_need() -> u32 {
+    1
+}
+
+/// The odds of drawing every category by a given turn, drawing without replacement.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct DrawOdds {
+    #[serde(default = "default_deck_size")]
+    pub deck_size: u32,
+    #[serde(default = "default_hand_size")]
+    pub hand_size: u32,
+    pub categories: Vec<Category>,
+    /// Turns after the opening hand, each drawing back up to hand size.
+    #[serde(default)]
+    pub turns: u32,
+    /// Cards drawn each turn when redrawing to hand size, the whole hand by default.
+    pub cards_per_turn: Option<u32>,
+    /// Whether the opening hand is mulliganed: every card that isn't one of the categories is
+    /// discarded and redrawn once.
+    #[serde(default)]
+    pub mulligan: bool,
+}
+
+fn default_deck_size() -> u32 {
+    DEFAULT_DECK_SIZE
+}
+
+fn default_hand_size() -> u32 {
+    DEFAULT_HAND_SIZE
+}
+
+impl DrawOdds {
+    pub fn new(categories: Vec<Category>) -> Self {
+        Self {
+            deck_size: DEFAULT_DECK_SIZE,
+            hand_size: DEFAULT_HAND_SIZE,
+            categories,
+            turns: 0,
+            cards_per_turn: None,
+            mulligan: false,
+        }
+    }
+
+    /// Cards drawn after the opening hand.
+    pub fn later_draws(&self) -> u32 {
+        self.cards_per_turn
+            .unwrap_or(self.hand_size)
+            .saturating_mul(self.turns)
+    }
+
+    /// Cards drawn by the last turn, including the opening hand.
+    pub fn cards_seen(&self) -> u32 {
+        self.hand_size
+            .saturating_add(self.later_draws())
+            .min(self.deck_size)
+    }
+
+    /// The probability of having drawn at least `need` copies of every category.
+    pub fn probability(&self) -> Result<f64, OddsError> {
+        if self.categories.is_empty() {
+            return Err(OddsError::NoCategories);
+        }
+        if self.categories.len() > MAX_CATEGORIES {
+            return Err(OddsError::TooManyCategories);
+        }
+        if [self.deck_size, self.hand_size, self.turns]
+            .into_iter()
+            .chain(self.cards_per_turn)
+            .chain(self.categories.iter().map(|category| category.copies))
+            .any(|value| value > MAX_VALUE)
+        {
+            return Err(OddsError::TooLarge);
+        }
+        if self.hand_size == 0 || self.hand_size > self.deck_size {
+            return Err(OddsError::InvalidHandSize);
+        }
+        let copies: u32 = self.categories.iter().map(|category| category.copies).sum();
+        if copies > self.deck_size {
+            return Err(OddsError::TooManyCopies(self.deck_size));
+        }
+        if self
+            .categories
+            .iter()
+            .any(|category| category.need > category.copies)
+        {
+            return Err(OddsError::NeedMoreThanCopies);
+        }
+
+        if !self.mulligan {
+            return Ok(at_least(
+                self.deck_size,
+                &self.categories,
+                self.cards_seen(),
+            ));
+        }
+
+        // Keep every category card of the opening hand and redraw the rest.
+        let any = self
+            .categories
+            .iter()
+            .map(|category| Category {
+                need: 0,
+                ..*category
+            })
+            .collect::<Vec<_>>();
+        let remaining_deck = self.deck_size - self.hand_size;
+        let mut probability = 0.0;
+        visit(self.deck_size, &any, self.hand_size, |kept, p| {
+            let missing: Vec<Category> = self
+                .categories
+                .iter()
+                .zip(kept)
+                .map(|(category, kept)| Category {
+                    copies: category.copies - kept,
+                    need: category.need.saturating_sub(*kept),
+                })
+                .collect();
+            let redraw =
+                (self.hand_size - kept.iter().sum::<u32>()).saturating_add(self.later_draws());
+            probability += p * at_least(remaining_deck, &missing, redraw.min(remaining_deck));
+        });
+
+        Ok(probability)
+    }
+}
+
+/// The multivariate hypergeometric probability of drawing at least `need` of each category.
+pub fn at_least(deck_size: u32, categories: &[Category], draws: u32) -> f64 {
+    let mut probability = 0.0;
+    visit(deck_size, categories, draws, |_, p| probability += p);
+    probability
+}
+
+/// Calls `f` with every possible count of each category's cards among `draws` cards, that meets
+/// each category's `need`, and the probability of drawing exactly those counts.
+fn visit(deck_size: u32, categories: &[Category], draws: u32, mut f: impl FnMut(&[u32], f64)) {
+    let others = deck_size
+        - categories
+            .iter()
+            .map(|category| category.copies)
+            .sum::<u32>();
+    let total = choose(deck_size, draws);
+    let mut counts = Vec::with_capacity(categories.len());
+    visit_from(categories, draws, others, total, 1.0, &mut counts, &mut f);
+}
+
+fn visit_from(
+    categories: &[Category],
+    draws: u32,
+    others: u32,
+    total: f64,
+    ways: f64,
+    counts: &mut Vec<u32>,
+    f: &mut impl FnMut(&[u32], f64),
+) {
+    let Some((category, rest)) = categories.split_first() else {
+        f(counts, ways * choose(others, draws) / total);
+        return;
+    };
+
+    for count in category.need..=category.copies.min(draws) {
+        counts.push(count);
+        visit_from(
+            rest,
+            draws - count,
+            others,
+            total,
+            ways * choose(category.copies, count),
+            counts,
+            f,
+        );
+        counts.pop();
+    }
+}
+
+fn choose(n: u32, k: u32) -> f64 {
+    if k > n {
+        return 0.0;
+    }
+    let k = k.min(n - k);
+    (0..k).fold(1.0, |ways, i| ways * f64::from(n - i) / f64::from(i + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-4,
+            "expected {expected}, got {actual}"
+        );
+    }
+
+    #[test]
+    fn test_choose() {
+        assert_eq!(choose(40, 0), 1.0);
+        assert_eq!(choose(5, 2), 10.0);
+        assert_eq!(choose(3, 4), 0.0);
+        assert_eq!(choose(40, 6), 3_838_380.0);
+    }
+
+    #[test]
+    fn test_single_category() {
+        // 1 - C(37, 6) / C(40, 6)
+        let odds = DrawOdds {
+            hand_size: 6,
+            ..DrawOdds::new(vec![Category { copies: 3, need: 1 }])
+        };
+        assert_close(odds.probability().unwrap(), 0.3943);
+    }
+
+    #[test]
+    fn test_multiple_categories() {
+        let odds = DrawOdds::new(vec![
+            Category { copies: 3, need: 1 },
+            Category { copies: 2, need: 1 },
+        ]);
+        let both = odds.probability().unwrap();
+        let first = DrawOdds::new(vec![Category { copies: 3, need: 1 }])
+            .probability()
+            .unwrap();
+        assert!(both < first);
+        assert_close(both, 0.0681);
+    }
+
+    #[test]
+    fn test_mulligan_and_turns() {
+        let opening = DrawOdds::new(vec![Category { copies: 1, need: 1 }]);
+        assert_close(opening.probability().unwrap(), 5.0 / 40.0);
+
+        let mulligan = DrawOdds {
+            mulligan: true,
+            ..opening.clone()
+        };
+        assert_close(
+            mulligan.probability().unwrap(),
+            5.0 / 40.0 + 35.0 / 40.0 * 5.0 / 35.0,
+        );
+
+        let turns = DrawOdds {
+            turns: 2,
+            ..opening
+        };
+        assert_close(turns.probability().unwrap(), 15.0 / 40.0);
+    }
+
+    #[test]
+    fn test_invalid() {
+        let odds = DrawOdds::new(vec![Category { copies: 2, need: 3 }]);
+        assert_eq!(odds.probability(), Err(OddsError::NeedMoreThanCopies));
+        assert_eq!(
+            DrawOdds::new(Vec::new()).probability(),
+            Err(OddsError::NoCategories)
+        );
+
+        let endless = DrawOdds {
+            turns: u32::MAX,
+            cards_per_turn: Some(u32::MAX),
+            ..DrawOdds::new(vec![Category { copies: 1, need: 1 }])
+        };
+        assert_eq!(endless.later_draws(), u32::MAX);
+        assert_eq!(endless.probability(), Err(OddsError::TooLarge));
+        assert_eq!(
+            DrawOdds::new(vec![Category { copies: 1, need: 1 }; 3]).probability(),
+            Err(OddsError::TooManyCategories)
+        );
+    }
+}
@@ -0,0 +1,416 @@
+use rand::{Rng, seq::SliceRandom};
+use std::collections::{HashMap, HashSet, VecDeque};
+use thiserror::Error;
+
+use super::{
+    CardDatabase,
+    database::normalize_name,
+    types::{Card, CardSet},
+};
+
+pub const MAX_PLAYERS: usize = 4;
+pub const MAX_HEROIC_LEVEL: u32 = 4;
+const ASPECTS: [&str; 5] = ["aggression", "justice", "leadership", "protection", "pool"];
+
+#[derive(Error, Debug, PartialEq)]
+pub enum RandomizerError {
+    #[error("No villain matches the filters")]
+    NoVillain,
+    #[error("Only {0} modular sets match the filters")]
+    NotEnoughModulars(usize),
+    #[error("Only {0} heroes match the filters")]
+    NotEnoughHeroes(usize),
+    #[error("No {0} encounter set matches the filters")]
+    NoDifficultySet(Difficulty),
+    #[error("No aspect matches the filters")]
+    NoAspect,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Difficulty {
+    Standard,
+    Expert,
+}
+
+impl std::fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Difficulty::Standard => write!(f, "Standard"),
+            Difficulty::Expert => write!(f, "Expert"),
+        }
+    }
+}
+
+/// What to draw a setup from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RandomizerOptions {
+    pub players: usize,
+    pub modulars: usize,
+    /// Drawn at random when not set.
+    pub difficulty: Option<Difficulty>,
+    /// The heroic level is drawn from zero up to this.
+    pub max_heroic: u32,
+    /// Pack codes to draw from, or every pack when not set.
+    pub packs: Option<HashSet<String>>,
+    /// Names or codes of villains, sets, heroes and aspects to leave out.
+    pub exclude: Vec<String>,
+}
+
+impl Default for RandomizerOptions {
+    fn default() -> Self {
+        Self {
+            players: 1,
+            modulars: 1,
+            difficulty: None,
+            max_heroic: 0,
+            packs: None,
+            exclude: Vec::new(),
+        }
+    }
+}
+
+impl RandomizerOptions {
+    /// Only draws from these packs, on top of any packs already chosen.
+    pub fn restrict_to_packs(&mut self, packs: impl IntoIterator<Item = String>) {
+        let packs: HashSet<String> = packs.into_iter().collect();
+        self.packs = Some(match self.packs.take() {
+            Some(chosen) => chosen.intersection(&packs).cloned().collect(),
+            None => packs,
+        });
+    }
+}
+
+/// The options of the latest randomized setups by message id, so they can be rerolled. Older
+/// setups are forgotten.
+#[derive(Debug)]
+pub struct RecentSetups {
+    limit: usize,
+    order: VecDeque<u64>,
+    options: HashMap<u64, RandomizerOptions>,
+}
+
+impl RecentSetups {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            order: VecDeque::new(),
+            options: HashMap::new(),
+        }
+    }
+
+    pub fn insert(&mut self, message_id: u64, options: RandomizerOptions) {
+        if self.options.insert(message_id, options).is_some() {
+            return;
+        }
+        self.order.push_back(message_id);
+        if self.order.len() > self.limit
+            && let Some(oldest) = self.order.pop_front()
+        {
+            self.options.remove(&oldest);
+        }
+    }
+
+    pub fn get(&self, message_id: u64) -> Option<&RandomizerOptions> {
+        self.options.get(&message_id)
+    }
+}
+
+/// A randomized game setup.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Setup {
+    pub villain: CardSet,
+    pub modulars: Vec<CardSet>,
+    pub difficulty: Difficulty,
+    /// The standard, and for expert also the expert, encounter sets.
+    pub difficulty_sets: Vec<CardSet>,
+    pub heroic: u32,
+    /// Each player's hero and aspects.
+    pub heroes: Vec<(Card, Vec<String>)>,
+}
+
+/// Draws a villain scenario, modular sets, difficulty and a hero and aspect for each player.
+pub fn randomize(
+    database: &CardDatabase,
+    options: &RandomizerOptions,
+    rng: &mut impl Rng,
+) -> Result<Setup, RandomizerError> {
+    let excluded: HashSet<String> = options
+        .exclude
+        .iter()
+        .map(|name| normalize_name(name))
+        .collect();
+    let is_excluded = |code: &str, name: &str| {
+        excluded.contains(&normalize_name(code)) || excluded.contains(&normalize_name(name))
+    };
+    let is_owned = |pack_code: Option<&str>| match (&options.packs, pack_code) {
+        (Some(packs), Some(pack_code)) => packs.contains(pack_code),
+        (Some(_), None) => false,
+        (None, _) => true,
+    };
+    let sets_of_type = |set_type: &str| -> Vec<&CardSet> {
+        database
+            .sets()
+            .iter()
+            .filter(|set| set.card_set_type_code.as_deref() == Some(set_type))
+            .filter(|set| is_owned(set_pack(database, set)) && !is_excluded(&set.code, &set.name))
+            .collect()
+    };
+
+    let villain = *sets_of_type("villain")
+        .choose(rng)
+        .ok_or(RandomizerError::NoVillain)?;
+
+    let modulars = sets_of_type("modular");
+    if modulars.len() < options.modulars {
+        return Err(RandomizerError::NotEnoughModulars(modulars.len()));
+    }
+    let modulars = modulars
+        .choose_multiple(rng, options.modulars)
+        .map(|set| (*set).clone())
+        .collect();
+
+    let difficulty = options.difficulty.unwrap_or_else(|| {
+        if rng.gen_bool(0.5) {
+            Difficulty::Standard
+        } else {
+            Difficulty::Expert
+        }
+    });
+    let mut difficulty_sets = vec![
+        (*sets_of_type("standard")
+            .choose(rng)
+            .ok_or(RandomizerError::NoDifficultySet(Difficulty::Standard))?)
+        .clone(),
+    ];
+    if difficulty == Difficulty::Expert {
+        difficulty_sets.push(
+            (*sets_of_type("expert")
+                .choose(rng)
+                .ok_or(RandomizerError::NoDifficultySet(Difficulty::Expert))?)
+            .clone(),
+        );
+    }
+
+    let heroes: Vec<&Card> = database
+        .cards()
+        .filter(|card| card.type_code == "hero")
+        .filter(|card| is_owned(Some(&card.pack_code)) && !is_excluded(&card.code, &card.name))
+        .collect();
+    if heroes.len() < options.players {
+        return Err(RandomizerError::NotEnoughHeroes(heroes.len()));
+    }
+    let aspects: Vec<&str> = ASPECTS
+        .into_iter()
+        .filter(|aspect| !is_excluded(aspect, aspect))
+        .filter(|aspect| {
+            database
+                .cards_in_aspect(aspect)
+                .iter()
+                .any(|card| is_owned(Some(&card.pack_code)))
+        })
+        .collect();
+    let heroes = heroes
+        .choose_multiple(rng, options.players)
+        .map(|hero| {
+            let count = hero
+                .deck_requirements
+                .as_ref()
+                .and_then(|requirements| requirements.get("aspects"))
+                .and_then(serde_json::Value::as_u64)
+                .map_or(1, |count| count as usize);
+            let hero_aspects: Vec<String> = aspects
+                .choose_multiple(rng, count)
+                .map(|aspect| aspect.to_string())
+                .collect();
+            (!hero_aspects.is_empty())
+                .then(|| ((*hero).clone(), hero_aspects))
+                .ok_or(RandomizerError::NoAspect)
+        })
+        .collect::<Result<_, _>>()?;
+
+    Ok(Setup {
+        villain: villain.clone(),
+        modulars,
+        difficulty,
+        difficulty_sets,
+        heroic: rng.gen_range(0..=options.max_heroic.min(MAX_HEROIC_LEVEL)),
+        heroes,
+    })
+}
+
+/// The pack a set was released in, known from its cards.
+fn set_pack<'a>(database: &'a CardDatabase, set: &CardSet) -> Option<&'a str> {
+    database
+        .cards_in_set(&set.code)
+        .first()
+        .map(|card| card.pack_code.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{SeedableRng, rngs::StdRng};
+    use serde_json::json;
+
+    fn database() -> CardDatabase {
+        let card =
+            |code: &str, name: &str, type_code: &str, faction: &str, pack: &str, set: &str| {
+                json!({
+                    "code": code, "name": name, "type_code": type_code, "faction_code": faction,
+                    "pack_code": pack, "set_code": (!set.is_empty()).then_some(set)
+                })
+            };
+        let cards = json!([
+            card("01001a", "Spider-Man", "hero", "hero", "core", "spider_man"),
+            card(
+                "01010a",
+                "Captain Marvel",
+                "hero",
+                "hero",
+                "core",
+                "captain_marvel"
+            ),
+            card("01050", "Chase Them Down", "event", "justice", "core", ""),
+            card(
+                "01040",
+                "Relentless Assault",
+                "event",
+                "aggression",
+                "core",
+                ""
+            ),
+            card("01094", "Rhino", "villain", "encounter", "core", "rhino"),
+            card("01113", "Klaw", "villain", "encounter", "core", "klaw"),
+            card(
+                "01174",
+                "Bomb Scare",
+                "treachery",
+                "encounter",
+                "core",
+                "bomb_scare"
+            ),
+            card(
+                "01181",
+                "Masters of Evil",
+                "minion",
+                "encounter",
+                "core",
+                "masters_of_evil"
+            ),
+            card(
+                "01185",
+                "Advance",
+                "treachery",
+                "encounter",
+                "core",
+                "standard"
+            ),
+            card(
+                "01190",
+                "Exhaustion",
+                "treachery",
+                "encounter",
+                "core",
+                "expert"
+            ),
+            card(
+                "16001a",
+                "Spider-Woman",
+                "hero",
+                "hero",
+                "spider_woman",
+                "spider_woman"
+            )
+        ]);
+        let set = |code: &str, name: &str, set_type: &str| json!({ "code": code, "name": name, "card_set_type_code": set_type });
+        let sets = json!([
+            set("spider_man", "Spider-Man", "hero"),
+            set("captain_marvel", "Captain Marvel", "hero"),
+            set("spider_woman", "Spider-Woman", "hero"),
+            set("rhino", "Rhino", "villain"),
+            set("klaw", "Klaw", "villain"),
+            set("bomb_scare", "Bomb Scare", "modular"),
+            set("masters_of_evil", "Masters of Evil", "modular"),
+            set("standard", "Standard", "standard"),
+            set("expert", "Expert", "expert")
+        ]);
+
+        CardDatabase::new(
+            serde_json::from_value(cards).unwrap(),
+            Vec::new(),
+            serde_json::from_value(sets).unwrap(),
+            Vec::new(),
+            Vec::new(),
+        )
+    }
+
+    #[test]
+    fn test_randomize() {
+        let database = database();
+        let options = RandomizerOptions {
+            players: 2,
+            modulars: 2,
+            difficulty: Some(Difficulty::Expert),
+            max_heroic: 2,
+            ..Default::default()
+        };
+
+        let setup = randomize(&database, &options, &mut StdRng::seed_from_u64(7)).unwrap();
+        assert_eq!(setup.modulars.len(), 2);
+        assert_eq!(setup.difficulty_sets.len(), 2);
+        assert!(setup.heroic <= 2);
+        assert_eq!(setup.heroes.len(), 2);
+        assert_ne!(setup.heroes[0].0.code, setup.heroes[1].0.code);
+        for (_, aspects) in &setup.heroes {
+            assert_eq!(aspects.len(), 1);
+            assert!(["justice", "aggression"].contains(&aspects[0].as_str()));
+        }
+    }
+
+    #[test]
+    fn test_filters() {
+        let database = database();
+        let options = RandomizerOptions {
+            packs: Some(HashSet::from(["core".to_string()])),
+            exclude: vec![
+                "Rhino".to_string(),
+                "spider man".to_string(),
+                "justice".to_string(),
+            ],
+            ..Default::default()
+        };
+
+        for seed in 0..10 {
+            let setup = randomize(&database, &options, &mut StdRng::seed_from_u64(seed)).unwrap();
+            assert_eq!(setup.villain.code, "klaw");
+            assert_eq!(setup.heroes[0].0.code, "01010a");
+            assert_eq!(setup.heroes[0].1, vec!["aggression".to_string()]);
+        }
+    }
+
+    #[test]
+    fn test_not_enough() {
+        let database = database();
+        let options = RandomizerOptions {
+            modulars: 3,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            randomize(&database, &options, &mut StdRng::seed_from_u64(0)),
+            Err(RandomizerError::NotEnoughModulars(2))
+        );
+    }
+
+    #[test]
+    fn test_recent_setups() {
+        let mut setups = RecentSetups::new(2);
+        setups.insert(1, RandomizerOptions::default());
+        setups.insert(2, RandomizerOptions::default());
+        setups.insert(3, RandomizerOptions::default());
+
+        assert!(setups.get(1).is_none());
+        assert!(setups.get(2).is_some());
+        assert!(setups.get(3).is_some());
+    }
+}
@@ -0,0 +1,204 @@
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+
+/// A card as stored in MarvelCDB's JSON data.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct Card {
+    pub code: String,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subname: Option<String>,
+    pub type_code: String,
+    pub faction_code: String,
+    pub pack_code: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub set_code: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub position: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quantity: Option<u32>,
+    #[serde(
+        default,
+        deserialize_with = "deserialize_lenient_number",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub cost: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub traits: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resource_energy: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resource_mental: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resource_physical: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resource_wild: Option<u32>,
+    #[serde(
+        default,
+        deserialize_with = "deserialize_lenient_number",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub health: Option<i32>,
+    #[serde(
+        default,
+        deserialize_with = "deserialize_lenient_number",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub attack: Option<i32>,
+    #[serde(
+        default,
+        deserialize_with = "deserialize_lenient_number",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub thwart: Option<i32>,
+    #[serde(
+        default,
+        deserialize_with = "deserialize_lenient_number",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub defense: Option<i32>,
+    #[serde(
+        default,
+        deserialize_with = "deserialize_lenient_number",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub hand_size: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deck_limit: Option<u32>,
+    #[serde(default)]
+    pub is_unique: bool,
+    #[serde(default)]
+    pub permanent: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duplicate_of: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub back_link: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deck_requirements: Option<Value>,
+}
+
+impl Card {
+    /// The card's traits, e.g. `["Avenger", "Soldier"]` for `"Avenger. Soldier."`.
+    pub fn traits(&self) -> Vec<&str> {
+        self.traits
+            .as_deref()
+            .unwrap_or_default()
+            .split('.')
+            .map(str::trim)
+            .filter(|t| !t.is_empty())
+            .collect()
+    }
+
+    /// Whether this card is a reprint of another card.
+    pub fn is_duplicate(&self) -> bool {
+        self.duplicate_of.is_some()
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct Pack {
+    pub code: String,
+    pub name: String,
+    #[serde(default)]
+    pub position: Option<u32>,
+    #[serde(default)]
+    pub date_release: Option<String>,
+    #[serde(default)]
+    pub pack_type_code: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct CardSet {
+    pub code: String,
+    pub name: String,
+    #[serde(default)]
+    pub card_set_type_code: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct Faction {
+    pub code: String,
+    pub name: String,
+    #[serde(default)]
+    pub is_primary: bool,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct CardType {
+    pub code: String,
+    pub name: String,
+}
+
+/// Stats are numbers, but `X` and `*` values show up as strings or nulls in the data.
+fn deserialize_lenient_number<'de, D>(deserializer: D) -> Result<Option<i32>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value: Option<Value> = Option::deserialize(deserializer)?;
+
+    Ok(match value {
+        Some(Value::Number(number)) => number.as_i64().map(|n| n as i32),
+        Some(Value::String(text)) => text.parse().ok(),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_card_deserialization() {
+        let json_data = json!({
+            "code": "01001a",
+            "name": "Spider-Man",
+            "subname": "Peter Parker",
+            "type_code": "hero",
+            "faction_code": "hero",
+            "pack_code": "core",
+            "set_code": "spider_man",
+            "position": 1,
+            "quantity": 1,
+            "traits": "Avenger.",
+            "attack": 2,
+            "thwart": 1,
+            "defense": 3,
+            "health": 10,
+            "hand_size": 5,
+            "is_unique": true,
+            "back_link": "01001b",
+            "text": "<b>Spider-Sense</b> — <b>Interrupt</b>: When the villain initiates an attack against you, draw 1 card."
+        });
+
+        let card: Card = serde_json::from_value(json_data).unwrap();
+        assert_eq!(card.code, "01001a");
+        assert_eq!(card.subname, Some("Peter Parker".to_string()));
+        assert_eq!(card.health, Some(10));
+        assert_eq!(card.cost, None);
+        assert!(card.is_unique);
+        assert!(!card.is_duplicate());
+        assert_eq!(card.traits(), vec!["Avenger"]);
+    }
+
+    #[test]
+    fn test_card_lenient_numbers() {
+        let json_data = json!({
+            "code": "01050",
+            "name": "Power of Justice",
+            "type_code": "resource",
+            "faction_code": "justice",
+            "pack_code": "core",
+            "cost": "X",
+            "health": "3",
+            "traits": "Tech. Weapon. "
+        });
+
+        let card: Card = serde_json::from_value(json_data).unwrap();
+        assert_eq!(card.cost, None);
+        assert_eq!(card.health, Some(3));
+        assert_eq!(card.traits(), vec!["Tech", "Weapon"]);
+    }
+}
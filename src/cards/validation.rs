@@ -0,0 +1,380 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fmt};
+
+use super::{CardDatabase, database::normalize_name, deck::Deck, types::Card};
+
+pub const MIN_DECK_SIZE: u32 = 40;
+pub const MAX_DECK_SIZE: u32 = 50;
+const DEFAULT_DECK_LIMIT: u32 = 3;
+/// A deck may hold at most this many different restricted cards.
+const MAX_RESTRICTED_CARDS: usize = 1;
+/// Aspects a hero can build with; `pool` can't be combined with any other aspect.
+const ASPECTS: [&str; 5] = ["aggression", "justice", "leadership", "protection", "pool"];
+/// Card types that don't count towards the deck size.
+const UNCOUNTED_TYPES: [&str; 3] = ["hero", "alter_ego", "obligation"];
+
+/// Banned and restricted card codes, as published in the official ban list.
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+pub struct Legality {
+    #[serde(default)]
+    pub banned: Vec<String>,
+    #[serde(default)]
+    pub restricted: Vec<String>,
+}
+
+/// A rule the deck breaks.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Violation {
+    NoHero,
+    UnknownCard(String),
+    DeckSize(u32),
+    MissingSignature {
+        card: String,
+        needed: u32,
+        found: u32,
+    },
+    ForeignSignature(String),
+    TooManyAspects {
+        aspects: Vec<String>,
+        allowed: usize,
+    },
+    PoolWithOtherAspect,
+    OffAspect {
+        card: String,
+        aspect: String,
+    },
+    NotAPlayerCard(String),
+    OverLimit {
+        card: String,
+        limit: u32,
+        found: u32,
+    },
+    Banned(String),
+    TooManyRestricted(Vec<String>),
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::NoHero => write!(f, "The deck has no hero"),
+            Violation::UnknownCard(code) => write!(f, "Unknown card {code}"),
+            Violation::DeckSize(size) => write!(
+                f,
+                "The deck has {size} cards, it needs {MIN_DECK_SIZE} to {MAX_DECK_SIZE}"
+            ),
+            Violation::MissingSignature {
+                card,
+                needed,
+                found,
+            } => write!(
+                f,
+                "{card} is a signature card: {needed} copies needed, {found} found"
+            ),
+            Violation::ForeignSignature(card) => {
+                write!(f, "{card} is another hero's signature card")
+            }
+            Violation::TooManyAspects { aspects, allowed } => write!(
+                f,
+                "The deck uses {} aspects ({}), the hero allows {allowed}",
+                aspects.len(),
+                aspects.join(", ")
+            ),
+            Violation::PoolWithOtherAspect => {
+                write!(f, "Pool cards can't be combined with another aspect")
+            }
+            Violation::OffAspect { card, aspect } => {
+                write!(f, "{card} is a {aspect} card, outside the deck's aspects")
+            }
+            Violation::NotAPlayerCard(card) => write!(f, "{card} isn't a player card"),
+            Violation::OverLimit { card, limit, found } => {
+                write!(f, "{card} is limited to {limit} copies, {found} found")
+            }
+            Violation::Banned(card) => write!(f, "{card} is banned"),
+            Violation::TooManyRestricted(cards) => write!(
+                f,
+                "Only {MAX_RESTRICTED_CARDS} restricted card is allowed, found {}",
+                cards.join(", ")
+            ),
+        }
+    }
+}
+
+/// Checks a deck against the deckbuilding rules: deck size, signature cards, aspects, copy
+/// limits and the ban list. Returns every violation, so an empty list means the deck is legal.
+pub fn validate(deck: &Deck, database: &CardDatabase) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    let hero = deck.hero(database);
+
+    let mut cards = Vec::new();
+    for (code, quantity) in &deck.slots {
+        match database.card(code) {
+            // Reprints follow the rules of the card they reprint.
+            Some(card) => cards.push((original(card, database), *quantity)),
+            None => violations.push(Violation::UnknownCard(code.clone())),
+        }
+    }
+
+    let size: u32 = cards
+        .iter()
+        .filter(|(card, _)| !UNCOUNTED_TYPES.contains(&card.type_code.as_str()))
+        .map(|(_, quantity)| quantity)
+        .sum();
+    if !(MIN_DECK_SIZE..=MAX_DECK_SIZE).contains(&size) {
+        violations.push(Violation::DeckSize(size));
+    }
+
+    match hero {
+        Some(hero) => violations.extend(check_signature(hero, &cards, database)),
+        None => violations.push(Violation::NoHero),
+    }
+    violations.extend(check_aspects(deck, hero, &cards));
+    violations.extend(check_limits(&cards));
+    violations.extend(check_legality(&cards, database));
+
+    violations
+}
+
+fn original<'a>(card: &'a Card, database: &'a CardDatabase) -> &'a Card {
+    card.duplicate_of
+        .as_deref()
+        .and_then(|code| database.card(code))
+        .unwrap_or(card)
+}
+
+fn title(card: &Card) -> String {
+    match &card.subname {
+        Some(subname) => format!("{} ({subname})", card.name),
+        None => card.name.clone(),
+    }
+}
+
+fn check_signature(hero: &Card, cards: &[(&Card, u32)], database: &CardDatabase) -> Vec<Violation> {
+    let mut violations = Vec::new();
+
+    let signature: Vec<&Card> = hero
+        .set_code
+        .as_deref()
+        .map(|set_code| database.cards_in_set(set_code))
+        .unwrap_or_default()
+        .into_iter()
+        // The obligation shares the hero's set but is shuffled into the encounter deck.
+        .filter(|card| {
+            card.faction_code == "hero" && !matches!(card.type_code.as_str(), "hero" | "alter_ego")
+        })
+        .collect();
+    for card in &signature {
+        let needed = card.quantity.unwrap_or(1);
+        let found = cards
+            .iter()
+            .filter(|(deck_card, _)| deck_card.code == card.code)
+            .map(|(_, quantity)| quantity)
+            .sum();
+        if found != needed {
+            violations.push(Violation::MissingSignature {
+                card: title(card),
+                needed,
+                found,
+            });
+        }
+    }
+
+    for (card, _) in cards {
+        if card.faction_code == "hero" && card.set_code != hero.set_code {
+            violations.push(Violation::ForeignSignature(title(card)));
+        }
+    }
+
+    violations
+}
+
+fn check_aspects(deck: &Deck, hero: Option<&Card>, cards: &[(&Card, u32)]) -> Vec<Violation> {
+    let mut violations = Vec::new();
+
+    let allowed = hero
+        .and_then(|hero| hero.deck_requirements.as_ref())
+        .and_then(|requirements| requirements.get("aspects"))
+        .and_then(serde_json::Value::as_u64)
+        .map_or(1, |aspects| aspects as usize);
+    let mut aspects: Vec<String> = deck.aspects.clone();
+    for (card, _) in cards {
+        if ASPECTS.contains(&card.faction_code.as_str()) && !aspects.contains(&card.faction_code) {
+            aspects.push(card.faction_code.clone());
+        }
+    }
+
+    if aspects.len() > allowed {
+        violations.push(Violation::TooManyAspects {
+            aspects: aspects.clone(),
+            allowed,
+        });
+    }
+    if aspects.len() > 1 && aspects.iter().any(|aspect| aspect == "pool") {
+        violations.push(Violation::PoolWithOtherAspect);
+    }
+
+    for (card, _) in cards {
+        let faction = card.faction_code.as_str();
+        if ASPECTS.contains(&faction) {
+            if !deck.aspects.is_empty() && !deck.aspects.iter().any(|aspect| aspect == faction) {
+                violations.push(Violation::OffAspect {
+                    card: title(card),
+                    aspect: faction.to_string(),
+                });
+            }
+        } else if !matches!(faction, "basic" | "hero") && !is_obligation_of(card, hero) {
+            violations.push(Violation::NotAPlayerCard(title(card)));
+        }
+    }
+
+    violations
+}
+
+/// Deck lists may include the hero's obligation, although it isn't a player card.
+fn is_obligation_of(card: &Card, hero: Option<&Card>) -> bool {
+    card.type_code == "obligation" && hero.is_some_and(|hero| hero.set_code == card.set_code)
+}
+
+/// Copy limits apply per title, across every printing of a card.
+fn check_limits(cards: &[(&Card, u32)]) -> Vec<Violation> {
+    let mut by_title: BTreeMap<String, (&Card, u32)> = BTreeMap::new();
+    for (card, quantity) in cards {
+        let key = normalize_name(&title(card));
+        by_title.entry(key).or_insert((card, 0)).1 += quantity;
+    }
+
+    by_title
+        .into_values()
+        .filter(|(card, _)| card.faction_code != "hero")
+        .filter_map(|(card, found)| {
+            let limit = card.deck_limit.unwrap_or(DEFAULT_DECK_LIMIT);
+            (found > limit).then(|| Violation::OverLimit {
+                card: title(card),
+                limit,
+                found,
+            })
+        })
+        .collect()
+}
+
+fn check_legality(cards: &[(&Card, u32)], database: &CardDatabase) -> Vec<Violation> {
+    let legality = database.legality();
+    let mut violations: Vec<Violation> = cards
+        .iter()
+        .filter(|(card, _)| legality.banned.contains(&card.code))
+        .map(|(card, _)| Violation::Banned(title(card)))
+        .collect();
+
+    let restricted: Vec<String> = cards
+        .iter()
+        .filter(|(card, _)| legality.restricted.contains(&card.code))
+        .map(|(card, _)| title(card))
+        .collect();
+    if restricted.len() > MAX_RESTRICTED_CARDS {
+        violations.push(Violation::TooManyRestricted(restricted));
+    }
+
+    violations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cards::database::tests::test_database;
+
+    fn deck(slots: &[(&str, u32)]) -> Deck {
+        Deck {
+            hero_code: Some("01001a".to_string()),
+            aspects: vec!["justice".to_string()],
+            slots: slots
+                .iter()
+                .map(|(code, quantity)| (code.to_string(), *quantity))
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_reports_every_violation() {
+        let database = test_database();
+        let violations = validate(
+            &deck(&[("01089", 2), ("40089", 2), ("01094", 1)]),
+            &database,
+        );
+
+        assert!(violations.contains(&Violation::DeckSize(5)));
+        assert!(violations.contains(&Violation::MissingSignature {
+            card: "Black Cat".to_string(),
+            needed: 1,
+            found: 0
+        }));
+        assert!(violations.contains(&Violation::OverLimit {
+            card: "Haymaker".to_string(),
+            limit: 3,
+            found: 4
+        }));
+        assert!(violations.contains(&Violation::NotAPlayerCard("Rhino".to_string())));
+    }
+
+    #[test]
+    fn test_obligation_is_optional() {
+        let database = test_database();
+        let slots = [
+            ("01002", 1),
+            ("01009", 1),
+            ("01050", 3),
+            ("01089", 3),
+            ("01092", 1),
+        ];
+
+        assert_eq!(
+            validate(&deck(&slots), &database),
+            vec![Violation::DeckSize(8)]
+        );
+    }
+
+    #[test]
+    fn test_aspects() {
+        let database = test_database();
+        let mut deck = deck(&[("01002", 1), ("01050", 3)]);
+        deck.aspects = vec!["aggression".to_string()];
+
+        let violations = validate(&deck, &database);
+        assert!(violations.contains(&Violation::OffAspect {
+            card: "Chase Them Down".to_string(),
+            aspect: "justice".to_string()
+        }));
+        assert!(violations.contains(&Violation::TooManyAspects {
+            aspects: vec!["aggression".to_string(), "justice".to_string()],
+            allowed: 1
+        }));
+    }
+
+    #[test]
+    fn test_legality() {
+        let database = test_database().with_legality(Legality {
+            banned: vec!["01092".to_string()],
+            restricted: vec!["01050".to_string(), "01089".to_string()],
+        });
+
+        let violations = validate(
+            &deck(&[("01092", 1), ("01050", 1), ("01089", 1)]),
+            &database,
+        );
+        assert!(violations.contains(&Violation::Banned("Avengers Mansion".to_string())));
+        assert!(violations.contains(&Violation::TooManyRestricted(vec![
+            "Chase Them Down".to_string(),
+            "Haymaker".to_string()
+        ])));
+    }
+
+    #[test]
+    fn test_small_legal_deck_only_breaks_deck_size() {
+        let database = test_database();
+        let slots = [("01002", 1), ("01050", 3), ("01089", 3), ("01092", 1)];
+
+        assert_eq!(
+            validate(&deck(&slots), &database),
+            vec![Violation::DeckSize(8)]
+        );
+    }
+}
@@ -0,0 +1,348 @@
+use std::fmt;
+
+use super::{CardDatabase, linker::CardLinker, types::Card};
+
+/// Shorter quotes are more likely keywords or paraphrases than quoted card text.
+const MIN_QUOTE_WORDS: usize = 4;
+/// The share of a quote's word pairs that must appear in the card's text, so that symbols
+/// written out or small slips don't count as misquotes.
+const MIN_QUOTE_OVERLAP: f64 = 0.8;
+
+/// A printed stat an answer can claim for a card.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Stat {
+    Cost,
+    HitPoints,
+    Attack,
+    Thwart,
+    Defense,
+}
+
+impl Stat {
+    fn value(self, card: &Card) -> Option<i32> {
+        match self {
+            Stat::Cost => card.cost,
+            Stat::HitPoints => card.health,
+            Stat::Attack => card.attack,
+            Stat::Thwart => card.thwart,
+            Stat::Defense => card.defense,
+        }
+    }
+}
+
+impl fmt::Display for Stat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Stat::Cost => "cost",
+            Stat::HitPoints => "hit points",
+            Stat::Attack => "ATK",
+            Stat::Thwart => "THW",
+            Stat::Defense => "DEF",
+        })
+    }
+}
+
+/// Something an answer says about a card that its card data contradicts.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Mismatch {
+    /// A quote that isn't in the card's text.
+    Text {
+        code: String,
+        name: String,
+        quote: String,
+        official: String,
+    },
+    Stat {
+        code: String,
+        name: String,
+        stat: Stat,
+        claimed: i32,
+        official: i32,
+    },
+}
+
+/// Checks the card text quoted and the stats claimed in an answer against the card database.
+///
+/// Quotes are attributed to the card named last before them on their line, and block quotes to
+/// the card named last before the quote starts. Stats are only checked in sentences that name a
+/// single card, and a claim matches if any card of that name has it.
+pub fn verify(answer: &str, linker: &CardLinker, database: &CardDatabase) -> Vec<Mismatch> {
+    let mentions = linker.mentions(answer);
+    let mut mismatches: Vec<Mismatch> = Vec::new();
+    let mut push = |mismatch: Mismatch| {
+        if !mismatches.contains(&mismatch) {
+            mismatches.push(mismatch);
+        }
+    };
+
+    let mut offset = 0;
+    for line in answer.split_inclusive('\n') {
+        let start = offset;
+        offset += line.len();
+
+        let named_before = |position: usize| {
+            mentions
+                .iter()
+                .rev()
+                .find(|mention| mention.range.end <= position)
+        };
+        let quotes: Vec<(usize, &str)> = match line.trim_start().strip_prefix('>') {
+            Some(quote) => vec![(start, quote)],
+            None => inline_quotes(line)
+                .into_iter()
+                .map(|(position, quote)| (start + position, quote))
+                .collect(),
+        };
+        for (position, quote) in quotes {
+            let Some(mention) = named_before(position).filter(|mention| {
+                line.trim_start().starts_with('>') || mention.range.start >= start
+            }) else {
+                continue;
+            };
+            let cards = database.cards_named(mention.name);
+            if words(quote).len() >= MIN_QUOTE_WORDS
+                && !cards
+                    .iter()
+                    .any(|card| is_quoted(quote, card.text.as_deref().unwrap_or_default()))
+                && let Some(card) = cards.first()
+            {
+                push(Mismatch::Text {
+                    code: card.code.clone(),
+                    name: card.name.clone(),
+                    quote: quote.trim().to_string(),
+                    official: card.text.clone().unwrap_or_default(),
+                });
+            }
+        }
+
+        let mut sentence_start = 0;
+        for sentence in line.split_inclusive(['.', '!', '?']) {
+            let range = start + sentence_start..start + sentence_start + sentence.len();
+            sentence_start += sentence.len();
+
+            let mut named: Vec<&str> = mentions
+                .iter()
+                .filter(|mention| range.contains(&mention.range.start))
+                .map(|mention| mention.name)
+                .collect();
+            named.dedup();
+            let [name] = named.as_slice() else {
+                continue;
+            };
+            let cards = database.cards_named(name);
+            for (stat, claimed) in stat_claims(sentence) {
+                let values: Vec<i32> = cards.iter().filter_map(|card| stat.value(card)).collect();
+                if let Some(&official) = values.first()
+                    && !values.contains(&claimed)
+                {
+                    push(Mismatch::Stat {
+                        code: cards[0].code.clone(),
+                        name: cards[0].name.clone(),
+                        stat,
+                        claimed,
+                        official,
+                    });
+                }
+            }
+        }
+    }
+
+    mismatches
+}
+
+/// Text between straight or curly double quotes, with its byte position in the line.
+fn inline_quotes(line: &str) -> Vec<(usize, &str)> {
+    let mut quotes = Vec::new();
+    let mut open: Option<usize> = None;
+    for (position, c) in line.char_indices() {
+        match (c, open) {
+            ('"' | '“', None) => open = Some(position + c.len_utf8()),
+            ('"' | '”', Some(from)) => {
+                quotes.push((from, &line[from..position]));
+                open = None;
+            }
+            _ => {}
+        }
+    }
+
+    quotes
+}
+
+/// Stat values claimed in a sentence, like `costs 2`, `a 3-cost ally`, `10 hit points` or
+/// `ATK 2`.
+fn stat_claims(sentence: &str) -> Vec<(Stat, i32)> {
+    let tokens: Vec<String> = sentence
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|token| !token.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    let number = |index: usize| {
+        tokens
+            .get(index)
+            .and_then(|token| token.parse::<i32>().ok())
+    };
+    let token = |index: usize| tokens.get(index).map_or("", String::as_str);
+    // The number before the stat, unless it's what a character is down to in a game: `7 hit
+    // points left`, `is at 9 HP`.
+    let hit_points = |index: usize, end: usize| {
+        let value = index.checked_sub(1)?;
+        let current = matches!(token(end), "left" | "remaining")
+            || value
+                .checked_sub(1)
+                .is_some_and(|before| token(before) == "at");
+        number(value).filter(|_| !current)
+    };
+
+    let mut claims = Vec::new();
+    for index in 0..tokens.len() {
+        let claim = match token(index) {
+            "cost" | "costs" => {
+                let value = if token(index + 1) == "of" {
+                    index + 2
+                } else {
+                    index + 1
+                };
+                // `costs 1 less` and `a 1 cost reduction` aren't about the printed cost.
+                let after = number(value).filter(|_| !matches!(token(value + 1), "less" | "more"));
+                let before = index
+                    .checked_sub(1)
+                    .and_then(number)
+                    .filter(|_| token(index) == "cost" && token(index + 1) != "reduction");
+                after.or(before).map(|value| (Stat::Cost, value))
+            }
+            "hit" if token(index + 1) == "points" => {
+                hit_points(index, index + 2).map(|value| (Stat::HitPoints, value))
+            }
+            "hp" | "health" => hit_points(index, index + 1).map(|value| (Stat::HitPoints, value)),
+            "atk" | "thw" | "def" => {
+                let stat = match token(index) {
+                    "atk" => Stat::Attack,
+                    "thw" => Stat::Thwart,
+                    _ => Stat::Defense,
+                };
+                number(index + 1)
+                    .or_else(|| index.checked_sub(1).and_then(number))
+                    .map(|value| (stat, value))
+            }
+            _ => None,
+        };
+        claims.extend(claim);
+    }
+    claims.dedup();
+
+    claims
+}
+
+/// Whether most of the quote's word pairs appear in the card text.
+fn is_quoted(quote: &str, text: &str) -> bool {
+    let quote = words(quote);
+    let text = words(text);
+    let pairs: Vec<_> = quote.windows(2).collect();
+    let found = pairs
+        .iter()
+        .filter(|pair| text.windows(2).any(|other| other == **pair))
+        .count();
+
+    found as f64 >= pairs.len() as f64 * MIN_QUOTE_OVERLAP
+}
+
+/// The lowercase words of a text, without markup or resource symbols.
+fn words(text: &str) -> Vec<String> {
+    let text = text.replace("[[", "").replace("]]", "");
+    let mut plain = String::with_capacity(text.len());
+    let mut skipping: Option<char> = None;
+    for c in text.chars() {
+        match (c, skipping) {
+            ('<', None) => skipping = Some('>'),
+            ('[', None) => skipping = Some(']'),
+            (c, Some(end)) if c == end => {
+                skipping = None;
+                plain.push(' ');
+            }
+            (_, Some(_)) => {}
+            (c, None) if c.is_alphanumeric() => plain.extend(c.to_lowercase()),
+            (_, None) => plain.push(' '),
+        }
+    }
+
+    plain.split_whitespace().map(str::to_string).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cards::database::tests::test_database;
+    use serde_json::json;
+
+    fn database() -> CardDatabase {
+        let mut cards: Vec<Card> = test_database().cards().cloned().collect();
+        cards.push(
+            serde_json::from_value(json!({
+                "code": "01076", "name": "Swinging Web Kick", "type_code": "event",
+                "faction_code": "hero", "pack_code": "core", "set_code": "spider_man",
+                "cost": 3, "text": "<b>Hero Action</b> <i>(attack)</i>: Deal 8 damage to an enemy."
+            }))
+            .unwrap(),
+        );
+        CardDatabase::new(cards, Vec::new(), Vec::new(), Vec::new(), Vec::new())
+    }
+
+    #[test]
+    fn test_verify_quotes() {
+        let database = database();
+        let linker = CardLinker::new(&database);
+
+        let answer = "Swinging Web Kick reads \"Hero Action (attack): Deal 8 damage to an enemy.\"\n\
+                      Black Cat says \"Ready Black Cat after she discards a card\".";
+        assert_eq!(
+            verify(answer, &linker, &database),
+            vec![Mismatch::Text {
+                code: "01002".to_string(),
+                name: "Black Cat".to_string(),
+                quote: "Ready Black Cat after she discards a card".to_string(),
+                official: String::new(),
+            }]
+        );
+
+        let answer = "Play Swinging Web Kick:\n> Hero Action: Deal 10 damage to each enemy.";
+        assert!(matches!(
+            verify(answer, &linker, &database).as_slice(),
+            [Mismatch::Text { code, .. }] if code == "01076"
+        ));
+    }
+
+    #[test]
+    fn test_verify_stats() {
+        let database = database();
+        let linker = CardLinker::new(&database);
+
+        let answer = "Haymaker costs 3. Spider-Man has 10 hit points and Black Cat is no hero. \
+                      Black Cat is a 2-cost ally. Avengers Mansion costs 1 less.";
+        assert_eq!(
+            verify(answer, &linker, &database),
+            vec![Mismatch::Stat {
+                code: "01089".to_string(),
+                name: "Haymaker".to_string(),
+                stat: Stat::Cost,
+                claimed: 3,
+                official: 2,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_stat_claims() {
+        assert_eq!(
+            stat_claims("Rhino has 14 HP, ATK 2 and a 3-cost side scheme"),
+            vec![(Stat::HitPoints, 14), (Stat::Attack, 2), (Stat::Cost, 3)]
+        );
+        assert_eq!(
+            stat_claims("It costs 1 less and has a cost reduction"),
+            vec![]
+        );
+        assert_eq!(
+            stat_claims("Spider-Man has 7 hit points left and Rhino is at 9 HP"),
+            vec![]
+        );
+    }
+}
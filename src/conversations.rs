@@ -0,0 +1,177 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use crate::{feedback::Turn, heroku_mia::types::Message};
+
+/// A conversation's history, the Discord messages it was held in and the answers posted in them.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Conversation {
+    /// The user who started the conversation.
+    pub author: Option<u64>,
+    pub messages: Vec<Message>,
+    pub message_ids: HashSet<u64>,
+    pub turns: Vec<Arc<Turn>>,
+}
+
+/// Conversations by key, the id of the message or thread they started in, with an index from
+/// every message posted in them to their key, so a reply to any of them finds its conversation
+/// without walking the reply chain.
+#[derive(Debug, Default)]
+pub struct Conversations {
+    conversations: HashMap<u64, Conversation>,
+    index: HashMap<u64, u64>,
+}
+
+impl Conversations {
+    pub fn get(&self, key: u64) -> Option<&Conversation> {
+        self.conversations.get(&key)
+    }
+
+    pub fn contains(&self, key: u64) -> bool {
+        self.conversations.contains_key(&key)
+    }
+
+    /// The key of the conversation a message was posted in.
+    pub fn find(&self, message_id: u64) -> Option<u64> {
+        if self.contains(message_id) {
+            return Some(message_id);
+        }
+        self.index.get(&message_id).copied()
+    }
+
+    /// Starts a conversation by `author`, unless it was already started.
+    pub fn start(&mut self, key: u64, author: u64) {
+        self.conversations
+            .entry(key)
+            .or_insert_with(|| Conversation {
+                author: Some(author),
+                ..Conversation::default()
+            });
+    }
+
+    /// Replaces a conversation's history, starting the conversation if it is new.
+    pub fn set_messages(&mut self, key: u64, messages: Vec<Message>) {
+        self.conversations.entry(key).or_default().messages = messages;
+    }
+
+    /// Adds messages posted in a conversation to the index.
+    pub fn link(&mut self, key: u64, message_ids: impl IntoIterator<Item = u64>) {
+        let Some(conversation) = self.conversations.get_mut(&key) else {
+            return;
+        };
+        for message_id in message_ids {
+            conversation.message_ids.insert(message_id);
+            self.index.insert(message_id, key);
+        }
+    }
+
+    /// Keeps an answer posted in a conversation, so reactions to its messages can be traced back
+    /// to it.
+    pub fn add_turn(&mut self, key: u64, turn: Turn) {
+        let message_ids = turn.message_ids.clone();
+        let Some(conversation) = self.conversations.get_mut(&key) else {
+            return;
+        };
+        conversation.turns.push(Arc::new(turn));
+        self.link(key, message_ids);
+    }
+
+    /// The answer a message was posted in.
+    pub fn turn(&self, message_id: u64) -> Option<Arc<Turn>> {
+        self.conversations
+            .get(&self.find(message_id)?)?
+            .turns
+            .iter()
+            .rev()
+            .find(|turn| turn.message_ids.contains(&message_id))
+            .cloned()
+    }
+
+    pub fn remove(&mut self, key: u64) -> Option<Conversation> {
+        let conversation = self.conversations.remove(&key)?;
+        for message_id in &conversation.message_ids {
+            self.index.remove(message_id);
+        }
+
+        Some(conversation)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (u64, &Conversation)> {
+        self.conversations
+            .iter()
+            .map(|(key, conversation)| (*key, conversation))
+    }
+
+    pub fn len(&self) -> usize {
+        self.conversations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.conversations.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conversations() {
+        let mut conversations = Conversations::default();
+        conversations.link(1, [10]);
+        assert_eq!(conversations.find(10), None);
+
+        conversations.set_messages(
+            1,
+            vec![Message::User {
+                content: "Who is Black Cat?".to_string(),
+            }],
+        );
+        conversations.link(1, [10, 11]);
+        conversations.set_messages(1, Vec::new());
+        assert_eq!(conversations.find(1), Some(1));
+        assert_eq!(conversations.find(11), Some(1));
+        assert_eq!(conversations.find(12), None);
+
+        conversations.start(2, 7);
+        conversations.start(2, 8);
+        conversations.link(2, [20]);
+        assert_eq!(conversations.get(2).and_then(|c| c.author), Some(7));
+        assert_eq!(
+            conversations.remove(1).map(|c| c.message_ids.len()),
+            Some(2)
+        );
+        assert_eq!(conversations.find(10), None);
+        assert_eq!(conversations.find(20), Some(2));
+        assert_eq!(conversations.len(), 1);
+    }
+
+    #[test]
+    fn test_turns() {
+        let turn = |message_ids: Vec<u64>| Turn {
+            conversation_id: 1,
+            message_ids,
+            model_id: "claude-4-sonnet".to_string(),
+            prompt: Vec::new(),
+            response: Vec::new(),
+        };
+        let mut conversations = Conversations::default();
+        conversations.add_turn(1, turn(vec![10]));
+        assert_eq!(conversations.turn(10), None);
+
+        conversations.set_messages(1, Vec::new());
+        conversations.add_turn(1, turn(vec![10, 11]));
+        conversations.add_turn(1, turn(vec![12]));
+        assert_eq!(conversations.find(11), Some(1));
+        assert_eq!(
+            conversations.turn(11).map(|turn| turn.message_ids[0]),
+            Some(10)
+        );
+        assert_eq!(conversations.turn(1), None);
+
+        conversations.remove(1);
+        assert_eq!(conversations.turn(12), None);
+    }
+}
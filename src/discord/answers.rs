@@ -0,0 +1,301 @@
+use serenity::all::{
+    ButtonStyle, ChannelId, ComponentInteraction, Context, CreateActionRow, CreateButton,
+    CreateInteractionResponse, CreateInteractionResponseMessage, EditMessage, MessageId,
+};
+use std::{collections::HashMap, sync::Arc};
+use tokio::sync::Notify;
+
+use crate::{
+    discord::{
+        commands::{is_admin, query},
+        limits, queue,
+        streaming::ReplyTarget,
+        type_map_keys,
+    },
+    heroku_mia::types::Message as HerokuMiaMessage,
+};
+
+pub(crate) const ANSWER_PREFIX: &str = "answer:";
+const CONTINUE_PROMPT: &str = "Continue your answer from where it was cut off.";
+
+/// What a button under an answer asks for.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Action {
+    /// Answer the last prompt again.
+    Regenerate,
+    /// Carry on with an answer that was cut off.
+    Continue,
+    /// Cancel the answer being written.
+    Stop,
+}
+
+impl Action {
+    fn custom_id(self, conversation_key: u64) -> String {
+        let action = match self {
+            Action::Regenerate => "regenerate",
+            Action::Continue => "continue",
+            Action::Stop => "stop",
+        };
+        format!("{ANSWER_PREFIX}{action}:{conversation_key}")
+    }
+
+    fn parse(custom_id: &str) -> Option<(Self, u64)> {
+        let (action, conversation_key) = custom_id.strip_prefix(ANSWER_PREFIX)?.split_once(':')?;
+        let action = match action {
+            "regenerate" => Action::Regenerate,
+            "continue" => Action::Continue,
+            "stop" => Action::Stop,
+            _ => return None,
+        };
+        Some((action, conversation_key.parse().ok()?))
+    }
+}
+
+/// The answers being written, which Stop cancels, and the message each conversation's buttons
+/// are on, which is the only one whose buttons still work.
+#[derive(Debug, Default)]
+pub(crate) struct Answers {
+    writing: HashMap<u64, Arc<Notify>>,
+    buttons: HashMap<u64, (ChannelId, MessageId)>,
+}
+
+/// The Stop button shown while an answer is written.
+pub(crate) fn writing_buttons(conversation_key: u64) -> Vec<CreateActionRow> {
+    vec![CreateActionRow::Buttons(vec![
+        CreateButton::new(Action::Stop.custom_id(conversation_key))
+            .label("Stop")
+            .style(ButtonStyle::Danger),
+    ])]
+}
+
+/// The buttons shown under a finished answer. Continue is only offered if it was cut off.
+pub(crate) fn answered_buttons(conversation_key: u64, cut_off: bool) -> Vec<CreateActionRow> {
+    let mut buttons = vec![
+        CreateButton::new(Action::Regenerate.custom_id(conversation_key))
+            .label("Regenerate")
+            .style(ButtonStyle::Secondary),
+    ];
+    if cut_off {
+        buttons.push(
+            CreateButton::new(Action::Continue.custom_id(conversation_key))
+                .label("Continue")
+                .style(ButtonStyle::Primary),
+        );
+    }
+
+    vec![CreateActionRow::Buttons(buttons)]
+}
+
+/// Registers an answer being written and takes the buttons off the conversation's previous
+/// answer. The returned notification is signalled when Stop is pressed.
+pub(crate) async fn start(ctx: &Context, conversation_key: u64) -> Arc<Notify> {
+    let stop = Arc::new(Notify::new());
+    let previous = {
+        let answers = type_map_keys::Answers::get(&ctx.data).await;
+        let mut answers = answers.lock().await;
+        answers.writing.insert(conversation_key, Arc::clone(&stop));
+        answers.buttons.remove(&conversation_key)
+    };
+
+    if let Some((channel_id, message_id)) = previous
+        && let Err(e) = channel_id
+            .edit_message(ctx, message_id, EditMessage::new().components(Vec::new()))
+            .await
+    {
+        tracing::error!("Answer {conversation_key}: Error removing buttons: {:?}", e);
+    }
+
+    stop
+}
+
+/// Marks an answer as written, with its buttons on `buttons_on`.
+pub(crate) async fn finish(
+    ctx: &Context,
+    conversation_key: u64,
+    stop: &Arc<Notify>,
+    buttons_on: Option<(ChannelId, MessageId)>,
+) {
+    let answers = type_map_keys::Answers::get(&ctx.data).await;
+    let mut answers = answers.lock().await;
+    if answers
+        .writing
+        .get(&conversation_key)
+        .is_some_and(|writing| Arc::ptr_eq(writing, stop))
+    {
+        answers.writing.remove(&conversation_key);
+    }
+    if let Some(buttons_on) = buttons_on {
+        answers.buttons.insert(conversation_key, buttons_on);
+    }
+}
+
+pub(crate) async fn press(
+    ctx: &Context,
+    component: &ComponentInteraction,
+) -> Result<(), serenity::Error> {
+    let Some((action, conversation_key)) = Action::parse(&component.data.custom_id) else {
+        return component
+            .create_response(&ctx.http, CreateInteractionResponse::Acknowledge)
+            .await;
+    };
+
+    let author = type_map_keys::ConversationHistory::get(&ctx.data)
+        .await
+        .read()
+        .await
+        .get(conversation_key)
+        .and_then(|conversation| conversation.author);
+    if author.is_some_and(|author| author != component.user.id.get())
+        && !is_admin(ctx, component.member.as_ref()).await
+    {
+        return respond(
+            ctx,
+            component,
+            "Only whoever started this conversation can use its buttons.",
+        )
+        .await;
+    }
+
+    if action == Action::Stop {
+        if let Some(stop) = type_map_keys::Answers::get(&ctx.data)
+            .await
+            .lock()
+            .await
+            .writing
+            .get(&conversation_key)
+        {
+            tracing::info!(
+                "Answer {conversation_key}: Stopped by {}",
+                component.user.id
+            );
+            stop.notify_one();
+        }
+        return component
+            .create_response(&ctx.http, CreateInteractionResponse::Acknowledge)
+            .await;
+    }
+
+    let refusal = {
+        let answers = type_map_keys::Answers::get(&ctx.data).await;
+        let answers = answers.lock().await;
+        if answers.writing.contains_key(&conversation_key) {
+            Some("This conversation is still being answered.")
+        } else if answers
+            .buttons
+            .get(&conversation_key)
+            .is_none_or(|(_, message_id)| *message_id != component.message.id)
+        {
+            Some("Only the latest answer of a conversation can be regenerated or continued.")
+        } else {
+            None
+        }
+    };
+    if let Some(refusal) = refusal {
+        return respond(ctx, component, refusal).await;
+    }
+    if !limits::check_component(ctx, component).await? {
+        return Ok(());
+    }
+
+    // Take the buttons off the pressed answer, as the new one gets its own.
+    type_map_keys::Answers::get(&ctx.data)
+        .await
+        .lock()
+        .await
+        .buttons
+        .remove(&conversation_key);
+    component
+        .create_response(
+            &ctx.http,
+            CreateInteractionResponse::UpdateMessage(
+                CreateInteractionResponseMessage::new().components(Vec::new()),
+            ),
+        )
+        .await?;
+    tracing::info!(
+        "Answer {conversation_key}: {:?} pressed by {}",
+        action,
+        component.user.id
+    );
+
+    // The history is read once earlier turns are done, so the new turn builds on them.
+    let _turn = type_map_keys::ConversationLocks::get(&ctx.data)
+        .await
+        .lock(conversation_key)
+        .await;
+    let Some(mut conversation) = type_map_keys::ConversationHistory::get(&ctx.data)
+        .await
+        .read()
+        .await
+        .get(conversation_key)
+        .map(|conversation| conversation.messages.clone())
+    else {
+        tracing::info!("Answer {conversation_key}: Conversation was cleared");
+        return Ok(());
+    };
+    match action {
+        Action::Regenerate => {
+            let Some(prompt) = conversation
+                .iter()
+                .rposition(|message| matches!(message, HerokuMiaMessage::User { .. }))
+            else {
+                return Ok(());
+            };
+            conversation.truncate(prompt + 1);
+        }
+        _ => conversation.push(HerokuMiaMessage::User {
+            content: CONTINUE_PROMPT.to_string(),
+        }),
+    }
+
+    let _permit = match queue::start(ctx).await {
+        Ok(permit) => permit,
+        Err(ticket) => ticket.wait().await,
+    };
+    let target = if component.channel_id.get() == conversation_key {
+        ReplyTarget::Thread(component.channel_id)
+    } else {
+        ReplyTarget::Chain(component.message.clone())
+    };
+    query::respond(
+        ctx,
+        component.user.id,
+        conversation_key,
+        conversation,
+        target,
+    )
+    .await;
+
+    Ok(())
+}
+
+async fn respond(
+    ctx: &Context,
+    component: &ComponentInteraction,
+    content: &str,
+) -> Result<(), serenity::Error> {
+    component
+        .create_response(
+            &ctx.http,
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content(content)
+                    .ephemeral(true),
+            ),
+        )
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_action_custom_id() {
+        for action in [Action::Regenerate, Action::Continue, Action::Stop] {
+            assert_eq!(Action::parse(&action.custom_id(42)), Some((action, 42)));
+        }
+        assert_eq!(Action::parse("answer:stop:x"), None);
+        assert_eq!(Action::parse("game:villain:-1"), None);
+    }
+}
@@ -0,0 +1,410 @@
+use serde::Deserialize;
+use serde_json::Value;
+use serenity::{
+    all::{CreateAttachment, CreateEmbed, CreateEmbedFooter, CreateMessage},
+    constants::{EMBED_MAX_COUNT, EMBED_MAX_LENGTH},
+};
+use std::path::Path;
+
+use crate::{
+    cards::{CardDatabase, types::Card},
+    discord::markdown::truncate,
+    heroku_mia::types::Message as HerokuMiaMessage,
+};
+
+const MAX_DESCRIPTION_LENGTH: usize = 4096;
+const IMAGE_EXTENSIONS: [&str; 3] = ["png", "jpg", "webp"];
+
+/// The fields of a card shown in its embed, in the shape MarvelCDB's API and the card tools
+/// return them.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct CardDetails {
+    pub code: String,
+    pub name: String,
+    pub subname: Option<String>,
+    pub type_name: Option<String>,
+    pub faction_name: Option<String>,
+    pub cost: Option<Value>,
+    pub resource_energy: Option<u32>,
+    pub resource_mental: Option<u32>,
+    pub resource_physical: Option<u32>,
+    pub resource_wild: Option<u32>,
+    pub traits: Option<String>,
+    pub text: Option<String>,
+    pub pack_name: Option<String>,
+}
+
+impl CardDetails {
+    /// The details of a card from the local card database.
+    pub fn from_database(card: &Card, database: &CardDatabase) -> Option<Self> {
+        serde_json::from_value(database.card_json(card)).ok()
+    }
+
+    fn title(&self) -> String {
+        match &self.subname {
+            Some(subname) if !subname.is_empty() => format!("{} ({subname})", self.name),
+            _ => self.name.clone(),
+        }
+    }
+
+    fn resources(&self) -> Option<String> {
+        let resources: Vec<String> = [
+            (self.resource_energy, "⚡"),
+            (self.resource_mental, "🧠"),
+            (self.resource_physical, "👊"),
+            (self.resource_wild, "✳️"),
+        ]
+        .into_iter()
+        .filter_map(|(count, icon)| {
+            count
+                .filter(|count| *count > 0)
+                .map(|c| format!("{icon}×{c}"))
+        })
+        .collect();
+
+        (!resources.is_empty()).then(|| resources.join(" "))
+    }
+
+    fn fields(&self) -> Vec<(&'static str, String)> {
+        let cost = self.cost.as_ref().and_then(|cost| match cost {
+            Value::Null => None,
+            Value::String(cost) => Some(cost.clone()),
+            cost => Some(cost.to_string()),
+        });
+
+        [
+            ("Type", self.type_name.clone()),
+            ("Aspect", self.faction_name.clone()),
+            ("Cost", cost),
+            ("Resources", self.resources()),
+            (
+                "Traits",
+                self.traits.clone().filter(|traits| !traits.is_empty()),
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| value.map(|value| (name, value)))
+        .collect()
+    }
+
+    fn description(&self) -> String {
+        truncate(
+            &clean_card_text(self.text.as_deref().unwrap_or_default()),
+            MAX_DESCRIPTION_LENGTH,
+        )
+    }
+
+    /// The number of characters the embed counts towards Discord's per-message limit.
+    fn embed_length(&self) -> usize {
+        self.title().chars().count()
+            + self.description().chars().count()
+            + self
+                .pack_name
+                .as_deref()
+                .map_or(0, |pack| pack.chars().count())
+            + self
+                .fields()
+                .iter()
+                .map(|(name, value)| name.chars().count() + value.chars().count())
+                .sum::<usize>()
+    }
+
+    pub async fn embed(&self, image_dir: Option<&Path>) -> (CreateEmbed, Option<CreateAttachment>) {
+        let mut embed = CreateEmbed::new()
+            .title(self.title())
+            .url(format!("https://marvelcdb.com/card/{}", self.code))
+            .description(self.description())
+            .fields(
+                self.fields()
+                    .into_iter()
+                    .map(|(name, value)| (name, value, true)),
+            );
+        if let Some(pack_name) = &self.pack_name {
+            embed = embed.footer(CreateEmbedFooter::new(pack_name));
+        }
+
+        let image = match image_dir {
+            Some(image_dir) => card_image(image_dir, &self.code).await,
+            None => None,
+        };
+        if let Some(image) = &image {
+            embed = embed.thumbnail(format!("attachment://{}", image.filename));
+        }
+
+        (embed, image)
+    }
+}
+
+/// Collects the cards returned by tool calls in `messages`. Tool output is searched for card
+/// objects at any depth, including JSON nested in text content.
+pub(crate) fn cards_in_tool_results(messages: &[HerokuMiaMessage]) -> Vec<CardDetails> {
+    let mut cards = Vec::new();
+    for message in messages {
+        if let HerokuMiaMessage::Tool { content, .. } = message {
+            collect_cards(content, &mut cards);
+        }
+    }
+
+    cards
+}
+
+fn collect_cards(value: &Value, cards: &mut Vec<CardDetails>) {
+    match value {
+        Value::Object(object) => {
+            if object.contains_key("code")
+                && object.contains_key("name")
+                && object.contains_key("type_code")
+                && let Ok(card) = CardDetails::deserialize(value)
+            {
+                if !cards.iter().any(|c| c.code == card.code) {
+                    cards.push(card);
+                }
+                return;
+            }
+            object
+                .values()
+                .for_each(|value| collect_cards(value, cards));
+        }
+        Value::Array(values) => values.iter().for_each(|value| collect_cards(value, cards)),
+        Value::String(text) => {
+            if let Ok(value) = serde_json::from_str::<Value>(text)
+                && (value.is_object() || value.is_array())
+            {
+                collect_cards(&value, cards);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The cards whose names are mentioned in `answer`, in order of first mention.
+pub(crate) fn referenced_cards<'a>(answer: &str, cards: &'a [CardDetails]) -> Vec<&'a CardDetails> {
+    let answer = answer.to_lowercase();
+    let mut referenced: Vec<(usize, &CardDetails)> = cards
+        .iter()
+        .filter_map(|card| find_word(&answer, &card.name.to_lowercase()).map(|at| (at, card)))
+        .collect();
+    referenced.sort_by_key(|(at, _)| *at);
+
+    referenced.into_iter().map(|(_, card)| card).collect()
+}
+
+/// Finds `needle` in `haystack` where it isn't part of a longer word.
+fn find_word(haystack: &str, needle: &str) -> Option<usize> {
+    if needle.is_empty() {
+        return None;
+    }
+
+    haystack.match_indices(needle).map(|(at, _)| at).find(|at| {
+        let before = haystack[..*at].chars().next_back();
+        let after = haystack[at + needle.len()..].chars().next();
+        !before.is_some_and(char::is_alphanumeric) && !after.is_some_and(char::is_alphanumeric)
+    })
+}
+
+/// Builds messages holding an embed per card, split to stay within Discord's per-message
+/// embed count and length limits.
+pub(crate) async fn card_embed_messages(
+    cards: &[&CardDetails],
+    image_dir: Option<&Path>,
+) -> Vec<CreateMessage> {
+    let mut messages = Vec::new();
+    for batch in batches(cards) {
+        let mut message = CreateMessage::new();
+        let mut embeds = Vec::with_capacity(batch.len());
+        for card in batch {
+            let (embed, image) = card.embed(image_dir).await;
+            embeds.push(embed);
+            if let Some(image) = image {
+                message = message.add_file(image);
+            }
+        }
+        messages.push(message.embeds(embeds));
+    }
+
+    messages
+}
+
+fn batches<'a>(cards: &[&'a CardDetails]) -> Vec<Vec<&'a CardDetails>> {
+    let mut batches: Vec<Vec<&CardDetails>> = Vec::new();
+    let mut length = 0;
+
+    for card in cards {
+        let card_length = card.embed_length();
+        match batches.last_mut() {
+            Some(batch)
+                if batch.len() < EMBED_MAX_COUNT && length + card_length <= EMBED_MAX_LENGTH =>
+            {
+                batch.push(card);
+                length += card_length;
+            }
+            _ => {
+                batches.push(vec![card]);
+                length = card_length;
+            }
+        }
+    }
+
+    batches
+}
+
+async fn card_image(image_dir: &Path, code: &str) -> Option<CreateAttachment> {
+    for extension in IMAGE_EXTENSIONS {
+        let path = image_dir.join(format!("{code}.{extension}"));
+        if tokio::fs::try_exists(&path).await.unwrap_or(false) {
+            match CreateAttachment::path(&path).await {
+                Ok(attachment) => return Some(attachment),
+                Err(e) => tracing::error!("Error reading card image {:?}: {:?}", path, e),
+            }
+        }
+    }
+
+    None
+}
+
+/// Converts MarvelCDB card text markup to Discord Markdown.
+pub(crate) fn clean_card_text(text: &str) -> String {
+    let mut text = text
+        .replace("<b>", "**")
+        .replace("</b>", "**")
+        .replace("<i>", "*")
+        .replace("</i>", "*")
+        .replace("<br/>", "\n")
+        .replace("<br>", "\n")
+        .replace("[[", "***")
+        .replace("]]", "***");
+
+    for (symbol, replacement) in [
+        ("[energy]", "⚡"),
+        ("[mental]", "🧠"),
+        ("[physical]", "👊"),
+        ("[wild]", "✳️"),
+        ("[per_hero]", "(per player)"),
+        ("[star]", "★"),
+        ("[unique]", "◆"),
+        ("[boost]", "💥"),
+        ("[crisis]", "⚠️"),
+        ("[acceleration]", "⏩"),
+        ("[hazard]", "☣️"),
+        ("[amplify]", "🔊"),
+    ] {
+        text = text.replace(symbol, replacement);
+    }
+
+    strip_html_tags(&text)
+}
+
+fn strip_html_tags(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut in_tag = false;
+    for c in text.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            _ if !in_tag => stripped.push(c),
+            _ => {}
+        }
+    }
+
+    stripped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn card(code: &str, name: &str) -> CardDetails {
+        CardDetails {
+            code: code.to_string(),
+            name: name.to_string(),
+            subname: None,
+            type_name: Some("Event".to_string()),
+            faction_name: Some("Basic".to_string()),
+            cost: Some(json!(2)),
+            resource_energy: None,
+            resource_mental: None,
+            resource_physical: Some(1),
+            resource_wild: None,
+            traits: None,
+            text: None,
+            pack_name: Some("Core Set".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_cards_in_tool_results() {
+        let card_json = json!({
+            "code": "01089",
+            "name": "Haymaker",
+            "type_code": "event",
+            "type_name": "Event",
+            "cost": 2,
+            "resource_physical": 1,
+            "pack_name": "Core Set"
+        });
+        let messages = vec![
+            HerokuMiaMessage::User {
+                content: "What does Haymaker do?".to_string(),
+            },
+            HerokuMiaMessage::Tool {
+                content: json!([{ "type": "text", "text": card_json.to_string() }]),
+                tool_call_id: "call_1".to_string(),
+            },
+            HerokuMiaMessage::Tool {
+                content: json!({ "cards": [card_json] }),
+                tool_call_id: "call_2".to_string(),
+            },
+        ];
+
+        let cards = cards_in_tool_results(&messages);
+        assert_eq!(cards.len(), 1);
+        assert_eq!(cards[0].code, "01089");
+        assert_eq!(cards[0].resources(), Some("👊×1".to_string()));
+    }
+
+    #[test]
+    fn test_referenced_cards_in_order_of_mention() {
+        let cards = vec![
+            card("01089", "Haymaker"),
+            card("01088", "Enhanced Physique"),
+        ];
+        let referenced = referenced_cards("Play enhanced physique, then Haymaker.", &cards);
+        let codes: Vec<&str> = referenced.iter().map(|card| card.code.as_str()).collect();
+        assert_eq!(codes, vec!["01088", "01089"]);
+    }
+
+    #[test]
+    fn test_referenced_cards_match_whole_words() {
+        let cards = vec![card("01001", "Spider")];
+        assert!(referenced_cards("Spider-Man swings in", &cards).len() == 1);
+        assert!(referenced_cards("Spiders everywhere", &cards).is_empty());
+    }
+
+    #[test]
+    fn test_batches_respect_embed_count() {
+        let cards: Vec<CardDetails> = (0..12).map(|i| card(&i.to_string(), "Card")).collect();
+        let cards: Vec<&CardDetails> = cards.iter().collect();
+        let batches = batches(&cards);
+        assert_eq!(batches.len(), 2);
+        assert_eq!(batches[0].len(), EMBED_MAX_COUNT);
+    }
+
+    #[test]
+    fn test_batches_respect_embed_length() {
+        let mut long_card = card("01001", "Card");
+        long_card.text = Some("a".repeat(3500));
+        let cards = [&long_card, &long_card];
+        assert_eq!(batches(&cards).len(), 2);
+    }
+
+    #[test]
+    fn test_clean_card_text() {
+        assert_eq!(
+            clean_card_text(
+                "<b>Hero Action</b>: Deal 5 damage to an enemy. [[Aerial]] [energy]<hr/>"
+            ),
+            "**Hero Action**: Deal 5 damage to an enemy. ***Aerial*** ⚡"
+        );
+    }
+}
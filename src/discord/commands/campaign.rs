@@ -0,0 +1,345 @@
+use serenity::all::{
+    CommandInteraction, CommandOptionType, Context, CreateCommand, CreateCommandOption,
+    CreateEmbed, CreateInteractionResponse, CreateInteractionResponseMessage, ResolvedValue,
+};
+
+use crate::{
+    campaign::{CAMPAIGNS, CampaignError, CampaignLog, Entry},
+    discord::{
+        commands::{bool_option, string_option},
+        markdown::truncate,
+        type_map_keys,
+    },
+};
+
+const NO_CAMPAIGN: &str = "No campaign is being logged here. Start one with `/campaign start`.";
+const MAX_FIELD_LENGTH: usize = 1024;
+const MAX_EMBED_LENGTH: usize = 6000;
+/// Fields a long list may take up, so it leaves room for the lists after it.
+const MAX_FIELDS_PER_LIST: usize = 2;
+
+/// Reasons a campaign subcommand doesn't change the log.
+enum Outcome {
+    NoCampaign,
+    Invalid(CampaignError),
+}
+
+/// Creates, updates, shows or ends the campaign log of the channel or thread.
+pub async fn run(ctx: &Context, command: &CommandInteraction) -> Result<(), serenity::Error> {
+    let Some(subcommand) = command.data.options().into_iter().next() else {
+        return respond(ctx, command, message("Use a subcommand.", true)).await;
+    };
+    let options = match &subcommand.value {
+        ResolvedValue::SubCommand(options) => options.as_slice(),
+        _ => &[],
+    };
+    let string = |name: &str| string_option(options, name);
+    let boolean = |name: &str| bool_option(options, name);
+
+    let channel_id = command.channel_id.get();
+    let logs = type_map_keys::CampaignLogs::get(&ctx.data).await;
+
+    if subcommand.name == "view" {
+        let response = match logs.read().await.get(&channel_id) {
+            Some(log) => CreateInteractionResponseMessage::new().embed(log_embed(log)),
+            None => message(NO_CAMPAIGN, true),
+        };
+        return respond(ctx, command, response).await;
+    }
+
+    let result = logs
+        .update(|logs| -> Result<String, Outcome> {
+            if subcommand.name == "start" {
+                if let Some(log) = logs.get(&channel_id) {
+                    return Ok(format!(
+                        "A {} campaign is already logged here, end it with `/campaign end` first.",
+                        log.campaign
+                    ));
+                }
+                let heroes: Vec<&str> = string("heroes")
+                    .unwrap_or_default()
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|hero| !hero.is_empty())
+                    .collect();
+                let log = CampaignLog::new(
+                    string("campaign").unwrap_or_default(),
+                    boolean("expert"),
+                    &heroes,
+                );
+                let content = format!("Started logging {}.", log.campaign);
+                logs.insert(channel_id, log);
+                return Ok(content);
+            }
+            if subcommand.name == "end" {
+                return logs
+                    .remove(&channel_id)
+                    .map(|log| format!("Ended the {} campaign log.", log.campaign))
+                    .ok_or(Outcome::NoCampaign);
+            }
+
+            let log = logs.get_mut(&channel_id).ok_or(Outcome::NoCampaign)?;
+            let hero = string("hero");
+            let card = string("card").unwrap_or_default();
+            let entry = match subcommand.name {
+                "obligation" => Entry::Obligation,
+                _ => Entry::Upgrade,
+            };
+            match subcommand.name {
+                "hero" => {
+                    let name = hero.unwrap_or_default();
+                    log.add_hero(name).map_err(Outcome::Invalid)?;
+                    Ok(format!("Added {name} to the campaign."))
+                }
+                "obligation" | "upgrade" if boolean("remove") => {
+                    log.remove(entry, hero, card).map_err(Outcome::Invalid)?;
+                    Ok(format!("Removed {card}."))
+                }
+                "obligation" | "upgrade" => {
+                    log.add(entry, hero, card).map_err(Outcome::Invalid)?;
+                    Ok(format!("Recorded {card}."))
+                }
+                "result" => {
+                    let scenario = string("scenario").unwrap_or_default();
+                    let won = string("outcome") == Some("won");
+                    log.record_result(scenario, won, string("notes"));
+                    Ok(format!(
+                        "Recorded {scenario} as {}.",
+                        if won { "won" } else { "lost" }
+                    ))
+                }
+                _ => {
+                    let note = string("text").unwrap_or_default();
+                    log.notes.push(note.to_string());
+                    Ok("Added the note.".to_string())
+                }
+            }
+        })
+        .await;
+
+    let response = match result {
+        Ok(Ok(content)) => message(&content, false),
+        Ok(Err(Outcome::NoCampaign)) => message(NO_CAMPAIGN, true),
+        Ok(Err(Outcome::Invalid(e))) => message(&e.to_string(), true),
+        Err(e) => {
+            tracing::error!("Campaign: Error saving campaign logs: {:?}", e);
+            message("Could not save the campaign log.", true)
+        }
+    };
+
+    respond(ctx, command, response).await
+}
+
+fn log_embed(log: &CampaignLog) -> CreateEmbed {
+    let title = format!(
+        "{}{}",
+        log.campaign,
+        if log.expert { " (Expert)" } else { "" }
+    );
+    let mut fields = Fields::new(MAX_EMBED_LENGTH - title.chars().count());
+    for hero in &log.heroes {
+        fields.push(
+            hero.name.clone(),
+            &format!(
+                "**Obligations:** {}\n**Upgrades:** {}",
+                or_none(&hero.obligations),
+                or_none(&hero.upgrades)
+            ),
+            true,
+        );
+    }
+    if !log.upgrades.is_empty() {
+        fields.push("Team upgrades".to_string(), &log.upgrades.join(", "), false);
+    }
+    let results = log
+        .results
+        .iter()
+        .enumerate()
+        .map(|(number, result)| {
+            format!(
+                "{}. {} {}{}",
+                number + 1,
+                if result.won { "✅" } else { "❌" },
+                result.scenario,
+                result
+                    .notes
+                    .as_ref()
+                    .map(|notes| format!(" · {notes}"))
+                    .unwrap_or_default()
+            )
+        })
+        .collect::<Vec<_>>();
+    if results.is_empty() {
+        fields.push("Scenarios".to_string(), "None played yet", false);
+    } else {
+        fields.push_list("Scenarios", &results);
+    }
+    if !log.notes.is_empty() {
+        fields.push_list("Notes", &log.notes);
+    }
+
+    CreateEmbed::new().title(title).fields(fields.fields)
+}
+
+/// An embed's fields, cut to fit what is left of Discord's 6000 characters per embed.
+struct Fields {
+    fields: Vec<(String, String, bool)>,
+    remaining: usize,
+}
+
+impl Fields {
+    fn new(remaining: usize) -> Self {
+        Self {
+            fields: Vec::new(),
+            remaining,
+        }
+    }
+
+    /// Adds a field, shortening its value to fit. It is left out if there's no room at all.
+    fn push(&mut self, name: String, value: &str, inline: bool) {
+        let room = self
+            .remaining
+            .saturating_sub(name.chars().count())
+            .min(MAX_FIELD_LENGTH);
+        if room == 0 {
+            return;
+        }
+
+        let value = truncate(value, room);
+        self.remaining -= name.chars().count() + value.chars().count();
+        self.fields.push((name, value, inline));
+    }
+
+    /// Splits a list across fields of at most [`MAX_FIELD_LENGTH`] characters. A list too long
+    /// for [`MAX_FIELDS_PER_LIST`] fields, or for the room left, shows its latest entries.
+    fn push_list(&mut self, name: &str, lines: &[String]) {
+        let continued = format!("{name} (cont.)");
+        // Counted as if every field had the longer name, so the list surely fits.
+        let name_length = continued.chars().count();
+        let mut remaining = self.remaining;
+        let mut chunks: Vec<String> = Vec::new();
+        for line in lines.iter().rev() {
+            let fits = chunks.last().is_some_and(|chunk| {
+                let length = line.chars().count() + 1;
+                chunk.chars().count() + length <= MAX_FIELD_LENGTH && length <= remaining
+            });
+            if let Some(chunk) = chunks.last_mut().filter(|_| fits) {
+                remaining -= line.chars().count() + 1;
+                *chunk = format!("{line}\n{chunk}");
+                continue;
+            }
+
+            let room = remaining.saturating_sub(name_length).min(MAX_FIELD_LENGTH);
+            // Only the latest entry is shortened to fit, the others are left out whole.
+            let partial = !chunks.is_empty() && line.chars().count() > room;
+            if chunks.len() == MAX_FIELDS_PER_LIST || room == 0 || partial {
+                break;
+            }
+            let line = truncate(line, room);
+            remaining -= name_length + line.chars().count();
+            chunks.push(line);
+        }
+
+        for (index, chunk) in chunks.into_iter().rev().enumerate() {
+            let name = if index == 0 {
+                name.to_string()
+            } else {
+                continued.clone()
+            };
+            self.remaining -= name.chars().count() + chunk.chars().count();
+            self.fields.push((name, chunk, false));
+        }
+    }
+}
+
+fn or_none(items: &[String]) -> String {
+    if items.is_empty() {
+        "None".to_string()
+    } else {
+        items.join(", ")
+    }
+}
+
+fn message(content: &str, ephemeral: bool) -> CreateInteractionResponseMessage {
+    CreateInteractionResponseMessage::new()
+        .content(content)
+        .ephemeral(ephemeral)
+}
+
+async fn respond(
+    ctx: &Context,
+    command: &CommandInteraction,
+    message: CreateInteractionResponseMessage,
+) -> Result<(), serenity::Error> {
+    command
+        .create_response(&ctx.http, CreateInteractionResponse::Message(message))
+        .await
+}
+
+pub fn register() -> CreateCommand {
+    let string = |name: &str, description: &str| {
+        CreateCommandOption::new(CommandOptionType::String, name, description)
+    };
+    let subcommand = |name: &str, description: &str| {
+        CreateCommandOption::new(CommandOptionType::SubCommand, name, description)
+    };
+
+    let mut campaign = string("campaign", "Campaign box").required(true);
+    for (code, name) in CAMPAIGNS {
+        campaign = campaign.add_string_choice(name, code);
+    }
+
+    CreateCommand::new("campaign")
+        .description("Keep the campaign log of this channel or thread")
+        .add_option(
+            subcommand("start", "Start logging a campaign")
+                .add_sub_option(campaign)
+                .add_sub_option(string("heroes", "Comma-separated heroes"))
+                .add_sub_option(CreateCommandOption::new(
+                    CommandOptionType::Boolean,
+                    "expert",
+                    "Expert campaign mode",
+                )),
+        )
+        .add_option(
+            subcommand("hero", "Add a hero to the campaign")
+                .add_sub_option(string("hero", "Hero name").required(true)),
+        )
+        .add_option(
+            subcommand("obligation", "Record or remove a hero's obligation")
+                .add_sub_option(string("hero", "Hero name").required(true))
+                .add_sub_option(string("card", "Obligation").required(true))
+                .add_sub_option(CreateCommandOption::new(
+                    CommandOptionType::Boolean,
+                    "remove",
+                    "Remove the obligation instead",
+                )),
+        )
+        .add_option(
+            subcommand("upgrade", "Record or remove an upgrade")
+                .add_sub_option(string("card", "Upgrade").required(true))
+                .add_sub_option(string("hero", "Hero name, or leave out for the team"))
+                .add_sub_option(CreateCommandOption::new(
+                    CommandOptionType::Boolean,
+                    "remove",
+                    "Remove the upgrade instead",
+                )),
+        )
+        .add_option(
+            subcommand("result", "Record a scenario result")
+                .add_sub_option(string("scenario", "Scenario").required(true))
+                .add_sub_option(
+                    string("outcome", "Outcome")
+                        .required(true)
+                        .add_string_choice("Won", "won")
+                        .add_string_choice("Lost", "lost"),
+                )
+                .add_sub_option(string("notes", "Rewards, allies gained and other notes")),
+        )
+        .add_option(
+            subcommand("note", "Add a note to the log")
+                .add_sub_option(string("text", "Note").required(true)),
+        )
+        .add_option(subcommand("view", "Show the campaign log"))
+        .add_option(subcommand("end", "Stop logging the campaign"))
+}
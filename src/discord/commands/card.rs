@@ -0,0 +1,96 @@
+use serenity::all::{
+    CommandInteraction, CommandOptionType, Context, CreateAutocompleteResponse, CreateCommand,
+    CreateCommandOption, CreateInteractionResponse, CreateInteractionResponseMessage,
+};
+
+use crate::{
+    cards::{CardDatabase, types::Card},
+    discord::{card_embed::CardDetails, markdown::truncate, type_map_keys},
+};
+
+const MAX_AUTOCOMPLETE_CHOICES: usize = 25;
+const MAX_CHOICE_NAME_LENGTH: usize = 100;
+
+/// Answers with the card's embed straight from the local card database, without an LLM call.
+pub async fn run(
+    ctx: &Context,
+    command: &CommandInteraction,
+    name: &str,
+) -> Result<(), serenity::Error> {
+    let database = type_map_keys::CardDatabase::get(&ctx.data).await;
+
+    // Autocomplete choices carry the card code, anything typed by hand is matched by name.
+    let card = database
+        .card(name)
+        .or_else(|| database.fuzzy_search(name, 1).into_iter().next());
+    let details = card.and_then(|card| CardDetails::from_database(card, &database));
+
+    let response = match details {
+        Some(details) => {
+            let image_dir = type_map_keys::CardImageDir::get(&ctx.data).await;
+            let (embed, image) = details.embed(image_dir.as_deref()).await;
+            let mut message = CreateInteractionResponseMessage::new().embed(embed);
+            if let Some(image) = image {
+                message = message.add_file(image);
+            }
+            message
+        }
+        None => CreateInteractionResponseMessage::new()
+            .content(format!("No card matches \"{name}\"."))
+            .ephemeral(true),
+    };
+
+    command
+        .create_response(&ctx.http, CreateInteractionResponse::Message(response))
+        .await
+}
+
+pub async fn autocomplete(
+    ctx: &Context,
+    interaction: &CommandInteraction,
+) -> Result<(), serenity::Error> {
+    let query = interaction
+        .data
+        .autocomplete()
+        .map(|option| option.value)
+        .unwrap_or_default();
+    let database = type_map_keys::CardDatabase::get(&ctx.data).await;
+
+    let mut response = CreateAutocompleteResponse::new();
+    if !query.trim().is_empty() {
+        for card in database.fuzzy_search(query, MAX_AUTOCOMPLETE_CHOICES) {
+            response = response.add_string_choice(choice_name(card, &database), &card.code);
+        }
+    }
+
+    interaction
+        .create_response(&ctx.http, CreateInteractionResponse::Autocomplete(response))
+        .await
+}
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("card")
+        .description("Look up a Marvel Champions card")
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::String, "name", "Card name")
+                .required(true)
+                .set_autocomplete(true),
+        )
+}
+
+/// Labels a choice with what tells cards of the same name apart, e.g.
+/// "Black Widow (Natasha Romanoff) · Hero · Black Widow".
+fn choice_name(card: &Card, database: &CardDatabase) -> String {
+    let mut name = match &card.subname {
+        Some(subname) => format!("{} ({subname})", card.name),
+        None => card.name.clone(),
+    };
+    if let Some(card_type) = database.card_type(&card.type_code) {
+        name.push_str(&format!(" · {}", card_type.name));
+    }
+    if let Some(pack) = database.pack(&card.pack_code) {
+        name.push_str(&format!(" · {}", pack.name));
+    }
+
+    truncate(&name, MAX_CHOICE_NAME_LENGTH)
+}
@@ -0,0 +1,201 @@
+use serenity::all::{
+    CommandInteraction, CommandOptionType, Context, CreateAutocompleteResponse, CreateCommand,
+    CreateCommandOption, CreateInteractionResponse, CreateInteractionResponseMessage,
+    ResolvedValue,
+};
+
+use crate::{
+    cards::{CardDatabase, collection::pack_names, database::normalize_name, fuzzy},
+    discord::type_map_keys,
+};
+
+const MAX_AUTOCOMPLETE_CHOICES: usize = 25;
+/// Autocomplete drops packs scoring below this.
+const MIN_PACK_SCORE: f64 = 0.5;
+
+/// Adds packs to, removes packs from, or lists the calling user's collection.
+pub async fn run(ctx: &Context, command: &CommandInteraction) -> Result<(), serenity::Error> {
+    let Some(subcommand) = command.data.options().into_iter().next() else {
+        return respond(ctx, command, "Use add, remove or list.".to_string()).await;
+    };
+    let packs = match &subcommand.value {
+        ResolvedValue::SubCommand(options) => options
+            .iter()
+            .find(|option| option.name == "pack")
+            .and_then(|option| match option.value {
+                ResolvedValue::String(packs) => Some(packs),
+                _ => None,
+            })
+            .unwrap_or_default(),
+        _ => "",
+    };
+
+    let database = type_map_keys::CardDatabase::get(&ctx.data).await;
+    let collections = type_map_keys::Collections::get(&ctx.data).await;
+    let user_id = command.user.id.get();
+    let (codes, unknown) = pack_codes(packs, &database);
+
+    let content = match subcommand.name {
+        "add" | "remove" => {
+            let adding = subcommand.name == "add";
+            let result = collections
+                .update(|collections| {
+                    let owned = collections.entry(user_id).or_default();
+                    for code in &codes {
+                        if adding {
+                            owned.insert(code.clone());
+                        } else {
+                            owned.remove(code);
+                        }
+                    }
+                })
+                .await;
+            match result {
+                Ok(()) => {
+                    let mut content = match (codes.is_empty(), adding) {
+                        (true, _) => "No packs changed.".to_string(),
+                        (false, true) => {
+                            format!(
+                                "Added {} to your collection.",
+                                pack_names(&codes, &database)
+                            )
+                        }
+                        (false, false) => format!(
+                            "Removed {} from your collection.",
+                            pack_names(&codes, &database)
+                        ),
+                    };
+                    if !unknown.is_empty() {
+                        content.push_str(&format!("\nUnknown packs: {}", unknown.join(", ")));
+                    }
+                    content
+                }
+                Err(e) => {
+                    tracing::error!("Collection: Error saving collections: {:?}", e);
+                    "Could not save your collection.".to_string()
+                }
+            }
+        }
+        _ => {
+            let collections = collections.read().await;
+            match collections.get(&user_id).filter(|owned| !owned.is_empty()) {
+                Some(owned) => format!(
+                    "Your collection ({} packs): {}",
+                    owned.len(),
+                    pack_names(owned, &database)
+                ),
+                None => "Your collection is empty. Add packs with `/collection add`.".to_string(),
+            }
+        }
+    };
+
+    respond(ctx, command, content).await
+}
+
+pub async fn autocomplete(
+    ctx: &Context,
+    interaction: &CommandInteraction,
+) -> Result<(), serenity::Error> {
+    let query = interaction
+        .data
+        .autocomplete()
+        .map(|option| option.value)
+        .unwrap_or_default();
+    let database = type_map_keys::CardDatabase::get(&ctx.data).await;
+    let query = normalize_name(query);
+
+    let mut packs: Vec<(f64, &str, &str)> = database
+        .packs()
+        .iter()
+        .map(|pack| {
+            let score = if query.trim().is_empty() {
+                1.0
+            } else {
+                fuzzy::score(&query, &normalize_name(&pack.name))
+            };
+            (score, pack.name.as_str(), pack.code.as_str())
+        })
+        .filter(|(score, _, _)| *score >= MIN_PACK_SCORE)
+        .collect();
+    packs.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    let mut response = CreateAutocompleteResponse::new();
+    for (_, name, code) in packs.into_iter().take(MAX_AUTOCOMPLETE_CHOICES) {
+        response = response.add_string_choice(name, code);
+    }
+
+    interaction
+        .create_response(&ctx.http, CreateInteractionResponse::Autocomplete(response))
+        .await
+}
+
+async fn respond(
+    ctx: &Context,
+    command: &CommandInteraction,
+    content: String,
+) -> Result<(), serenity::Error> {
+    command
+        .create_response(
+            &ctx.http,
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content(content)
+                    .ephemeral(true),
+            ),
+        )
+        .await
+}
+
+/// Resolves comma-separated pack codes or names, returning the codes and the unknown packs.
+fn pack_codes(packs: &str, database: &CardDatabase) -> (Vec<String>, Vec<String>) {
+    let mut codes = Vec::new();
+    let mut unknown = Vec::new();
+    for pack in packs
+        .split(',')
+        .map(str::trim)
+        .filter(|pack| !pack.is_empty())
+    {
+        match database.find_pack(pack) {
+            Some(known) => codes.push(known.code.clone()),
+            None => unknown.push(pack.to_string()),
+        }
+    }
+
+    (codes, unknown)
+}
+
+pub fn register() -> CreateCommand {
+    let pack_option = || {
+        CreateCommandOption::new(
+            CommandOptionType::String,
+            "pack",
+            "Pack name, or several separated by commas",
+        )
+        .required(true)
+        .set_autocomplete(true)
+    };
+
+    CreateCommand::new("collection")
+        .description("Track the packs you own")
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "add",
+                "Add packs to your collection",
+            )
+            .add_sub_option(pack_option()),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "remove",
+                "Remove packs from your collection",
+            )
+            .add_sub_option(pack_option()),
+        )
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "list",
+            "List the packs in your collection",
+        ))
+}
@@ -0,0 +1,143 @@
+use serenity::all::{
+    CommandInteraction, CommandOptionType, Context, CreateCommand, CreateCommandOption,
+    EditInteractionResponse,
+};
+
+use crate::{
+    cards::{
+        deck::{Deck, DeckReference},
+        marvelcdb::MarvelCdbError,
+    },
+    discord::{commands::query, limits, personas, queue, streaming::ReplyTarget, type_map_keys},
+    heroku_mia::types::Message as HerokuMiaMessage,
+};
+
+const ANALYSIS_PROMPT: &str = "Analyze my deck. Use a short heading for each of: cost curve, resource distribution, aspect cards, signature cards, strengths and weaknesses, and suggested swaps. Refer to cards by name.";
+
+/// Imports a deck from MarvelCDB or a pasted list and starts a conversation analyzing it. The
+/// deck is kept in the system prompt so replies can keep asking about it.
+pub async fn run(
+    ctx: &Context,
+    command: &CommandInteraction,
+    deck: &str,
+    question: Option<&str>,
+) -> Result<(), serenity::Error> {
+    if !limits::check_command(ctx, command).await? {
+        return Ok(());
+    }
+    // Fetching from MarvelCDB can take longer than Discord waits for a response.
+    command.defer(&ctx.http).await?;
+
+    let deck = match import(ctx, deck).await {
+        Ok(deck) if deck.card_count() > 0 => deck,
+        Ok(_) => {
+            return edit_content(ctx, command, "No cards found in that deck.").await;
+        }
+        Err(e) => {
+            tracing::error!("Deck: Error importing {deck:?}: {e:?}");
+            return edit_content(ctx, command, &format!("Could not import the deck: {e}")).await;
+        }
+    };
+
+    let database = type_map_keys::CardDatabase::get(&ctx.data).await;
+    let hero = deck
+        .hero(&database)
+        .map_or("Unknown hero", |hero| hero.name.as_str());
+    let mut summary = format!(
+        "Analyzing **{}** · {hero} · {} · {} cards. Reply to this message to continue.",
+        deck.name.as_deref().unwrap_or("Untitled deck"),
+        if deck.aspects.is_empty() {
+            "no aspect".to_string()
+        } else {
+            deck.aspects.join(" & ")
+        },
+        deck.card_count()
+    );
+    if !deck.unresolved.is_empty() {
+        summary.push_str(&format!("\nCould not find: {}", deck.unresolved.join(", ")));
+    }
+    let start = queue::start(ctx).await;
+    edit_content(ctx, command, &queue::with_position(&summary, &start)).await?;
+    let _permit = queue::wait_for_command(ctx, command, start, &summary).await?;
+    let response = command.get_response(&ctx.http).await?;
+
+    let mut conversation = query::bootstrap_messages(
+        &personas::agent_settings(ctx, command.channel_id)
+            .await
+            .system_prompt,
+    );
+    query::add_system_context(
+        &mut conversation,
+        &format!(
+            "The user is asking about this deck:\n{}",
+            deck.describe(&database)
+        ),
+    );
+    conversation.push(HerokuMiaMessage::User {
+        content: match question {
+            Some(question) => format!("{ANALYSIS_PROMPT} Also answer: {question}"),
+            None => ANALYSIS_PROMPT.to_string(),
+        },
+    });
+
+    let conversation_key = response.id.get();
+    tracing::info!("Deck {conversation_key}...");
+    let _turn = type_map_keys::ConversationLocks::get(&ctx.data)
+        .await
+        .lock(conversation_key)
+        .await;
+    query::respond(
+        ctx,
+        command.user.id,
+        conversation_key,
+        conversation,
+        ReplyTarget::Chain(Box::new(response)),
+    )
+    .await;
+
+    Ok(())
+}
+
+/// Fetches a deck from MarvelCDB by id or URL, or parses a pasted deck list.
+pub(crate) async fn import(ctx: &Context, input: &str) -> Result<Deck, MarvelCdbError> {
+    match DeckReference::parse(input) {
+        DeckReference::List(list) => {
+            let database = type_map_keys::CardDatabase::get(&ctx.data).await;
+            Ok(Deck::parse_list(&list, &database))
+        }
+        reference => type_map_keys::MarvelCdbClient::get(&ctx.data)
+            .await
+            .fetch_deck(&reference)
+            .await
+            .map(Option::unwrap_or_default),
+    }
+}
+
+pub(crate) async fn edit_content(
+    ctx: &Context,
+    command: &CommandInteraction,
+    content: &str,
+) -> Result<(), serenity::Error> {
+    command
+        .edit_response(&ctx.http, EditInteractionResponse::new().content(content))
+        .await
+        .map(|_| ())
+}
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("deck")
+        .description("Import a deck and get an analysis of it")
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "deck",
+                "MarvelCDB deck id or URL, or a pasted deck list",
+            )
+            .required(true),
+        )
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::String,
+            "question",
+            "Anything specific to ask about the deck",
+        ))
+}
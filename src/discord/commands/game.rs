@@ -0,0 +1,308 @@
+use serenity::all::{
+    ButtonStyle, CommandInteraction, CommandOptionType, ComponentInteraction, Context,
+    CreateActionRow, CreateButton, CreateCommand, CreateCommandOption, CreateEmbed,
+    CreateInteractionResponse, CreateInteractionResponseMessage, ResolvedValue,
+};
+
+use crate::{
+    discord::{
+        commands::{integer_option, string_option},
+        markdown::truncate,
+        type_map_keys,
+    },
+    game::{Action, GameState, MAX_HEROES, MAX_SIDE_SCHEMES, parse_heroes},
+};
+
+const NO_GAME: &str = "No game is being tracked here. Start one with `/game start`.";
+const MAX_LABEL_LENGTH: usize = 24;
+
+/// Starts, extends, reposts or ends the game tracked in the channel or thread.
+pub async fn run(ctx: &Context, command: &CommandInteraction) -> Result<(), serenity::Error> {
+    let Some(subcommand) = command.data.options().into_iter().next() else {
+        return respond(ctx, command, message(NO_GAME)).await;
+    };
+    let options = match &subcommand.value {
+        ResolvedValue::SubCommand(options) => options.as_slice(),
+        _ => &[],
+    };
+
+    let games = type_map_keys::Games::get(&ctx.data).await;
+    let mut games = games.write().await;
+    let channel_id = command.channel_id.get();
+
+    let response = match subcommand.name {
+        "start" => {
+            let game = parse_heroes(string_option(options, "heroes").unwrap_or_default()).and_then(
+                |heroes| {
+                    GameState::new(
+                        string_option(options, "villain").unwrap_or_default(),
+                        integer_option(options, "villain_hp").unwrap_or_default(),
+                        string_option(options, "scheme").unwrap_or("Main scheme"),
+                        integer_option(options, "threat").unwrap_or_default(),
+                        integer_option(options, "max_threat"),
+                        heroes,
+                    )
+                },
+            );
+            match game {
+                Ok(game) => {
+                    let response = tracker(&game);
+                    games.insert(channel_id, game);
+                    response
+                }
+                Err(e) => message(&e.to_string()),
+            }
+        }
+        "side-scheme" => match games.get_mut(&channel_id) {
+            Some(game) => {
+                let added = game.add_side_scheme(
+                    string_option(options, "name").unwrap_or_default(),
+                    integer_option(options, "threat").unwrap_or_default(),
+                );
+                match added {
+                    Ok(()) => tracker(game),
+                    Err(e) => message(&e.to_string()),
+                }
+            }
+            None => message(NO_GAME),
+        },
+        "end" => match games.remove(&channel_id) {
+            Some(_) => {
+                CreateInteractionResponseMessage::new().content("Stopped tracking the game.")
+            }
+            None => message(NO_GAME),
+        },
+        _ => match games.get(&channel_id) {
+            Some(game) => tracker(game),
+            None => message(NO_GAME),
+        },
+    };
+    drop(games);
+
+    respond(ctx, command, response).await
+}
+
+/// Applies a tracker button press to the channel's game and updates the tracker.
+pub async fn press(ctx: &Context, component: &ComponentInteraction) -> Result<(), serenity::Error> {
+    let games = type_map_keys::Games::get(&ctx.data).await;
+    let mut games = games.write().await;
+
+    let response = match (
+        games.get_mut(&component.channel_id.get()),
+        Action::parse(&component.data.custom_id),
+    ) {
+        (Some(game), Some(action)) => {
+            game.apply(action);
+            CreateInteractionResponse::UpdateMessage(tracker(game))
+        }
+        _ => CreateInteractionResponse::Message(message(NO_GAME)),
+    };
+    drop(games);
+
+    component.create_response(&ctx.http, response).await
+}
+
+/// The tracker message: an embed of the game state with buttons to update it.
+fn tracker(game: &GameState) -> CreateInteractionResponseMessage {
+    let button = |action: Action, label: String, style: ButtonStyle| {
+        CreateButton::new(action.custom_id())
+            .label(label)
+            .style(style)
+    };
+
+    let mut rows = vec![
+        CreateActionRow::Buttons(vec![
+            button(
+                Action::Villain(-1),
+                "Villain −1".to_string(),
+                ButtonStyle::Danger,
+            ),
+            button(
+                Action::Villain(-5),
+                "Villain −5".to_string(),
+                ButtonStyle::Danger,
+            ),
+            button(
+                Action::Villain(1),
+                "Villain +1".to_string(),
+                ButtonStyle::Secondary,
+            ),
+            button(
+                Action::Threat(1),
+                "Threat +1".to_string(),
+                ButtonStyle::Secondary,
+            ),
+            button(
+                Action::Threat(-1),
+                "Threat −1".to_string(),
+                ButtonStyle::Success,
+            ),
+        ]),
+        CreateActionRow::Buttons(vec![
+            button(
+                Action::Acceleration(1),
+                "Acceleration +1".to_string(),
+                ButtonStyle::Secondary,
+            ),
+            button(
+                Action::Acceleration(-1),
+                "Acceleration −1".to_string(),
+                ButtonStyle::Secondary,
+            ),
+        ]),
+    ];
+    // Two heroes, with a damage and a heal button each, per row.
+    for (row, heroes) in game.heroes.chunks(2).enumerate() {
+        let buttons = heroes
+            .iter()
+            .enumerate()
+            .flat_map(|(offset, hero)| {
+                let index = row * 2 + offset;
+                let name = truncate(&hero.name, MAX_LABEL_LENGTH);
+                [
+                    button(
+                        Action::Hero(index, -1),
+                        format!("{name} −1"),
+                        ButtonStyle::Danger,
+                    ),
+                    button(
+                        Action::Hero(index, 1),
+                        format!("{name} +1"),
+                        ButtonStyle::Success,
+                    ),
+                ]
+            })
+            .collect();
+        rows.push(CreateActionRow::Buttons(buttons));
+    }
+    if !game.side_schemes.is_empty() {
+        rows.push(CreateActionRow::Buttons(
+            game.side_schemes
+                .iter()
+                .map(|scheme| {
+                    button(
+                        Action::SideScheme(scheme.id, -1),
+                        format!("{} −1", truncate(&scheme.name, MAX_LABEL_LENGTH)),
+                        ButtonStyle::Success,
+                    )
+                })
+                .collect(),
+        ));
+    }
+
+    CreateInteractionResponseMessage::new()
+        .embed(tracker_embed(game))
+        .components(rows)
+}
+
+fn tracker_embed(game: &GameState) -> CreateEmbed {
+    let threat = match game.max_threat {
+        Some(max) => format!("{}/{max}", game.threat),
+        None => game.threat.to_string(),
+    };
+    let mut embed = CreateEmbed::new()
+        .title(format!("{} vs {}", game.villain, hero_names(game)))
+        .field(
+            &game.villain,
+            if game.villain_hp == 0 {
+                "Defeated!".to_string()
+            } else {
+                format!("{} HP", game.villain_hp)
+            },
+            true,
+        )
+        .field(&game.main_scheme, format!("{threat} threat"), true)
+        .field("Acceleration", game.acceleration.to_string(), true);
+    for hero in &game.heroes {
+        embed = embed.field(&hero.name, format!("{}/{} HP", hero.hp, hero.max_hp), true);
+    }
+    if !game.side_schemes.is_empty() {
+        embed = embed.field(
+            "Side schemes",
+            game.side_schemes
+                .iter()
+                .map(|scheme| format!("{}: {} threat", scheme.name, scheme.threat))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            false,
+        );
+    }
+
+    embed
+}
+
+fn hero_names(game: &GameState) -> String {
+    game.heroes
+        .iter()
+        .map(|hero| hero.name.as_str())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn message(content: &str) -> CreateInteractionResponseMessage {
+    CreateInteractionResponseMessage::new()
+        .content(content)
+        .ephemeral(true)
+}
+
+async fn respond(
+    ctx: &Context,
+    command: &CommandInteraction,
+    message: CreateInteractionResponseMessage,
+) -> Result<(), serenity::Error> {
+    command
+        .create_response(&ctx.http, CreateInteractionResponse::Message(message))
+        .await
+}
+
+pub fn register() -> CreateCommand {
+    let string = |name: &str, description: &str| {
+        CreateCommandOption::new(CommandOptionType::String, name, description)
+    };
+    let integer = |name: &str, description: &str| {
+        CreateCommandOption::new(CommandOptionType::Integer, name, description)
+            .min_int_value(0)
+            .max_int_value(999)
+    };
+
+    CreateCommand::new("game")
+        .description("Track a game in progress with buttons")
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "start",
+                "Start tracking a game",
+            )
+            .add_sub_option(string("villain", "Villain").required(true))
+            .add_sub_option(integer("villain_hp", "Villain hit points").required(true))
+            .add_sub_option(
+                string(
+                    "heroes",
+                    &format!("Up to {MAX_HEROES} heroes as Name:HP, separated by commas"),
+                )
+                .required(true),
+            )
+            .add_sub_option(string("scheme", "Main scheme"))
+            .add_sub_option(integer("threat", "Starting threat"))
+            .add_sub_option(integer("max_threat", "Threat at which the scheme advances")),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "side-scheme",
+                format!("Add a side scheme, up to {MAX_SIDE_SCHEMES}"),
+            )
+            .add_sub_option(string("name", "Side scheme").required(true))
+            .add_sub_option(integer("threat", "Threat on it").required(true)),
+        )
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "show",
+            "Post the tracker again",
+        ))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "end",
+            "Stop tracking the game",
+        ))
+}
@@ -0,0 +1,501 @@
+use serenity::all::{
+    ChannelId, ChannelType, CommandInteraction, CommandOptionType, Context, CreateAttachment,
+    CreateCommand, CreateCommandOption, CreateInteractionResponse,
+    CreateInteractionResponseMessage, Permissions, ResolvedOption, ResolvedValue,
+};
+
+use crate::{
+    discord::{
+        commands::{is_admin, string_option},
+        markdown::truncate,
+        personas, type_map_keys,
+    },
+    feedback::Vote,
+    heroku_mia::types::Message as HerokuMiaMessage,
+    limits,
+    personas::{Persona, ToolPolicy},
+    tools,
+};
+
+const MAX_LISTED: usize = 20;
+const MAX_PREVIEW_LENGTH: usize = 80;
+/// Prompts longer than this are attached instead of shown inline, and lists are cut short, to
+/// stay under Discord's 2000 characters per message.
+const MAX_INLINE_LENGTH: usize = 1900;
+
+/// Administers the bot: the model, the system prompt, channel personas, the MCP tools,
+/// conversations, feedback and usage.
+/// Everything is answered so that only the admin sees it.
+pub async fn run(ctx: &Context, command: &CommandInteraction) -> Result<(), serenity::Error> {
+    if !is_admin(ctx, command.member.as_deref()).await {
+        return respond(ctx, command, message("Only admins can use `/karen`.")).await;
+    }
+
+    let Some(group) = command.data.options().into_iter().next() else {
+        return respond(ctx, command, message("Use a subcommand.")).await;
+    };
+    let (subcommand, options): (&str, &[ResolvedOption]) = match &group.value {
+        ResolvedValue::SubCommandGroup(subcommands) => match subcommands.first() {
+            Some(ResolvedOption {
+                name,
+                value: ResolvedValue::SubCommand(options),
+                ..
+            }) => (name, options.as_slice()),
+            _ => ("", &[]),
+        },
+        _ => ("", &[]),
+    };
+
+    let response = match (group.name, subcommand) {
+        ("model", "set") => {
+            let model_id = string_option(options, "model").unwrap_or_default().trim();
+            type_map_keys::InferenceModelId::set(&ctx.data, model_id.to_string()).await;
+            save(ctx, |settings| {
+                settings.model_id = Some(model_id.to_string())
+            })
+            .await;
+            tracing::info!("Karen: {} set the model to {model_id}", command.user.id);
+            message(&format!("Agent calls now use `{model_id}`."))
+        }
+        ("model", _) => message(&format!(
+            "Agent calls use `{}`.",
+            type_map_keys::InferenceModelId::get(&ctx.data).await
+        )),
+        ("prompt", "set") => {
+            let prompt = string_option(options, "prompt").unwrap_or_default().trim();
+            type_map_keys::SystemPrompt::set(&ctx.data, prompt.to_string()).await;
+            save(ctx, |settings| {
+                settings.system_prompt = Some(prompt.to_string())
+            })
+            .await;
+            tracing::info!("Karen: {} replaced the system prompt", command.user.id);
+            message("Replaced the system prompt. Conversations that already started keep theirs.")
+        }
+        ("prompt", _) => {
+            let prompt = type_map_keys::SystemPrompt::get(&ctx.data).await;
+            if prompt.chars().count() <= MAX_INLINE_LENGTH {
+                message(&format!("```\n{prompt}\n```"))
+            } else {
+                message("The system prompt is attached.").add_file(CreateAttachment::bytes(
+                    prompt.into_bytes(),
+                    "system_prompt.md",
+                ))
+            }
+        }
+        ("persona", "set") => {
+            let channel_id = channel_option(options).unwrap_or(command.channel_id);
+            set_persona(ctx, channel_id, options).await
+        }
+        ("persona", "clear") => {
+            let channel_id = channel_option(options).unwrap_or(command.channel_id);
+            save(ctx, |settings| {
+                settings.personas.remove(&channel_id.get());
+            })
+            .await;
+            tracing::info!(
+                "Karen: {} cleared the persona of {channel_id}",
+                command.user.id
+            );
+            message(&format!("<#{channel_id}> uses the default persona again."))
+        }
+        ("persona", _) => {
+            show_persona(ctx, channel_option(options).unwrap_or(command.channel_id)).await
+        }
+        ("tools", _) => reload_tools(ctx).await,
+        ("conversations", "inspect") => {
+            inspect_conversation(ctx, string_option(options, "id")).await
+        }
+        ("conversations", "clear") => {
+            let cleared = match conversation_id(string_option(options, "id")) {
+                Some(id) => {
+                    let conversations = type_map_keys::ConversationHistory::get(&ctx.data).await;
+                    let mut conversations = conversations.write().await;
+                    conversations
+                        .find(id)
+                        .and_then(|key| conversations.remove(key))
+                        .is_some()
+                }
+                None => false,
+            };
+            message(if cleared {
+                "Cleared the conversation."
+            } else {
+                "No conversation has that id."
+            })
+        }
+        ("conversations", _) => list_conversations(ctx).await,
+        ("feedback", _) => {
+            let vote = match string_option(options, "vote") {
+                Some("up") => Some(Vote::Up),
+                Some("down") => Some(Vote::Down),
+                _ => None,
+            };
+            export_feedback(ctx, vote).await
+        }
+        _ => usage(ctx).await,
+    };
+
+    respond(ctx, command, response).await
+}
+
+async fn save(ctx: &Context, update: impl FnOnce(&mut crate::settings::Settings)) {
+    let settings = type_map_keys::Settings::get(&ctx.data).await;
+    if let Err(e) = settings.update(update).await {
+        tracing::error!("Karen: Error saving settings: {:?}", e);
+    }
+}
+
+/// Changes the persona fields given in `options`, keeping the others.
+async fn set_persona(
+    ctx: &Context,
+    channel_id: ChannelId,
+    options: &[ResolvedOption<'_>],
+) -> CreateInteractionResponseMessage {
+    let temperature = options.iter().find_map(|option| match option.value {
+        ResolvedValue::Number(value) if option.name == "temperature" => Some(value as f32),
+        _ => None,
+    });
+    let change = Persona {
+        system_prompt: string_option(options, "system_prompt").map(|prompt| prompt.trim().into()),
+        model_id: string_option(options, "model").map(|model| model.trim().into()),
+        temperature,
+        tools: string_option(options, "tools").map(ToolPolicy::parse),
+    };
+    if change.is_empty() {
+        return message("Give at least one of the persona's settings to change.");
+    }
+
+    save(ctx, |settings| {
+        let persona = settings.personas.entry(channel_id.get()).or_default();
+        *persona = change.or(persona);
+    })
+    .await;
+    tracing::info!("Karen: Changed the persona of {channel_id}");
+
+    show_persona(ctx, channel_id).await
+}
+
+/// The persona agent calls in a channel use, including what it inherits. System prompts only
+/// apply to conversations started after they were set.
+async fn show_persona(ctx: &Context, channel_id: ChannelId) -> CreateInteractionResponseMessage {
+    let persona = personas::persona(ctx, channel_id).await;
+    let settings = personas::agent_settings(ctx, channel_id).await;
+
+    let default_label = |set: bool| if set { "" } else { " (default)" };
+    let summary = format!(
+        "**Persona of <#{channel_id}>**\n\
+         Model: `{}`{}\n\
+         Temperature: {}\n\
+         Tools: {}{}\n\
+         System prompt{}:",
+        settings.model_id,
+        default_label(persona.model_id.is_some()),
+        persona
+            .temperature
+            .map_or("the model's default".to_string(), |temperature| {
+                temperature.to_string()
+            }),
+        persona.tools.clone().unwrap_or_default(),
+        default_label(persona.tools.is_some()),
+        default_label(persona.system_prompt.is_some()),
+    );
+    let prompt = settings.system_prompt;
+    if summary.chars().count() + prompt.chars().count() <= MAX_INLINE_LENGTH {
+        message(&format!("{summary}\n```\n{prompt}\n```"))
+    } else {
+        message(&format!("{summary} attached.")).add_file(CreateAttachment::bytes(
+            prompt.into_bytes(),
+            "system_prompt.md",
+        ))
+    }
+}
+
+async fn reload_tools(ctx: &Context) -> CreateInteractionResponseMessage {
+    let client = type_map_keys::HerokuMiaClient::get(&ctx.data).await;
+    match tools::mcp_tools(&client).await {
+        Ok(tools) => {
+            let names = tools
+                .iter()
+                .map(|tool| format!("`{}`", tool.name()))
+                .collect::<Vec<_>>()
+                .join(", ");
+            let count = tools.len();
+            type_map_keys::AgentTools::set(&ctx.data, tools).await;
+            message(&format!("Reloaded {count} MCP tools: {names}"))
+        }
+        Err(e) => {
+            tracing::error!("Karen: Error listing MCP servers: {e}");
+            message(&format!("Could not reload the MCP tools: {e}"))
+        }
+    }
+}
+
+async fn list_conversations(ctx: &Context) -> CreateInteractionResponseMessage {
+    let conversations = type_map_keys::ConversationHistory::get(&ctx.data).await;
+    let conversations = conversations.read().await;
+    if conversations.is_empty() {
+        return message("No active conversations.");
+    }
+
+    let mut listed: Vec<_> = conversations.iter().collect();
+    listed.sort_unstable_by_key(|(id, _)| std::cmp::Reverse(*id));
+    let mut lines = vec![format!("{} active conversations:", conversations.len())];
+    let mut length = lines[0].chars().count();
+    for (id, conversation) in listed.into_iter().take(MAX_LISTED) {
+        let messages = &conversation.messages;
+        let prompt = messages
+            .iter()
+            .find_map(|message| match message {
+                HerokuMiaMessage::User { content } => Some(content.as_str()),
+                _ => None,
+            })
+            .unwrap_or_default();
+        let line = format!(
+            "`{id}` · {} messages · {}",
+            messages.len(),
+            truncate(
+                prompt.lines().next().unwrap_or_default(),
+                MAX_PREVIEW_LENGTH
+            )
+        );
+        length += line.chars().count() + 1;
+        if length > MAX_INLINE_LENGTH {
+            break;
+        }
+        lines.push(line);
+    }
+
+    message(&lines.join("\n"))
+}
+
+async fn inspect_conversation(ctx: &Context, id: Option<&str>) -> CreateInteractionResponseMessage {
+    let conversation = match conversation_id(id) {
+        Some(id) => {
+            let conversations = type_map_keys::ConversationHistory::get(&ctx.data).await;
+            let conversations = conversations.read().await;
+            conversations.find(id).and_then(|key| {
+                conversations
+                    .get(key)
+                    .map(|conversation| (key, conversation.messages.clone()))
+            })
+        }
+        None => None,
+    };
+    let Some((id, conversation)) = conversation else {
+        return message("No conversation has that id.");
+    };
+
+    match serde_json::to_vec_pretty(&conversation) {
+        Ok(json) => message(&format!("{} messages.", conversation.len())).add_file(
+            CreateAttachment::bytes(json, format!("conversation-{id}.json")),
+        ),
+        Err(e) => message(&format!("Could not serialize the conversation: {e}")),
+    }
+}
+
+/// The ratings collected from reactions, as a JSON Lines attachment.
+async fn export_feedback(ctx: &Context, vote: Option<Vote>) -> CreateInteractionResponseMessage {
+    let log = type_map_keys::Feedback::get(&ctx.data).await;
+    let log = log.read().await;
+    let summary = format!(
+        "{} 👍 and {} 👎 collected.",
+        log.count(Vote::Up),
+        log.count(Vote::Down)
+    );
+
+    match log.to_jsonl(vote) {
+        Ok(jsonl) if jsonl.is_empty() => message(&summary),
+        Ok(jsonl) => message(&summary).add_file(CreateAttachment::bytes(jsonl, "feedback.jsonl")),
+        Err(e) => message(&format!("Could not serialize the feedback: {e}")),
+    }
+}
+
+async fn usage(ctx: &Context) -> CreateInteractionResponseMessage {
+    let usage = type_map_keys::Usage::get(&ctx.data).await;
+    let usage = usage.read().await;
+    let queue = type_map_keys::InferenceQueue::get(&ctx.data).await;
+    let conversations = type_map_keys::ConversationHistory::get(&ctx.data)
+        .await
+        .read()
+        .await
+        .len();
+
+    let today = limits::today();
+    let mut users: Vec<(u64, u64)> = usage
+        .tokens
+        .keys()
+        .map(|user_id| (*user_id, usage.used(*user_id, today)))
+        .filter(|(_, tokens)| *tokens > 0)
+        .collect();
+    users.sort_unstable_by_key(|(_, tokens)| std::cmp::Reverse(*tokens));
+
+    let mut lines = vec![
+        format!(
+            "**Tokens today:** {}",
+            users.iter().map(|(_, tokens)| tokens).sum::<u64>()
+        ),
+        format!(
+            "**Agent calls:** {} running, {} queued",
+            queue.running(),
+            queue.queued()
+        ),
+        format!("**Active conversations:** {conversations}"),
+    ];
+    for (user_id, tokens) in users.into_iter().take(MAX_LISTED) {
+        lines.push(format!("<@{user_id}>: {tokens} tokens"));
+    }
+
+    message(&lines.join("\n"))
+}
+
+/// The channel among a subcommand's options.
+fn channel_option(options: &[ResolvedOption]) -> Option<ChannelId> {
+    options.iter().find_map(|option| match option.value {
+        ResolvedValue::Channel(channel) if option.name == "channel" => Some(channel.id),
+        _ => None,
+    })
+}
+
+fn conversation_id(id: Option<&str>) -> Option<u64> {
+    id?.trim().parse().ok()
+}
+
+fn message(content: &str) -> CreateInteractionResponseMessage {
+    CreateInteractionResponseMessage::new()
+        .content(content)
+        .ephemeral(true)
+}
+
+async fn respond(
+    ctx: &Context,
+    command: &CommandInteraction,
+    message: CreateInteractionResponseMessage,
+) -> Result<(), serenity::Error> {
+    command
+        .create_response(&ctx.http, CreateInteractionResponse::Message(message))
+        .await
+}
+
+/// Visible to members who can manage the server. Members with an `ADMIN_ROLE_IDS` role may use
+/// it too, once the server's integration settings show it to them.
+pub fn register() -> CreateCommand {
+    let group = |name: &str, description: &str| {
+        CreateCommandOption::new(CommandOptionType::SubCommandGroup, name, description)
+    };
+    let subcommand = |name: &str, description: &str| {
+        CreateCommandOption::new(CommandOptionType::SubCommand, name, description)
+    };
+    let channel = || {
+        CreateCommandOption::new(
+            CommandOptionType::Channel,
+            "channel",
+            "Channel or thread, this one if not given",
+        )
+        .channel_types(vec![
+            ChannelType::Text,
+            ChannelType::PublicThread,
+            ChannelType::PrivateThread,
+        ])
+    };
+    let id = || {
+        CreateCommandOption::new(CommandOptionType::String, "id", "Conversation id").required(true)
+    };
+
+    CreateCommand::new("karen")
+        .description("Administer the bot")
+        .default_member_permissions(Permissions::MANAGE_GUILD)
+        .add_option(
+            group("model", "The model agent calls use")
+                .add_sub_option(subcommand("show", "Show the model id"))
+                .add_sub_option(
+                    subcommand("set", "Change the model id").add_sub_option(
+                        CreateCommandOption::new(CommandOptionType::String, "model", "Model id")
+                            .required(true),
+                    ),
+                ),
+        )
+        .add_option(
+            group("prompt", "The system prompt of new conversations")
+                .add_sub_option(subcommand("view", "Show the system prompt"))
+                .add_sub_option(
+                    subcommand("set", "Replace the system prompt").add_sub_option(
+                        CreateCommandOption::new(
+                            CommandOptionType::String,
+                            "prompt",
+                            "New system prompt",
+                        )
+                        .required(true)
+                        .max_length(6000),
+                    ),
+                ),
+        )
+        .add_option(
+            group("persona", "How the bot answers in a channel or thread")
+                .add_sub_option(
+                    subcommand("show", "Show a channel's persona").add_sub_option(channel()),
+                )
+                .add_sub_option(
+                    subcommand("set", "Change a channel's persona")
+                        .add_sub_option(channel())
+                        .add_sub_option(
+                            CreateCommandOption::new(
+                                CommandOptionType::String,
+                                "system_prompt",
+                                "System prompt of new conversations",
+                            )
+                            .max_length(6000),
+                        )
+                        .add_sub_option(CreateCommandOption::new(
+                            CommandOptionType::String,
+                            "model",
+                            "Model id",
+                        ))
+                        .add_sub_option(
+                            CreateCommandOption::new(
+                                CommandOptionType::Number,
+                                "temperature",
+                                "Sampling temperature",
+                            )
+                            .min_number_value(0.0)
+                            .max_number_value(1.0),
+                        )
+                        .add_sub_option(CreateCommandOption::new(
+                            CommandOptionType::String,
+                            "tools",
+                            "all, none, or tool names separated by commas",
+                        )),
+                )
+                .add_sub_option(
+                    subcommand("clear", "Use the default persona again").add_sub_option(channel()),
+                ),
+        )
+        .add_option(
+            group("tools", "The MCP tools agent calls may use")
+                .add_sub_option(subcommand("reload", "List the MCP servers' tools again")),
+        )
+        .add_option(
+            group("conversations", "Conversations in memory")
+                .add_sub_option(subcommand("list", "List the most recent conversations"))
+                .add_sub_option(
+                    subcommand("inspect", "Attach a conversation's messages").add_sub_option(id()),
+                )
+                .add_sub_option(subcommand("clear", "Forget a conversation").add_sub_option(id())),
+        )
+        .add_option(
+            group("feedback", "Ratings of answers from 👍 and 👎 reactions").add_sub_option(
+                subcommand("export", "Attach the ratings as JSON Lines").add_sub_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::String,
+                        "vote",
+                        "Only export these ratings",
+                    )
+                    .add_string_choice("👍", "up")
+                    .add_string_choice("👎", "down"),
+                ),
+            ),
+        )
+        .add_option(subcommand(
+            "usage",
+            "Show today's token usage and the inference queue",
+        ))
+}
@@ -1 +1,54 @@
+use serenity::all::{Context, Member, Permissions, ResolvedOption, ResolvedValue};
+
+use crate::discord::type_map_keys;
+
+pub(crate) mod campaign;
+pub(crate) mod card;
+pub(crate) mod collection;
+pub(crate) mod deck;
+pub(crate) mod game;
+pub(crate) mod karen;
+pub(crate) mod odds;
 pub(crate) mod query;
+pub(crate) mod randomize;
+pub(crate) mod reindex_rules;
+pub(crate) mod validate_deck;
+
+/// Looks up a string among a subcommand's options.
+pub(crate) fn string_option<'a>(options: &[ResolvedOption<'a>], name: &str) -> Option<&'a str> {
+    options.iter().find_map(|option| match option.value {
+        ResolvedValue::String(value) if option.name == name => Some(value),
+        _ => None,
+    })
+}
+
+/// Looks up a non-negative integer among a subcommand's options.
+pub(crate) fn integer_option(options: &[ResolvedOption], name: &str) -> Option<u32> {
+    options.iter().find_map(|option| match option.value {
+        ResolvedValue::Integer(value) if option.name == name => u32::try_from(value).ok(),
+        _ => None,
+    })
+}
+
+/// Whether a boolean among a subcommand's options is set to true.
+pub(crate) fn bool_option(options: &[ResolvedOption], name: &str) -> bool {
+    options
+        .iter()
+        .any(|option| option.name == name && matches!(option.value, ResolvedValue::Boolean(true)))
+}
+
+/// Whether a member can manage the server or has an admin role.
+pub(crate) async fn is_admin(ctx: &Context, member: Option<&Member>) -> bool {
+    let Some(member) = member else {
+        return false;
+    };
+    if member
+        .permissions
+        .is_some_and(|permissions| permissions.contains(Permissions::MANAGE_GUILD))
+    {
+        return true;
+    }
+
+    let admin_roles = type_map_keys::AdminRoles::get(&ctx.data).await;
+    member.roles.iter().any(|role| admin_roles.contains(role))
+}
@@ -0,0 +1,129 @@
+use serenity::all::{
+    CommandInteraction, CommandOptionType, Context, CreateCommand, CreateCommandOption,
+    CreateInteractionResponse, CreateInteractionResponseMessage,
+};
+
+use crate::cards::odds::{Category, DEFAULT_DECK_SIZE, DEFAULT_HAND_SIZE, DrawOdds, MAX_VALUE};
+
+/// Answers with the odds of drawing one or two kinds of cards, calculated locally.
+pub async fn run(
+    ctx: &Context,
+    command: &CommandInteraction,
+    odds: DrawOdds,
+) -> Result<(), serenity::Error> {
+    let response = match odds.probability() {
+        Ok(probability) => {
+            CreateInteractionResponseMessage::new().content(describe(&odds, probability))
+        }
+        Err(e) => CreateInteractionResponseMessage::new()
+            .content(e.to_string())
+            .ephemeral(true),
+    };
+
+    command
+        .create_response(&ctx.http, CreateInteractionResponse::Message(response))
+        .await
+}
+
+fn describe(odds: &DrawOdds, probability: f64) -> String {
+    let wanted = odds
+        .categories
+        .iter()
+        .map(|category| format!("{} of {} copies", category.need, category.copies))
+        .collect::<Vec<_>>()
+        .join(" and ");
+    let when = match odds.turns {
+        0 => format!("in an opening hand of {}", odds.hand_size),
+        turns => format!("by the end of turn {turns}"),
+    };
+    let mulligan = if odds.mulligan {
+        ", with a mulligan"
+    } else {
+        ""
+    };
+
+    format!(
+        "**{:.1}%** chance to draw at least {wanted} {when} from {} cards{mulligan}.",
+        probability * 100.0,
+        odds.deck_size
+    )
+}
+
+/// Reads the command's options into the odds to calculate.
+pub(crate) fn options(command: &CommandInteraction) -> Option<DrawOdds> {
+    let integer = |name: &str| {
+        command
+            .data
+            .options
+            .iter()
+            .find(|option| option.name == name)
+            .and_then(|option| option.value.as_i64())
+            .and_then(|value| u32::try_from(value).ok())
+    };
+
+    let mut categories = vec![Category {
+        copies: integer("copies")?,
+        need: integer("need").unwrap_or(1),
+    }];
+    if let Some(copies) = integer("copies2") {
+        categories.push(Category {
+            copies,
+            need: integer("need2").unwrap_or(1),
+        });
+    }
+
+    Some(DrawOdds {
+        deck_size: integer("deck").unwrap_or(DEFAULT_DECK_SIZE),
+        hand_size: integer("hand").unwrap_or(DEFAULT_HAND_SIZE),
+        turns: integer("turns").unwrap_or(0),
+        cards_per_turn: integer("per_turn"),
+        mulligan: command
+            .data
+            .options
+            .iter()
+            .find(|option| option.name == "mulligan")
+            .and_then(|option| option.value.as_bool())
+            .unwrap_or(false),
+        ..DrawOdds::new(categories)
+    })
+}
+
+pub fn register() -> CreateCommand {
+    let integer = |name: &str, description: &str, min: u64| {
+        CreateCommandOption::new(CommandOptionType::Integer, name, description)
+            .min_int_value(min)
+            .max_int_value(MAX_VALUE.into())
+    };
+
+    CreateCommand::new("odds")
+        .description("Calculate the odds of drawing cards")
+        .add_option(integer("copies", "Copies of the card in the deck", 1).required(true))
+        .add_option(integer("need", "Copies needed, 1 by default", 1))
+        .add_option(integer("deck", "Deck size, 40 by default", 1))
+        .add_option(integer("hand", "Hand size, 5 by default", 1))
+        .add_option(integer(
+            "turns",
+            "Turns after the opening hand, 0 by default",
+            0,
+        ))
+        .add_option(integer(
+            "per_turn",
+            "Cards drawn each turn, the hand size by default",
+            0,
+        ))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::Boolean,
+            "mulligan",
+            "Mulligan every other card of the opening hand",
+        ))
+        .add_option(integer(
+            "copies2",
+            "Copies of a second card that's also needed",
+            1,
+        ))
+        .add_option(integer(
+            "need2",
+            "Copies of the second card needed, 1 by default",
+            1,
+        ))
+}
@@ -1,96 +1,606 @@
 use futures::{Stream, StreamExt};
 use serenity::all::{
-    CommandInteraction, CommandOptionType, Context, CreateCommand, CreateCommandOption,
-    CreateInteractionResponseMessage, Message as SerenityMessage, MessageId,
+    ChannelId, ChannelType, CommandInteraction, CommandOptionType, Context, CreateCommand,
+    CreateCommandOption, CreateInteractionResponseMessage, CreateMessage, CreateThread,
+    EditInteractionResponse, Message as SerenityMessage, MessageId, UserId,
 };
 use std::pin::Pin;
-use std::sync::Arc;
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, AtomicU64, Ordering},
+};
 use tokio::sync::Mutex;
 
 use crate::{
-    discord::{DiscordError, type_map_keys},
+    cards::{
+        collection,
+        verification::{self, Mismatch},
+    },
+    discord::{
+        DiscordError, answers, card_embed, feedback, limits,
+        markdown::truncate,
+        output::{self, Output, Part},
+        personas::{self, AgentSettings},
+        queue,
+        streaming::{ReplyTarget, StreamingMessage},
+        type_map_keys,
+    },
+    feedback::Turn,
     heroku_mia::{
-        Client,
-        agents::{AgentRequest, AgentTool},
+        Client, agents::AgentRequest, chat_completion::ChatCompletionRequest,
         types::Message as HerokuMiaMessage,
     },
+    rules, tools,
 };
 
-const MAX_DISCORD_MESSAGE_LENGTH: usize = 2000;
 const MAX_CONVERSATION_MESSAGES: usize = 10; // Keep last 10 messages (5 turns)
 const MAX_TOOL_OUTPUT_CHARS: usize = 1000; // Max characters for tool output summary
+const MAX_THREAD_NAME_LENGTH: usize = 100;
+const RULES_PASSAGES: usize = 4;
+
+/// What an agent call used and how it ended.
+#[derive(Debug, Default)]
+pub(crate) struct TurnStats {
+    pub tokens: AtomicU64,
+    /// Whether the last answer stopped at the token limit.
+    pub truncated: AtomicBool,
+}
+
+/// Visibility of the thread a `/query` conversation is held in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum ThreadKind {
+    Public,
+    Private,
+}
+
+impl ThreadKind {
+    pub(crate) fn from_option(value: &str) -> Option<Self> {
+        match value {
+            "public" => Some(ThreadKind::Public),
+            "private" => Some(ThreadKind::Private),
+            _ => None,
+        }
+    }
+
+    fn channel_type(self) -> ChannelType {
+        match self {
+            ThreadKind::Public => ChannelType::PublicThread,
+            ThreadKind::Private => ChannelType::PrivateThread,
+        }
+    }
+}
 
 pub async fn run(
     ctx: &Context,
     command: &CommandInteraction,
     prompt: &str,
+    thread: Option<ThreadKind>,
+    owned_only: bool,
 ) -> Result<(), serenity::Error> {
+    if !limits::check_command(ctx, command).await? {
+        return Ok(());
+    }
+    let greeting = if thread.is_some() {
+        "Starting a new conversation in a thread."
+    } else {
+        "Starting a new conversation. Reply to this message to continue."
+    };
+    let start = queue::start(ctx).await;
     command
         .create_response(
             &ctx.http,
             serenity::all::CreateInteractionResponse::Message(
                 CreateInteractionResponseMessage::new()
-                    .content("Starting a new conversation. Reply to this message to continue."),
+                    .content(queue::with_position(greeting, &start)),
             ),
         )
         .await?;
-    let mut last_message = command.get_response(&ctx.http).await?;
-    let conversation_key = last_message.id.get();
+    let _permit = queue::wait_for_command(ctx, command, start, greeting).await?;
+    let response = command.get_response(&ctx.http).await?;
+
+    let (conversation_key, target) = match thread {
+        Some(kind) => {
+            let thread_id =
+                match create_conversation_thread(ctx, command, &response, prompt, kind).await {
+                    Ok(thread_id) => thread_id,
+                    Err(e) => {
+                        tracing::error!("Query: Error creating {:?} thread: {:?}", kind, e);
+                        command
+                            .edit_response(
+                                &ctx.http,
+                                EditInteractionResponse::new()
+                                    .content("Could not create a thread for this conversation."),
+                            )
+                            .await?;
+                        return Err(e);
+                    }
+                };
+            (thread_id.get(), ReplyTarget::Thread(thread_id))
+        }
+        None => (response.id.get(), ReplyTarget::Chain(Box::new(response))),
+    };
     tracing::info!("Query {conversation_key}...");
 
-    let messages = bootstrap_messages();
-    let mut initial_messages = messages;
+    let mut initial_messages = bootstrap_messages(
+        &personas::agent_settings(ctx, command.channel_id)
+            .await
+            .system_prompt,
+    );
+    if let Some(context) = collection_context(ctx, command.user.id.get(), owned_only).await {
+        add_system_context(&mut initial_messages, &context);
+    }
+    if let Some(log) = type_map_keys::CampaignLogs::get(&ctx.data)
+        .await
+        .read()
+        .await
+        .get(&command.channel_id.get())
+    {
+        add_system_context(
+            &mut initial_messages,
+            &format!(
+                "The user is playing this campaign, use its log to suggest what to do next:\n{}",
+                log.describe()
+            ),
+        );
+    }
+    if let Some(game) = type_map_keys::Games::get(&ctx.data)
+        .await
+        .read()
+        .await
+        .get(&command.channel_id.get())
+    {
+        add_system_context(
+            &mut initial_messages,
+            &format!(
+                "The user is playing a game right now, this is its current state:\n{}",
+                game.describe()
+            ),
+        );
+    }
+    if let Some(context) = rules_context(ctx, prompt).await {
+        add_system_context(&mut initial_messages, &context);
+    }
     initial_messages.push(HerokuMiaMessage::User {
         content: prompt.to_string(),
     });
 
-    let conversation_arc = Arc::new(Mutex::new(initial_messages));
+    let _turn = type_map_keys::ConversationLocks::get(&ctx.data)
+        .await
+        .lock(conversation_key)
+        .await;
+    respond(
+        ctx,
+        command.user.id,
+        conversation_key,
+        initial_messages,
+        target,
+    )
+    .await;
+
+    Ok(())
+}
+
+/// The user's owned packs, to keep recommendations to what they can build with.
+async fn collection_context(ctx: &Context, user_id: u64, owned_only: bool) -> Option<String> {
+    let collections = type_map_keys::Collections::get(&ctx.data).await;
+    let collections = collections.read().await;
+    let database = type_map_keys::CardDatabase::get(&ctx.data).await;
+
+    collection::describe(collections.get(&user_id)?, &database, owned_only)
+}
+
+/// The rules passages most relevant to the prompt, for answers to cite.
+async fn rules_context(ctx: &Context, prompt: &str) -> Option<String> {
+    let rules = type_map_keys::Rules::get(&ctx.data).await?;
+    match rules.retrieve(prompt, RULES_PASSAGES).await {
+        Ok(passages) if !passages.is_empty() => Some(rules::context(&passages)),
+        Ok(_) => None,
+        Err(e) => {
+            tracing::error!("Query: Error retrieving rules passages: {:?}", e);
+            None
+        }
+    }
+}
+
+/// Answers a message continuing a conversation. The conversation's turns are taken one at a
+/// time, each starting from the history the one before left.
+pub(crate) async fn follow_up(
+    ctx: &Context,
+    msg: SerenityMessage,
+    conversation_key: u64,
+    target: ReplyTarget,
+) {
+    if !limits::check_message(ctx, &msg).await {
+        return;
+    }
+    let _turn = type_map_keys::ConversationLocks::get(&ctx.data)
+        .await
+        .lock(conversation_key)
+        .await;
+    let Some(mut conversation) = type_map_keys::ConversationHistory::get(&ctx.data)
+        .await
+        .read()
+        .await
+        .get(conversation_key)
+        .map(|conversation| conversation.messages.clone())
+    else {
+        tracing::info!("Query {conversation_key}: Conversation was cleared");
+        return;
+    };
+
+    let _permit = queue::wait_for_message(ctx, &msg).await;
+    conversation.push(HerokuMiaMessage::User {
+        content: msg.content.clone(),
+    });
+    respond(ctx, msg.author.id, conversation_key, conversation, target).await;
+}
+
+/// Streams an agent turn for `conversation` into `target`, stores the resulting history under
+/// `conversation_key` and counts the tokens used towards the user's daily quota. Callers hold
+/// the conversation's lock, so the history is only written by one turn at a time.
+pub(crate) async fn respond(
+    ctx: &Context,
+    user_id: UserId,
+    conversation_key: u64,
+    conversation: Vec<HerokuMiaMessage>,
+    target: ReplyTarget,
+) {
+    // Stored right away, so that follow-ups find the conversation and wait for this turn. The
+    // message a chain replies to is the prompt, or the answer it continues.
+    let replying_to = match &target {
+        ReplyTarget::Chain(message) => Some(message.id),
+        ReplyTarget::Thread(_) => None,
+    };
+    type_map_keys::ConversationHistory::get(&ctx.data)
+        .await
+        .write()
+        .await
+        .start(conversation_key, user_id.get());
+    store_conversation(ctx, conversation_key, conversation.clone(), replying_to).await;
+    let conversation_arc = Arc::new(Mutex::new(conversation));
+    let stats = Arc::new(TurnStats::default());
+    tracing::debug!("Query {conversation_key}: {:?}", conversation_arc);
+    let stop = answers::start(ctx, conversation_key).await;
+    let channel_id = target.channel_id();
 
+    let settings = personas::agent_settings(ctx, channel_id).await;
+    let model_id = settings.model_id.clone();
     let mut stream = agents_call(
         &type_map_keys::HerokuMiaClient::get(&ctx.data).await,
-        type_map_keys::AgentTools::get(&ctx.data).await,
-        &type_map_keys::InferenceModelId::get(&ctx.data).await,
+        settings,
         Arc::clone(&conversation_arc),
+        Arc::clone(&stats),
     )
     .await;
 
-    while let Some(message_result) = stream.next().await {
-        match message_result {
-            Ok(message) => {
-                tracing::info!("Query {conversation_key}: Received streamed message");
-                if !message.is_empty() {
-                    let chunks = split_message_into_chunks(&message);
-                    for chunk in chunks {
-                        match last_message.reply(&ctx.http, chunk).await {
-                            Ok(message) => last_message = message,
-                            Err(e) => tracing::error!("Error sending error message: {:?}", e),
-                        }
+    let mut renderer = StreamingMessage::new(target);
+    if let Err(e) = renderer
+        .set_components(ctx, answers::writing_buttons(conversation_key))
+        .await
+    {
+        tracing::error!("Query {conversation_key}: Error adding buttons: {:?}", e);
+    }
+    let mut stopped = false;
+    loop {
+        tokio::select! {
+            message_result = stream.next() => match message_result {
+                Some(Ok(message)) => {
+                    tracing::info!("Query {conversation_key}: Received streamed message");
+                    if !message.is_empty()
+                        && let Err(e) = push_output(ctx, &mut renderer, &message).await
+                    {
+                        tracing::error!("Query {conversation_key}: Error sending message: {:?}", e);
                     }
                 }
-            }
-            Err(e) => {
-                tracing::error!("Heroku MIA Error during agent call: {:?}", e);
-                if let Err(e) = last_message
-                    .reply(&ctx.http, "Error communicating with inference service.")
-                    .await
-                {
-                    tracing::error!("Error sending error message: {:?}", e);
+                Some(Err(e)) => {
+                    tracing::error!(
+                        "Query {conversation_key}: Heroku MIA Error during agent call: {:?}",
+                        e
+                    );
+                    if let Err(e) = renderer
+                        .push_paragraph(ctx, "Error communicating with inference service.")
+                        .await
+                    {
+                        tracing::error!(
+                            "Query {conversation_key}: Error sending error message: {:?}",
+                            e
+                        );
+                    }
+                    break;
                 }
+                None => break,
+            },
+            _ = renderer.edit_due(), if renderer.has_pending() => {
+                if let Err(e) = renderer.flush(ctx).await {
+                    tracing::error!("Query {conversation_key}: Error editing message: {:?}", e);
+                }
+            }
+            _ = stop.notified() => {
+                stopped = true;
                 break;
             }
         }
     }
+    // Dropping the stream cancels the agent call.
+    drop(stream);
+    if stopped && let Err(e) = renderer.push_paragraph(ctx, "*Stopped.*").await {
+        tracing::error!("Query {conversation_key}: Error sending message: {:?}", e);
+    }
+    let (prompt, turn) = {
+        let mut conversation = conversation_arc.lock().await;
+        settle_turn(&mut conversation);
+        let turn_start = conversation
+            .iter()
+            .rposition(|message| matches!(message, HerokuMiaMessage::User { .. }))
+            .map_or(0, |index| index + 1);
+        let (prompt, turn) = conversation.split_at(turn_start);
+        (prompt.to_vec(), turn.to_vec())
+    };
+    let answer = answer_text(&turn);
+    if let Err(e) = push_verification_warning(ctx, &mut renderer, conversation_key, &answer).await {
+        tracing::error!(
+            "Query {conversation_key}: Error sending the card check: {:?}",
+            e
+        );
+    }
+    if let Err(e) = push_card_embeds(ctx, &mut renderer, &turn, &answer).await {
+        tracing::error!(
+            "Query {conversation_key}: Error sending card embeds: {:?}",
+            e
+        );
+    }
 
+    if let Err(e) = renderer.finish(ctx).await {
+        tracing::error!("Query {conversation_key}: Error sending message: {:?}", e);
+    }
+    // Recorded before the buttons show, so they work as soon as they can be pressed.
+    answers::finish(
+        ctx,
+        conversation_key,
+        &stop,
+        renderer
+            .sent()
+            .last()
+            .map(|message_id| (channel_id, *message_id)),
+    )
+    .await;
+    let cut_off = stopped || stats.truncated.load(Ordering::Relaxed);
+    if let Err(e) = renderer
+        .set_components(ctx, answers::answered_buttons(conversation_key, cut_off))
+        .await
     {
-        let conversations_lock = type_map_keys::ConversationHistory::get(&ctx.data).await;
-        let mut conversations = conversations_lock.write().await;
-        let final_conversation = conversation_arc.lock().await;
-        conversations.insert(conversation_key, final_conversation.clone());
+        tracing::error!("Query {conversation_key}: Error adding buttons: {:?}", e);
+    }
+
+    let final_conversation = conversation_arc.lock().await.clone();
+    store_conversation(
+        ctx,
+        conversation_key,
+        final_conversation,
+        renderer.sent().to_vec(),
+    )
+    .await;
+    feedback::remember(
+        ctx,
+        Turn {
+            conversation_id: conversation_key,
+            message_ids: renderer.sent().iter().map(|id| id.get()).collect(),
+            model_id,
+            prompt,
+            response: turn,
+        },
+    )
+    .await;
+
+    limits::record(ctx, user_id, stats.tokens.load(Ordering::Relaxed)).await;
+}
+
+/// Saves a conversation's history, indexing the messages posted in it by the way.
+async fn store_conversation(
+    ctx: &Context,
+    conversation_key: u64,
+    conversation: Vec<HerokuMiaMessage>,
+    message_ids: impl IntoIterator<Item = MessageId>,
+) {
+    let conversations = type_map_keys::ConversationHistory::get(&ctx.data).await;
+    let mut conversations = conversations.write().await;
+    conversations.set_messages(conversation_key, conversation);
+    conversations.link(
+        conversation_key,
+        message_ids.into_iter().map(MessageId::get),
+    );
+}
+
+/// Posts an agent message through the output post-processor.
+async fn push_output(
+    ctx: &Context,
+    renderer: &mut StreamingMessage,
+    message: &str,
+) -> Result<(), serenity::Error> {
+    let linker = type_map_keys::CardLinker::get(&ctx.data).await;
+    match output::render(message, &linker) {
+        Output::Text(parts) => {
+            for part in parts {
+                match part {
+                    Part::Markdown(content) => renderer.push_paragraph(ctx, &content).await?,
+                    Part::Embeds { embeds, .. } => {
+                        renderer
+                            .push_message(ctx, CreateMessage::new().embeds(embeds))
+                            .await?
+                    }
+                }
+            }
+        }
+        Output::Attachment { summary, file } => {
+            renderer
+                .push_message(ctx, CreateMessage::new().content(summary).add_file(file))
+                .await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// The assistant messages of a turn.
+fn answer_text(turn: &[HerokuMiaMessage]) -> String {
+    turn.iter()
+        .filter_map(|message| match message {
+            HerokuMiaMessage::Assistant { content, .. } => Some(content.as_str()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Checks the card text and stats the answer gives against the card database, and follows up
+/// with the official text where they differ. Mismatches are logged to tune the prompt.
+async fn push_verification_warning(
+    ctx: &Context,
+    renderer: &mut StreamingMessage,
+    conversation_key: u64,
+    answer: &str,
+) -> Result<(), serenity::Error> {
+    let database = type_map_keys::CardDatabase::get(&ctx.data).await;
+    let linker = type_map_keys::CardLinker::get(&ctx.data).await;
+    let mismatches = verification::verify(answer, &linker, &database);
+    if mismatches.is_empty() {
+        return Ok(());
+    }
+
+    let mut warning =
+        "⚠️ **Card check:** some card details above don't match the card database.".to_string();
+    for mismatch in &mismatches {
+        tracing::warn!("Query {conversation_key}: Card mismatch: {:?}", mismatch);
+        warning.push_str("\n- ");
+        warning.push_str(&match mismatch {
+            Mismatch::Stat {
+                name,
+                stat,
+                claimed,
+                official,
+                ..
+            } => format!("**{name}** has {stat} {official}, not {claimed}."),
+            Mismatch::Text { name, official, .. } if official.is_empty() => {
+                format!("**{name}** has no card text.")
+            }
+            Mismatch::Text { name, official, .. } => format!(
+                "**{name}** reads: {}",
+                card_embed::clean_card_text(official).replace('\n', " ")
+            ),
+        });
+    }
+
+    renderer.push_paragraph(ctx, &warning).await
+}
+
+/// Attaches embeds for the cards returned by this turn's tool calls that the answer mentions.
+async fn push_card_embeds(
+    ctx: &Context,
+    renderer: &mut StreamingMessage,
+    turn: &[HerokuMiaMessage],
+    answer: &str,
+) -> Result<(), serenity::Error> {
+    let cards = card_embed::cards_in_tool_results(turn);
+    if cards.is_empty() {
+        return Ok(());
+    }
+
+    let referenced = card_embed::referenced_cards(answer, &cards);
+    let image_dir = type_map_keys::CardImageDir::get(&ctx.data).await;
+
+    for message in card_embed::card_embed_messages(&referenced, image_dir.as_deref()).await {
+        renderer.push_message(ctx, message).await?;
     }
 
     Ok(())
 }
 
+async fn create_conversation_thread(
+    ctx: &Context,
+    command: &CommandInteraction,
+    response: &SerenityMessage,
+    prompt: &str,
+    kind: ThreadKind,
+) -> Result<ChannelId, serenity::Error> {
+    let title = generate_thread_title(
+        &type_map_keys::HerokuMiaClient::get(&ctx.data).await,
+        &personas::agent_settings(ctx, command.channel_id)
+            .await
+            .model_id,
+        prompt,
+    )
+    .await;
+    let builder = CreateThread::new(title).kind(kind.channel_type());
+
+    let thread = match kind {
+        ThreadKind::Public => {
+            command
+                .channel_id
+                .create_thread_from_message(&ctx.http, response.id, builder)
+                .await?
+        }
+        ThreadKind::Private => {
+            let thread = command.channel_id.create_thread(&ctx.http, builder).await?;
+            thread
+                .id
+                .add_thread_member(&ctx.http, command.user.id)
+                .await?;
+            command
+                .edit_response(
+                    &ctx.http,
+                    EditInteractionResponse::new().content(format!(
+                        "Started a private conversation in <#{}>.",
+                        thread.id
+                    )),
+                )
+                .await?;
+            thread
+        }
+    };
+
+    Ok(thread.id)
+}
+
+/// Asks the model for a short thread title, falling back to the truncated prompt.
+async fn generate_thread_title(client: &Client, inference_model_id: &str, prompt: &str) -> String {
+    let request = ChatCompletionRequest::builder(
+        inference_model_id,
+        vec![
+            HerokuMiaMessage::System {
+                content: serde_json::Value::String("Write a title of at most six words for a Discord thread that starts with the user's question. Reply with the title only, without quotes or punctuation at the end.".to_string()),
+            },
+            HerokuMiaMessage::User {
+                content: prompt.to_string(),
+            },
+        ],
+    )
+    .max_tokens(32)
+    .build();
+
+    let title = match client.chat_completion(&request).await {
+        Ok(response) => response
+            .choices
+            .first()
+            .and_then(|choice| match &choice.message {
+                HerokuMiaMessage::Assistant { content, .. } => {
+                    Some(content.trim().trim_matches('"').to_string())
+                }
+                _ => None,
+            })
+            .filter(|title| !title.is_empty()),
+        Err(e) => {
+            tracing::error!("Heroku MIA Error generating thread title: {e}");
+            None
+        }
+    };
+
+    let name = title.as_deref().unwrap_or(prompt);
+    truncate(
+        name.lines().next().unwrap_or_default().trim(),
+        MAX_THREAD_NAME_LENGTH,
+    )
+}
+
 pub fn register() -> CreateCommand {
     CreateCommand::new("query")
         .description("Start a conversation with the Marvel Champions Agent")
@@ -98,15 +608,29 @@ pub fn register() -> CreateCommand {
             CreateCommandOption::new(CommandOptionType::String, "prompt", "Prompt for the Agent")
                 .required(true),
         )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "thread",
+                "Hold the conversation in a new thread",
+            )
+            .add_string_choice("Public thread", "public")
+            .add_string_choice("Private thread", "private"),
+        )
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::Boolean,
+            "owned_only",
+            "Only recommend cards from packs in your collection",
+        ))
 }
 
 pub(crate) async fn agents_call(
     client: &Client,
-    tools: Vec<AgentTool>,
-    inference_model_id: &str,
+    settings: AgentSettings,
     conversation: Arc<Mutex<Vec<HerokuMiaMessage>>>,
+    stats: Arc<TurnStats>,
 ) -> Pin<Box<dyn Stream<Item = Result<String, DiscordError>> + Send>> {
-    let initial_conversation_for_request: Vec<HerokuMiaMessage>;
+    let mut initial_conversation_for_request: Vec<HerokuMiaMessage>;
     {
         let mut conv_guard = conversation.lock().await;
         prune_conversation_history(
@@ -117,19 +641,49 @@ pub(crate) async fn agents_call(
         initial_conversation_for_request = conv_guard.clone();
     }
 
-    let request = AgentRequest::builder(inference_model_id, initial_conversation_for_request)
+    // Resolved without holding the conversation, which turns are already serialized on.
+    if !settings.local_tools.is_empty() {
+        match tools::resolve(
+            client,
+            &settings.model_id,
+            &initial_conversation_for_request,
+            &settings.local_tools,
+        )
+        .await
+        {
+            Ok((tool_messages, tool_tokens)) => {
+                conversation.lock().await.extend(tool_messages.clone());
+                initial_conversation_for_request.extend(tool_messages);
+                stats.tokens.fetch_add(tool_tokens, Ordering::Relaxed);
+            }
+            Err(e) => tracing::error!("Heroku MIA Error calling local tools: {e}"),
+        }
+    }
+
+    let mut request = AgentRequest::builder(&settings.model_id, initial_conversation_for_request)
         .max_tokens_per_inference_request(8192)
-        .tools(tools)
-        .build();
+        .tools(settings.tools);
+    if let Some(temperature) = settings.temperature {
+        request = request.temperature(temperature);
+    }
+    let request = request.build();
 
     let client_stream = client.agents_call(&request).await;
 
     Box::pin(client_stream.filter_map(move |message_result| {
         let conversation_clone_for_move = Arc::clone(&conversation);
+        let stats = Arc::clone(&stats);
         async move {
             match message_result {
                 Ok(message) => {
-                    if let Some(choice) = message.choices.get(0) {
+                    stats.tokens.fetch_add(
+                        u64::from(message.usage.total_tokens.unwrap_or_default()),
+                        Ordering::Relaxed,
+                    );
+                    if let Some(choice) = message.choices.first() {
+                        stats
+                            .truncated
+                            .store(choice.finish_reason == "length", Ordering::Relaxed);
                         let mut conv_guard = conversation_clone_for_move.lock().await;
                         conv_guard.push(choice.message.clone());
                         if let HerokuMiaMessage::Assistant { content, .. } = &choice.message {
@@ -147,27 +701,64 @@ pub(crate) async fn agents_call(
     }))
 }
 
-pub(crate) async fn get_original_message_id(
+/// The key of the conversation a reply continues. Messages are looked up in the conversation
+/// index, which only misses answers still being written; for those the reply chain is walked
+/// back to the first message a conversation knows.
+pub(crate) async fn find_conversation(
     ctx: &Context,
     msg: &SerenityMessage,
-) -> Result<MessageId, serenity::Error> {
+) -> Result<Option<u64>, serenity::Error> {
+    let conversations = type_map_keys::ConversationHistory::get(&ctx.data).await;
     let mut current_msg = msg.clone();
     while let Some(reference_message) = &current_msg.referenced_message {
-        current_msg = msg
+        if let Some(key) = conversations.read().await.find(reference_message.id.get()) {
+            return Ok(Some(key));
+        }
+        current_msg = current_msg
             .channel_id
             .message(&ctx.http, reference_message.id)
             .await?;
     }
 
-    Ok(current_msg.id)
+    Ok(None)
 }
 
-pub(crate) fn bootstrap_messages() -> Vec<HerokuMiaMessage> {
+/// Drops the tool calls a stopped or failed turn left unanswered, and gives a turn without an
+/// answer a placeholder one, so the conversation can go on.
+fn settle_turn(messages: &mut Vec<HerokuMiaMessage>) {
+    while messages.last().is_some_and(|message| match message {
+        HerokuMiaMessage::Tool { .. } => true,
+        HerokuMiaMessage::Assistant { tool_calls, .. } => {
+            tool_calls.as_ref().is_some_and(|calls| !calls.is_empty())
+        }
+        _ => false,
+    }) {
+        messages.pop();
+    }
+    if let Some(HerokuMiaMessage::User { .. }) = messages.last() {
+        messages.push(HerokuMiaMessage::Assistant {
+            content: "(No answer was given.)".to_string(),
+            refusal: None,
+            tool_calls: None,
+        });
+    }
+}
+
+pub(crate) fn bootstrap_messages(system_prompt: &str) -> Vec<HerokuMiaMessage> {
     vec![HerokuMiaMessage::System {
-        content: serde_json::Value::String("You are a helpful expert on the Marvel Champions card game with access to all the card, pack, and set data. When querying for data stick to only official cards. Hero sets or signature sets are identified by their SetId.".to_string()),
+        content: serde_json::Value::String(system_prompt.to_string()),
     }]
 }
 
+/// Appends `context` to the conversation's system prompt, which pruning never drops, so it stays
+/// available for the whole conversation.
+pub(crate) fn add_system_context(messages: &mut [HerokuMiaMessage], context: &str) {
+    if let Some(HerokuMiaMessage::System { content }) = messages.first_mut() {
+        let prompt = content.as_str().unwrap_or_default();
+        *content = serde_json::Value::String(format!("{prompt}\n\n{context}"));
+    }
+}
+
 fn prune_conversation_history(
     messages: &mut Vec<HerokuMiaMessage>,
     max_messages: usize,
@@ -176,10 +767,8 @@ fn prune_conversation_history(
     // message pruning
     if messages.len() > max_messages {
         let mut start_index = 0;
-        if let Some(first_msg) = messages.first() {
-            if let HerokuMiaMessage::System { .. } = first_msg {
-                start_index = 1; // Keep the system message
-            }
+        if let Some(HerokuMiaMessage::System { .. }) = messages.first() {
+            start_index = 1; // Keep the system message
         }
         let messages_to_remove = messages.len() - max_messages;
         if messages_to_remove > 0 {
@@ -206,44 +795,3 @@ fn prune_conversation_history(
         }
     }
 }
-
-fn split_message_into_chunks(message: &str) -> Vec<String> {
-    let mut chunks = Vec::new();
-    let mut current_chunk = String::new();
-    let mut in_code_block = false;
-
-    for line in message.lines() {
-        if line.trim().starts_with("```") {
-            in_code_block = !in_code_block;
-        }
-
-        // +1 for newline
-        if current_chunk.len() + line.len() + 1 > MAX_DISCORD_MESSAGE_LENGTH {
-            if !current_chunk.is_empty() {
-                chunks.push(current_chunk.trim().to_string());
-                current_chunk = String::new();
-            }
-
-            if line.len() > MAX_DISCORD_MESSAGE_LENGTH {
-                let mut remaining_line = line;
-                while remaining_line.len() > MAX_DISCORD_MESSAGE_LENGTH {
-                    let (part, rest) = remaining_line.split_at(MAX_DISCORD_MESSAGE_LENGTH);
-                    chunks.push(part.to_string());
-                    remaining_line = rest;
-                }
-                current_chunk.push_str(remaining_line);
-            } else {
-                current_chunk.push_str(line);
-            }
-        } else {
-            current_chunk.push_str(line);
-        }
-        current_chunk.push('\n');
-    }
-
-    if !current_chunk.is_empty() {
-        chunks.push(current_chunk.trim().to_string());
-    }
-
-    chunks
-}
@@ -0,0 +1,266 @@
+use serenity::all::{
+    ButtonStyle, CommandInteraction, CommandOptionType, ComponentInteraction, Context,
+    CreateActionRow, CreateButton, CreateCommand, CreateCommandOption, CreateEmbed,
+    CreateInteractionResponse, CreateInteractionResponseMessage,
+};
+use std::collections::HashSet;
+
+use crate::{
+    cards::{
+        CardDatabase,
+        randomizer::{self, Difficulty, MAX_HEROIC_LEVEL, MAX_PLAYERS, RandomizerOptions, Setup},
+    },
+    discord::type_map_keys,
+};
+
+pub(crate) const REROLL_BUTTON_ID: &str = "randomize:reroll";
+
+/// Draws a random setup and posts it with a button to reroll it. The options are kept by the
+/// message's id, so rerolls draw with the same filters.
+pub async fn run(
+    ctx: &Context,
+    command: &CommandInteraction,
+    options: RandomizerOptions,
+) -> Result<(), serenity::Error> {
+    let response = match draw(ctx, &options).await {
+        Ok(message) => message,
+        Err(message) => {
+            return command
+                .create_response(&ctx.http, CreateInteractionResponse::Message(message))
+                .await;
+        }
+    };
+
+    command
+        .create_response(&ctx.http, CreateInteractionResponse::Message(response))
+        .await?;
+    let message = command.get_response(&ctx.http).await?;
+
+    type_map_keys::Randomizations::get(&ctx.data)
+        .await
+        .write()
+        .await
+        .insert(message.id.get(), options);
+
+    Ok(())
+}
+
+/// Answers `owned:true` from a user who hasn't recorded any packs.
+pub async fn no_collection(
+    ctx: &Context,
+    command: &CommandInteraction,
+) -> Result<(), serenity::Error> {
+    let message = CreateInteractionResponseMessage::new()
+        .content("Your collection is empty. Add your packs with `/collection add` first.")
+        .ephemeral(true);
+    command
+        .create_response(&ctx.http, CreateInteractionResponse::Message(message))
+        .await
+}
+
+/// Replaces the setup with a new one drawn from the same options.
+pub async fn reroll(
+    ctx: &Context,
+    component: &ComponentInteraction,
+) -> Result<(), serenity::Error> {
+    let options = type_map_keys::Randomizations::get(&ctx.data)
+        .await
+        .read()
+        .await
+        .get(component.message.id.get())
+        .cloned();
+
+    let response = match options {
+        Some(options) => match draw(ctx, &options).await {
+            Ok(message) => CreateInteractionResponse::UpdateMessage(message),
+            Err(message) => CreateInteractionResponse::Message(message),
+        },
+        None => CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new()
+                .content("This setup can't be rerolled anymore, run /randomize again.")
+                .ephemeral(true),
+        ),
+    };
+
+    component.create_response(&ctx.http, response).await
+}
+
+/// Draws a setup, or returns an ephemeral explanation of why none matches the options.
+async fn draw(
+    ctx: &Context,
+    options: &RandomizerOptions,
+) -> Result<CreateInteractionResponseMessage, CreateInteractionResponseMessage> {
+    let database = type_map_keys::CardDatabase::get(&ctx.data).await;
+
+    match randomizer::randomize(&database, options, &mut rand::thread_rng()) {
+        Ok(setup) => Ok(CreateInteractionResponseMessage::new()
+            .embed(setup_embed(&setup, &database))
+            .components(vec![CreateActionRow::Buttons(vec![
+                CreateButton::new(REROLL_BUTTON_ID)
+                    .label("Reroll")
+                    .emoji('🎲')
+                    .style(ButtonStyle::Secondary),
+            ])])),
+        Err(e) => Err(CreateInteractionResponseMessage::new()
+            .content(format!("Could not randomize a setup: {e}"))
+            .ephemeral(true)),
+    }
+}
+
+fn setup_embed(setup: &Setup, database: &CardDatabase) -> CreateEmbed {
+    let encounter_sets = std::iter::once(&setup.villain)
+        .chain(&setup.modulars)
+        .chain(&setup.difficulty_sets)
+        .map(|set| format!("- {}", set.name))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let heroes = setup
+        .heroes
+        .iter()
+        .enumerate()
+        .map(|(player, (hero, aspects))| {
+            let aspects = aspects
+                .iter()
+                .map(|aspect| {
+                    database
+                        .faction(aspect)
+                        .map_or(aspect.as_str(), |faction| faction.name.as_str())
+                })
+                .collect::<Vec<_>>()
+                .join(" & ");
+            format!("{}. {} ({aspects})", player + 1, hero.name)
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    let difficulty = if setup.heroic > 0 {
+        format!("{} · Heroic {}", setup.difficulty, setup.heroic)
+    } else {
+        setup.difficulty.to_string()
+    };
+
+    // Discord rejects empty field values, and `modulars:0` leaves no modular sets.
+    let modulars = if setup.modulars.is_empty() {
+        "None".to_string()
+    } else {
+        setup
+            .modulars
+            .iter()
+            .map(|set| set.name.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
+    CreateEmbed::new()
+        .title(format!("{} scenario", setup.villain.name))
+        .field("Villain", &setup.villain.name, true)
+        .field("Difficulty", difficulty, true)
+        .field("Modular sets", modulars, false)
+        .field("Heroes", heroes, false)
+        .field("Encounter deck", encounter_sets, false)
+}
+
+/// Reads the command's options into the randomizer's options.
+pub(crate) fn options(command: &CommandInteraction, database: &CardDatabase) -> RandomizerOptions {
+    let option = |name: &str| {
+        command
+            .data
+            .options
+            .iter()
+            .find(|option| option.name == name)
+            .map(|option| &option.value)
+    };
+    let list = |name: &str| -> Vec<String> {
+        option(name)
+            .and_then(|value| value.as_str())
+            .map(|value| {
+                value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|item| !item.is_empty())
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+
+    let packs = list("packs");
+    let packs = (!packs.is_empty()).then(|| pack_codes(&packs, database));
+    let defaults = RandomizerOptions::default();
+
+    RandomizerOptions {
+        players: option("players")
+            .and_then(|value| value.as_i64())
+            .map_or(defaults.players, |players| players as usize),
+        modulars: option("modulars")
+            .and_then(|value| value.as_i64())
+            .map_or(defaults.modulars, |modulars| modulars as usize),
+        difficulty: option("difficulty")
+            .and_then(|value| value.as_str())
+            .and_then(|value| match value {
+                "standard" => Some(Difficulty::Standard),
+                "expert" => Some(Difficulty::Expert),
+                _ => None,
+            }),
+        max_heroic: option("heroic")
+            .and_then(|value| value.as_i64())
+            .map_or(defaults.max_heroic, |heroic| heroic as u32),
+        packs,
+        exclude: list("exclude"),
+    }
+}
+
+/// Resolves pack names or codes to pack codes.
+fn pack_codes(packs: &[String], database: &CardDatabase) -> HashSet<String> {
+    packs
+        .iter()
+        .filter_map(|pack| database.find_pack(pack).map(|known| known.code.clone()))
+        .collect()
+}
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("randomize")
+        .description("Draw a random scenario, difficulty, heroes and aspects")
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::Integer, "players", "Number of players")
+                .min_int_value(1)
+                .max_int_value(MAX_PLAYERS as u64),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::Integer,
+                "modulars",
+                "Number of modular sets, 1 by default",
+            )
+            .min_int_value(0)
+            .max_int_value(4),
+        )
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::String, "difficulty", "Difficulty")
+                .add_string_choice("Standard", "standard")
+                .add_string_choice("Expert", "expert"),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::Integer,
+                "heroic",
+                "Highest heroic level to draw, 0 by default",
+            )
+            .min_int_value(0)
+            .max_int_value(u64::from(MAX_HEROIC_LEVEL)),
+        )
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::String,
+            "packs",
+            "Comma-separated packs to draw from, every pack by default",
+        ))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::String,
+            "exclude",
+            "Comma-separated villains, sets, heroes or aspects to leave out",
+        ))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::Boolean,
+            "owned",
+            "Only draw from packs in your collection",
+        ))
+}
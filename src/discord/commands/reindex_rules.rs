@@ -0,0 +1,33 @@
+use serenity::all::{CommandInteraction, Context, CreateCommand, Permissions};
+
+use crate::discord::{commands::deck::edit_content, type_map_keys};
+
+/// Chunks and embeds the rules documents again, e.g. after a new FAQ is added.
+pub async fn run(ctx: &Context, command: &CommandInteraction) -> Result<(), serenity::Error> {
+    command.defer_ephemeral(&ctx.http).await?;
+
+    let Some(rules) = type_map_keys::Rules::get(&ctx.data).await else {
+        return edit_content(
+            ctx,
+            command,
+            "Rules retrieval isn't configured, set RULES_DIR and the embedding model.",
+        )
+        .await;
+    };
+
+    let content = match rules.reindex().await {
+        Ok(count) => format!("Indexed {count} rules passages."),
+        Err(e) => {
+            tracing::error!("Reindex Rules: Error indexing the rules: {:?}", e);
+            format!("Could not index the rules: {e}")
+        }
+    };
+
+    edit_content(ctx, command, &content).await
+}
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("reindex-rules")
+        .description("Index the rules reference and FAQ documents again")
+        .default_member_permissions(Permissions::ADMINISTRATOR)
+}
@@ -0,0 +1,63 @@
+use serenity::all::{
+    CommandInteraction, CommandOptionType, Context, CreateCommand, CreateCommandOption,
+};
+
+use crate::{
+    cards::validation,
+    discord::{
+        commands::deck::{edit_content, import},
+        type_map_keys,
+    },
+};
+
+/// Checks a deck against the deckbuilding rules and lists every rule it breaks.
+pub async fn run(
+    ctx: &Context,
+    command: &CommandInteraction,
+    deck: &str,
+) -> Result<(), serenity::Error> {
+    command.defer(&ctx.http).await?;
+
+    let deck = match import(ctx, deck).await {
+        Ok(deck) => deck,
+        Err(e) => {
+            tracing::error!("Validate Deck: Error importing {deck:?}: {e:?}");
+            return edit_content(ctx, command, &format!("Could not import the deck: {e}")).await;
+        }
+    };
+
+    let database = type_map_keys::CardDatabase::get(&ctx.data).await;
+    let name = deck.name.as_deref().unwrap_or("The deck");
+    let violations = validation::validate(&deck, &database);
+
+    let mut content = if violations.is_empty() {
+        format!("✅ **{name}** is legal.")
+    } else {
+        let mut content = format!("❌ **{name}** is not legal:");
+        for violation in &violations {
+            content.push_str(&format!("\n- {violation}"));
+        }
+        content
+    };
+    if !deck.unresolved.is_empty() {
+        content.push_str(&format!(
+            "\n\nCould not find: {}",
+            deck.unresolved.join(", ")
+        ));
+    }
+
+    edit_content(ctx, command, &content).await
+}
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("validate-deck")
+        .description("Check a deck against the deckbuilding rules")
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "deck",
+                "MarvelCDB deck id or URL, or a pasted deck list",
+            )
+            .required(true),
+        )
+}
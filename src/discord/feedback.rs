@@ -0,0 +1,70 @@
+use serenity::all::{Context, Reaction, ReactionType};
+
+use crate::{
+    discord::type_map_keys,
+    feedback::{Feedback, FeedbackEvent, Turn, Vote},
+    limits,
+};
+
+/// Keeps an answer with its conversation so reactions to its messages can be traced back to it.
+pub(crate) async fn remember(ctx: &Context, turn: Turn) {
+    if turn.message_ids.is_empty() {
+        return;
+    }
+
+    type_map_keys::ConversationHistory::get(&ctx.data)
+        .await
+        .write()
+        .await
+        .add_turn(turn.conversation_id, turn);
+}
+
+/// Records a 👍 or 👎 on one of the bot's answers, or removes it when the reaction is taken back.
+pub(crate) async fn react(ctx: &Context, reaction: &Reaction, added: bool) {
+    let ReactionType::Unicode(emoji) = &reaction.emoji else {
+        return;
+    };
+    let (Some(vote), Some(user_id)) = (Vote::from_emoji(emoji), reaction.user_id) else {
+        return;
+    };
+    if user_id == ctx.cache.current_user().id {
+        return;
+    }
+    let Some(turn) = type_map_keys::ConversationHistory::get(&ctx.data)
+        .await
+        .read()
+        .await
+        .turn(reaction.message_id.get())
+    else {
+        return;
+    };
+
+    let feedback = Feedback::new(
+        &turn,
+        reaction.message_id.get(),
+        user_id.get(),
+        vote,
+        limits::unix_now(),
+    );
+    tracing::info!(
+        "Feedback {}: {user_id} {} {:?} on turn {}",
+        feedback.conversation_id,
+        if added { "voted" } else { "took back" },
+        vote,
+        feedback.turn_id
+    );
+    let event = if added {
+        FeedbackEvent::Rated(feedback)
+    } else {
+        FeedbackEvent::Retracted {
+            turn_id: feedback.turn_id,
+            user_id: feedback.user_id,
+            vote,
+            retracted_at: feedback.rated_at,
+        }
+    };
+    let log = type_map_keys::Feedback::get(&ctx.data).await;
+    if let Err(e) = log.append(event).await {
+        tracing::error!("Feedback: Error saving feedback: {:?}", e);
+    }
+}
@@ -0,0 +1,173 @@
+use serenity::all::{
+    CommandInteraction, ComponentInteraction, Context, CreateInteractionResponse,
+    CreateInteractionResponseMessage, CreateMessage, GuildId, Message as SerenityMessage, RoleId,
+    UserId,
+};
+use std::time::Instant;
+
+use crate::{
+    discord::type_map_keys,
+    limits::{self, Limited, Scope},
+};
+
+/// Takes a request from the rate limits of the user, the channel and the guild, if the user has
+/// tokens left today.
+pub(crate) async fn check(
+    ctx: &Context,
+    user_id: UserId,
+    channel_id: u64,
+    guild_id: Option<GuildId>,
+    roles: &[RoleId],
+) -> Result<(), Limited> {
+    let limiter = type_map_keys::RateLimiter::get(&ctx.data).await;
+    let mut limiter = limiter.lock().await;
+
+    let roles: Vec<u64> = roles.iter().map(|role| role.get()).collect();
+    let quota = limiter.config().quota(&roles);
+    type_map_keys::Usage::get(&ctx.data)
+        .await
+        .read()
+        .await
+        .check(user_id.get(), quota, limits::today())?;
+
+    limiter.check(
+        user_id.get(),
+        channel_id,
+        guild_id.map(GuildId::get),
+        Instant::now(),
+    )
+}
+
+/// Checks the limits for a command, answering it with an explanation only the user sees if they
+/// are over them. Returns whether the command may go ahead.
+pub(crate) async fn check_command(
+    ctx: &Context,
+    command: &CommandInteraction,
+) -> Result<bool, serenity::Error> {
+    let roles = command
+        .member
+        .as_ref()
+        .map(|member| member.roles.clone())
+        .unwrap_or_default();
+    let Err(limited) = check(
+        ctx,
+        command.user.id,
+        command.channel_id.get(),
+        command.guild_id,
+        &roles,
+    )
+    .await
+    else {
+        return Ok(true);
+    };
+
+    tracing::info!("Limits: {} turned away: {:?}", command.user.id, limited);
+    command
+        .create_response(
+            &ctx.http,
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content(explain(&limited))
+                    .ephemeral(true),
+            ),
+        )
+        .await?;
+    Ok(false)
+}
+
+/// Checks the limits for a button that starts an answer, like [`check_command`] does for a
+/// command.
+pub(crate) async fn check_component(
+    ctx: &Context,
+    component: &ComponentInteraction,
+) -> Result<bool, serenity::Error> {
+    let roles = component
+        .member
+        .as_ref()
+        .map(|member| member.roles.clone())
+        .unwrap_or_default();
+    let Err(limited) = check(
+        ctx,
+        component.user.id,
+        component.channel_id.get(),
+        component.guild_id,
+        &roles,
+    )
+    .await
+    else {
+        return Ok(true);
+    };
+
+    tracing::info!("Limits: {} turned away: {:?}", component.user.id, limited);
+    component
+        .create_response(
+            &ctx.http,
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content(explain(&limited))
+                    .ephemeral(true),
+            ),
+        )
+        .await?;
+    Ok(false)
+}
+
+/// Checks the limits for a follow-up message. Messages can't be answered ephemerally, so the
+/// explanation is sent as a direct message. Returns whether the follow-up may go ahead.
+pub(crate) async fn check_message(ctx: &Context, msg: &SerenityMessage) -> bool {
+    let roles = msg
+        .member
+        .as_ref()
+        .map(|member| member.roles.clone())
+        .unwrap_or_default();
+    let Err(limited) = check(
+        ctx,
+        msg.author.id,
+        msg.channel_id.get(),
+        msg.guild_id,
+        &roles,
+    )
+    .await
+    else {
+        return true;
+    };
+
+    tracing::info!("Limits: {} turned away: {:?}", msg.author.id, limited);
+    if let Err(e) = msg
+        .author
+        .direct_message(&ctx.http, CreateMessage::new().content(explain(&limited)))
+        .await
+    {
+        tracing::error!("Limits: Error sending the explanation: {:?}", e);
+    }
+    false
+}
+
+/// Counts tokens spent on a user's request towards their daily quota.
+pub(crate) async fn record(ctx: &Context, user_id: UserId, tokens: u64) {
+    let usage = type_map_keys::Usage::get(&ctx.data).await;
+    if let Err(e) = usage
+        .update(|usage| usage.add(user_id.get(), tokens, limits::today()))
+        .await
+    {
+        tracing::error!("Limits: Error saving usage: {:?}", e);
+    }
+}
+
+fn explain(limited: &Limited) -> String {
+    match limited {
+        Limited::Rate { scope, wait } => {
+            let retry = limits::unix_now() + wait.as_secs() + 1;
+            let who = match scope {
+                Scope::User => "You're asking questions",
+                Scope::Channel => "This channel is asking questions",
+                Scope::Guild => "The server is asking questions",
+            };
+            format!("{who} faster than I can keep up with. Please try again <t:{retry}:R>.")
+        }
+        Limited::Quota { quota } => format!(
+            "You've used your {quota} tokens for today. Your quota resets <t:{}:R>.",
+            limits::tomorrow()
+        ),
+    }
+}
@@ -0,0 +1,629 @@
+use unicode_segmentation::UnicodeSegmentation;
+
+pub(crate) const MAX_DISCORD_MESSAGE_LENGTH: usize = 2000;
+
+const FENCE: &str = "```";
+const CLOSING_FENCE: &str = "\n```";
+
+/// Splits a Markdown message into chunks of at most `max_length` characters.
+///
+/// Chunks break between lines where possible. Code fences that span a break are closed and
+/// reopened with the same language tag, list items and quote blocks are kept in one chunk when
+/// they fit, and lines that are too long on their own are split on grapheme boundaries.
+pub(crate) fn split_message(message: &str, max_length: usize) -> Vec<String> {
+    let mut splitter = Splitter::new(max_length);
+    let lines: Vec<&str> = message.lines().collect();
+
+    let mut start = 0;
+    while start < lines.len() {
+        let end = if splitter.fence.is_some() {
+            start + 1
+        } else {
+            block_end(&lines, start)
+        };
+        splitter.push_block(&lines[start..end]);
+        start = end;
+    }
+
+    splitter.finish()
+}
+
+/// Returns the end of the list item or quote block starting at `start`.
+fn block_end(lines: &[&str], start: usize) -> usize {
+    let mut end = start + 1;
+    if is_list_item(lines[start]) {
+        while end < lines.len() && is_item_continuation(lines[end]) {
+            end += 1;
+        }
+    } else if is_quote(lines[start]) {
+        while end < lines.len() && is_quote(lines[end]) {
+            end += 1;
+        }
+    }
+
+    end
+}
+
+fn is_fence(line: &str) -> bool {
+    line.trim_start().starts_with(FENCE)
+}
+
+fn is_quote(line: &str) -> bool {
+    line.trim_start().starts_with('>')
+}
+
+fn is_list_item(line: &str) -> bool {
+    let line = line.trim_start();
+    if line.starts_with("- ") || line.starts_with("* ") || line.starts_with("+ ") {
+        return true;
+    }
+
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+    digits > 0 && (line[digits..].starts_with(". ") || line[digits..].starts_with(") "))
+}
+
+fn is_item_continuation(line: &str) -> bool {
+    !line.trim().is_empty() && line.starts_with([' ', '\t'])
+}
+
+fn char_count(text: &str) -> usize {
+    text.chars().count()
+}
+
+/// Shortens `text` to at most `max_chars` characters, marking the cut with an ellipsis.
+pub(crate) fn truncate(text: &str, max_chars: usize) -> String {
+    if char_count(text) <= max_chars {
+        return text.to_string();
+    }
+
+    let mut truncated: String = text.chars().take(max_chars.saturating_sub(1)).collect();
+    truncated.push('…');
+    truncated
+}
+
+/// Splits `text` so the head has at most `capacity` characters, without breaking a grapheme
+/// cluster and preferring whitespace. The head always holds at least one grapheme.
+fn split_graphemes(text: &str, capacity: usize) -> (&str, &str) {
+    let mut length = 0;
+    let mut end = 0;
+    let mut last_whitespace = None;
+
+    for (index, grapheme) in text.grapheme_indices(true) {
+        let grapheme_length = char_count(grapheme);
+        if length + grapheme_length > capacity && end > 0 {
+            break;
+        }
+        if grapheme.chars().all(char::is_whitespace) && length >= capacity / 2 {
+            last_whitespace = Some(index);
+        }
+        length += grapheme_length;
+        end = index + grapheme.len();
+    }
+
+    if end < text.len()
+        && let Some(whitespace) = last_whitespace
+    {
+        end = whitespace;
+    }
+
+    text.split_at(end)
+}
+
+struct Splitter {
+    max_length: usize,
+    chunks: Vec<String>,
+    current: String,
+    current_length: usize,
+    /// Whether the current chunk holds nothing but a reopened fence.
+    fresh: bool,
+    /// The opening line of the code fence the current chunk is inside of.
+    fence: Option<String>,
+}
+
+impl Splitter {
+    fn new(max_length: usize) -> Self {
+        Self {
+            max_length,
+            chunks: Vec::new(),
+            current: String::new(),
+            current_length: 0,
+            fresh: true,
+            fence: None,
+        }
+    }
+
+    fn push_block(&mut self, lines: &[&str]) {
+        if lines.len() > 1 && !self.fresh {
+            let length = lines.iter().map(|line| char_count(line) + 1).sum();
+            if !self.fits(length, &self.fence) {
+                self.break_chunk();
+            }
+        }
+
+        for line in lines {
+            self.push_line(line);
+        }
+    }
+
+    fn push_line(&mut self, line: &str) {
+        if self.fresh && self.fence.is_none() && line.trim().is_empty() {
+            return;
+        }
+
+        let fence_after = if !is_fence(line) {
+            self.fence.clone()
+        } else if self.fence.is_some() {
+            None
+        } else {
+            Some(line.trim().to_string())
+        };
+
+        if !self.fits(self.line_cost(line), &fence_after) && !self.fresh {
+            self.break_chunk();
+        }
+
+        if self.fits(self.line_cost(line), &fence_after) {
+            self.append(line);
+        } else {
+            self.push_long_line(line, &fence_after);
+        }
+        self.fence = fence_after;
+    }
+
+    /// Hard splits a line that doesn't fit into an empty chunk, repeating any quote marker on
+    /// the continuation lines.
+    fn push_long_line(&mut self, line: &str, fence_after: &Option<String>) {
+        let prefix = if self.fence.is_none() && is_quote(line) {
+            "> "
+        } else {
+            ""
+        };
+        let mut rest = line;
+        let mut first = true;
+
+        loop {
+            let piece = if first {
+                rest.to_string()
+            } else {
+                format!("{prefix}{rest}")
+            };
+            if self.fits(self.line_cost(&piece), fence_after) {
+                self.append(&piece);
+                return;
+            }
+
+            let reserve = if self.fence.is_some() {
+                char_count(CLOSING_FENCE)
+            } else {
+                0
+            };
+            let prefix_length = if first { 0 } else { char_count(prefix) };
+            let capacity = self
+                .max_length
+                .saturating_sub(self.line_cost("") + reserve + prefix_length)
+                .max(1);
+            let (head, tail) = split_graphemes(rest, capacity);
+
+            let head = if first {
+                head.to_string()
+            } else {
+                format!("{prefix}{head}")
+            };
+            self.append(&head);
+            self.break_chunk();
+
+            rest = tail.trim_start();
+            first = false;
+            if rest.is_empty() {
+                return;
+            }
+        }
+    }
+
+    fn line_cost(&self, line: &str) -> usize {
+        let newline = if self.current.is_empty() { 0 } else { 1 };
+        char_count(line) + newline
+    }
+
+    fn fits(&self, length: usize, fence: &Option<String>) -> bool {
+        let reserve = if fence.is_some() {
+            char_count(CLOSING_FENCE)
+        } else {
+            0
+        };
+        self.current_length + length + reserve <= self.max_length
+    }
+
+    fn append(&mut self, line: &str) {
+        if !self.current.is_empty() {
+            self.current.push('\n');
+            self.current_length += 1;
+        }
+        self.current.push_str(line);
+        self.current_length += char_count(line);
+        self.fresh = false;
+    }
+
+    fn break_chunk(&mut self) {
+        if self.fence.is_some() {
+            self.current.push_str(CLOSING_FENCE);
+        }
+        self.push_chunk();
+
+        self.current = self.fence.clone().unwrap_or_default();
+        self.current_length = char_count(&self.current);
+        self.fresh = true;
+    }
+
+    fn push_chunk(&mut self) {
+        let chunk = self.current.trim_end();
+        if !chunk.trim().is_empty() {
+            self.chunks.push(chunk.to_string());
+        }
+    }
+
+    fn finish(mut self) -> Vec<String> {
+        if !self.fresh {
+            self.push_chunk();
+        }
+
+        self.chunks
+    }
+}
+
+/// Column alignment of a Markdown table.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Alignment {
+    Left,
+    Center,
+    Right,
+}
+
+/// A GitHub-flavored Markdown table, which Discord doesn't render.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Table {
+    pub header: Vec<String>,
+    pub alignments: Vec<Alignment>,
+    pub rows: Vec<Vec<String>>,
+}
+
+/// A run of a message that is either plain Markdown or a table.
+#[derive(Debug, PartialEq)]
+pub(crate) enum Block {
+    Text(String),
+    Table(Table),
+}
+
+/// Separates the tables of a message from the Markdown around them. Tables inside code blocks
+/// are left alone.
+pub(crate) fn extract_tables(message: &str) -> Vec<Block> {
+    let lines: Vec<&str> = message.lines().collect();
+    let mut blocks = Vec::new();
+    let mut text: Vec<&str> = Vec::new();
+    let mut in_code_block = false;
+
+    let mut index = 0;
+    while index < lines.len() {
+        let line = lines[index];
+        if is_fence(line) {
+            in_code_block = !in_code_block;
+        }
+
+        if !in_code_block
+            && index + 1 < lines.len()
+            && line.contains('|')
+            && let Some(alignments) = parse_separator(lines[index + 1])
+        {
+            let header = parse_row(line);
+            if header.len() == alignments.len() {
+                let mut end = index + 2;
+                let mut rows = Vec::new();
+                while end < lines.len() && is_table_row(lines[end], header.len()) {
+                    let mut row = parse_row(lines[end]);
+                    row.resize(header.len(), String::new());
+                    rows.push(row);
+                    end += 1;
+                }
+
+                if !text.is_empty() {
+                    blocks.push(Block::Text(text.join("\n")));
+                    text.clear();
+                }
+                blocks.push(Block::Table(Table {
+                    header,
+                    alignments,
+                    rows,
+                }));
+                index = end;
+                continue;
+            }
+        }
+
+        text.push(line);
+        index += 1;
+    }
+
+    if !text.is_empty() {
+        blocks.push(Block::Text(text.join("\n")));
+    }
+
+    blocks
+}
+
+/// Whether a line continues a table of `columns` columns: it must be fenced by a pipe on
+/// either side, or split into as many cells as the header.
+fn is_table_row(line: &str, columns: usize) -> bool {
+    let line = line.trim();
+    line.contains('|')
+        && (line.starts_with('|') || line.ends_with('|') || parse_row(line).len() == columns)
+}
+
+fn parse_separator(line: &str) -> Option<Vec<Alignment>> {
+    let cells = parse_row(line);
+    if !line.contains('-') || cells.is_empty() {
+        return None;
+    }
+
+    cells
+        .iter()
+        .map(|cell| {
+            let dashes = cell.trim_matches(':');
+            if dashes.is_empty() || !dashes.chars().all(|c| c == '-') {
+                return None;
+            }
+            Some(match (cell.starts_with(':'), cell.ends_with(':')) {
+                (true, true) => Alignment::Center,
+                (false, true) => Alignment::Right,
+                _ => Alignment::Left,
+            })
+        })
+        .collect()
+}
+
+fn parse_row(line: &str) -> Vec<String> {
+    let line = line.trim();
+    let line = line.strip_prefix('|').unwrap_or(line);
+    let line = line.strip_suffix('|').unwrap_or(line);
+
+    let mut cells = vec![String::new()];
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek() == Some(&'|') => {
+                cells.last_mut().unwrap().push('|');
+                chars.next();
+            }
+            '|' => cells.push(String::new()),
+            _ => cells.last_mut().unwrap().push(c),
+        }
+    }
+
+    cells.iter().map(|cell| cell.trim().to_string()).collect()
+}
+
+/// Removes the inline Markdown that would show up verbatim in a code block.
+pub(crate) fn strip_inline_markdown(text: &str) -> String {
+    text.replace("**", "").replace("__", "").replace('`', "")
+}
+
+impl Table {
+    /// Renders the table as an aligned monospace code block.
+    pub fn to_code_block(&self) -> String {
+        let header: Vec<String> = self
+            .header
+            .iter()
+            .map(|c| strip_inline_markdown(c))
+            .collect();
+        let rows: Vec<Vec<String>> = self
+            .rows
+            .iter()
+            .map(|row| row.iter().map(|c| strip_inline_markdown(c)).collect())
+            .collect();
+
+        let widths: Vec<usize> = (0..header.len())
+            .map(|column| {
+                std::iter::once(&header)
+                    .chain(rows.iter())
+                    .map(|row| char_count(&row[column]))
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+
+        let mut lines = vec![self.format_row(&header, &widths)];
+        lines.push(
+            widths
+                .iter()
+                .map(|width| "-".repeat(*width))
+                .collect::<Vec<_>>()
+                .join("-+-"),
+        );
+        lines.extend(rows.iter().map(|row| self.format_row(row, &widths)));
+
+        format!("```\n{}\n```", lines.join("\n"))
+    }
+
+    /// The width in characters of a line of [`Table::to_code_block`].
+    pub fn width(&self) -> usize {
+        let columns = self.header.len();
+        (0..columns)
+            .map(|column| {
+                std::iter::once(&self.header)
+                    .chain(self.rows.iter())
+                    .map(|row| char_count(&strip_inline_markdown(&row[column])))
+                    .max()
+                    .unwrap_or(0)
+            })
+            .sum::<usize>()
+            + columns.saturating_sub(1) * 3
+    }
+
+    fn format_row(&self, row: &[String], widths: &[usize]) -> String {
+        row.iter()
+            .zip(widths)
+            .zip(&self.alignments)
+            .map(|((cell, width), alignment)| match alignment {
+                Alignment::Left => format!("{cell:<width$}"),
+                Alignment::Center => format!("{cell:^width$}"),
+                Alignment::Right => format!("{cell:>width$}"),
+            })
+            .collect::<Vec<_>>()
+            .join(" | ")
+            .trim_end()
+            .to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_short_message_is_one_chunk() {
+        let chunks = split_message("Hello\n\nWorld", 2000);
+        assert_eq!(chunks, vec!["Hello\n\nWorld".to_string()]);
+    }
+
+    #[test]
+    fn test_splits_between_lines() {
+        let chunks = split_message("aaaa\nbbbb\ncccc", 10);
+        assert_eq!(chunks, vec!["aaaa\nbbbb", "cccc"]);
+    }
+
+    #[test]
+    fn test_reopens_code_fence_with_language() {
+        let message = "Intro\n```rust\nlet a = 1;\nlet b = 2;\nlet c = 3;\n```\nOutro";
+        let chunks = split_message(message, 30);
+
+        for chunk in &chunks {
+            assert!(char_count(chunk) <= 30, "{chunk:?} is too long");
+            assert_eq!(
+                chunk.matches(FENCE).count() % 2,
+                0,
+                "{chunk:?} has an open fence"
+            );
+        }
+        assert_eq!(
+            chunks,
+            vec![
+                "Intro\n```rust\nlet a = 1;\n```",
+                "```rust\nlet b = 2;\n```",
+                "```rust\nlet c = 3;\n```\nOutro",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_counts_characters_not_bytes() {
+        let message = "é".repeat(2000);
+        let chunks = split_message(&message, 2000);
+        assert_eq!(chunks, vec![message]);
+    }
+
+    #[test]
+    fn test_long_line_splits_on_char_boundaries() {
+        let message = "é".repeat(2500);
+        let chunks = split_message(&message, 2000);
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(char_count(&chunks[0]), 2000);
+        assert_eq!(char_count(&chunks[1]), 500);
+    }
+
+    #[test]
+    fn test_long_line_keeps_graphemes_whole() {
+        let family = "👨‍👩‍👧";
+        let message = family.repeat(3);
+        let chunks = split_message(&message, 7);
+        assert_eq!(chunks, vec![family.to_string(); 3]);
+    }
+
+    #[test]
+    fn test_long_line_prefers_whitespace() {
+        let chunks = split_message("alpha beta gamma", 12);
+        assert_eq!(chunks, vec!["alpha beta", "gamma"]);
+    }
+
+    #[test]
+    fn test_keeps_list_item_together() {
+        let message = "- first\n- second item\n  continued here";
+        let chunks = split_message(message, 30);
+        assert_eq!(chunks, vec!["- first", "- second item\n  continued here"]);
+    }
+
+    #[test]
+    fn test_repeats_quote_marker_on_long_quote_line() {
+        let chunks = split_message("> one two three four", 12);
+        for chunk in &chunks {
+            assert!(chunk.starts_with('>'), "{chunk:?} lost its quote marker");
+        }
+        assert_eq!(chunks.join(" ").replace("> ", ""), "one two three four");
+    }
+
+    #[test]
+    fn test_truncate() {
+        assert_eq!(truncate("Spider-Man", 10), "Spider-Man");
+        assert_eq!(truncate("Spider-Woman", 10), "Spider-Wo…");
+        assert_eq!(truncate("éééé", 3), "éé…");
+    }
+
+    #[test]
+    fn test_extract_tables() {
+        let message = "Compare:\n| Card | Cost |\n|:-----|-----:|\n| Haymaker | 2 |\n| Swinging Web Kick | 3 |\nDone.";
+        let blocks = extract_tables(message);
+
+        assert_eq!(
+            blocks,
+            vec![
+                Block::Text("Compare:".to_string()),
+                Block::Table(Table {
+                    header: vec!["Card".to_string(), "Cost".to_string()],
+                    alignments: vec![Alignment::Left, Alignment::Right],
+                    rows: vec![
+                        vec!["Haymaker".to_string(), "2".to_string()],
+                        vec!["Swinging Web Kick".to_string(), "3".to_string()],
+                    ],
+                }),
+                Block::Text("Done.".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_extract_tables_ends_at_prose_with_a_pipe() {
+        let message = "| Card | Cost | Type |\n|---|---|---|\n| Haymaker | 2 | Event |\nPlay it with Spider-Man | Peter Parker.";
+        let blocks = extract_tables(message);
+
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(
+            blocks[1],
+            Block::Text("Play it with Spider-Man | Peter Parker.".to_string())
+        );
+    }
+
+    #[test]
+    fn test_extract_tables_ignores_code_blocks() {
+        let message = "```\n| a | b |\n|---|---|\n```";
+        assert_eq!(
+            extract_tables(message),
+            vec![Block::Text(message.to_string())]
+        );
+    }
+
+    #[test]
+    fn test_table_to_code_block() {
+        let table = Table {
+            header: vec!["Card".to_string(), "Cost".to_string()],
+            alignments: vec![Alignment::Left, Alignment::Right],
+            rows: vec![
+                vec!["**Haymaker**".to_string(), "2".to_string()],
+                vec!["Swinging Web Kick".to_string(), "3".to_string()],
+            ],
+        };
+
+        assert_eq!(
+            table.to_code_block(),
+            "```\nCard              | Cost\n------------------+-----\nHaymaker          |    2\nSwinging Web Kick |    3\n```"
+        );
+        assert_eq!(table.width(), 24);
+    }
+}
@@ -1,21 +1,31 @@
-use commands::query::get_original_message_id;
-use futures::StreamExt;
+use commands::query::{ThreadKind, find_conversation};
 use serenity::{
     all::{
-        CommandDataOptionValue, Context, EventHandler, Interaction, Message as SerenityMessage,
-        Ready,
+        CommandInteraction, Context, EventHandler, Interaction, Message as SerenityMessage,
+        Reaction, Ready,
     },
     async_trait,
 };
-use std::sync::Arc;
+use streaming::ReplyTarget;
 use thiserror::Error;
-use tokio::sync::Mutex;
 
-use crate::heroku_mia::{self, types::Message as HerokuMiaMessage};
+use crate::{game, heroku_mia};
 
+pub(crate) mod answers;
+mod card_embed;
 mod commands;
+mod feedback;
+mod limits;
+mod markdown;
+mod output;
+mod personas;
+mod queue;
+mod streaming;
 pub(crate) mod type_map_keys;
 
+/// The system prompt conversations start with unless `/karen prompt set` replaced it.
+pub(crate) const DEFAULT_SYSTEM_PROMPT: &str = "You are a helpful expert on the Marvel Champions card game with access to all the card, pack, and set data. When querying for data stick to only official cards. Hero sets or signature sets are identified by their SetId.";
+
 #[derive(Error, Debug)]
 pub(crate) enum DiscordError {
     #[error("No Such Command {0}")]
@@ -38,7 +48,22 @@ impl EventHandler for Handler {
         let guild_id = type_map_keys::GuildId::get(&ctx.data).await;
 
         let commands = guild_id
-            .set_commands(&ctx.http, vec![commands::query::register()])
+            .set_commands(
+                &ctx.http,
+                vec![
+                    commands::query::register(),
+                    commands::card::register(),
+                    commands::deck::register(),
+                    commands::validate_deck::register(),
+                    commands::odds::register(),
+                    commands::randomize::register(),
+                    commands::collection::register(),
+                    commands::campaign::register(),
+                    commands::game::register(),
+                    commands::reindex_rules::register(),
+                    commands::karen::register(),
+                ],
+            )
             .await;
 
         tracing::info!(
@@ -58,21 +83,99 @@ impl EventHandler for Handler {
     }
 
     async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
-        if let Interaction::Command(command) = interaction {
+        if let Interaction::Autocomplete(autocomplete) = &interaction {
+            let result = match autocomplete.data.name.as_str() {
+                "card" => commands::card::autocomplete(&ctx, autocomplete)
+                    .await
+                    .map_err(DiscordError::SerinityError),
+                "collection" => commands::collection::autocomplete(&ctx, autocomplete)
+                    .await
+                    .map_err(DiscordError::SerinityError),
+                _ => Err(DiscordError::NoSuchCommand(
+                    autocomplete.data.name.as_str().to_string(),
+                )),
+            };
+
+            if let Err(err) = result {
+                tracing::error!("Error with autocomplete: {}", err);
+            }
+        } else if let Interaction::Command(command) = interaction {
             let result = match command.data.name.as_str() {
-                "query" => {
-                    if let Some(option) = command.data.options.get(0) {
-                        if let CommandDataOptionValue::String(prompt) = &option.value {
-                            commands::query::run(&ctx, &command, &prompt)
-                                .await
-                                .map_err(|e| DiscordError::SerinityError(e))
-                        } else {
-                            Err(DiscordError::InvalidArgument)
-                        }
-                    } else {
-                        Err(DiscordError::InvalidArgument)
+                "query" => match string_option(&command, "prompt") {
+                    Some(prompt) => {
+                        let thread =
+                            string_option(&command, "thread").and_then(ThreadKind::from_option);
+                        let owned_only = bool_option(&command, "owned_only");
+                        commands::query::run(&ctx, &command, prompt, thread, owned_only)
+                            .await
+                            .map_err(DiscordError::SerinityError)
+                    }
+                    None => Err(DiscordError::InvalidArgument),
+                },
+                "card" => match string_option(&command, "name") {
+                    Some(name) => commands::card::run(&ctx, &command, name)
+                        .await
+                        .map_err(DiscordError::SerinityError),
+                    None => Err(DiscordError::InvalidArgument),
+                },
+                "deck" => match string_option(&command, "deck") {
+                    Some(deck) => {
+                        let question = string_option(&command, "question");
+                        commands::deck::run(&ctx, &command, deck, question)
+                            .await
+                            .map_err(DiscordError::SerinityError)
                     }
+                    None => Err(DiscordError::InvalidArgument),
+                },
+                "validate-deck" => match string_option(&command, "deck") {
+                    Some(deck) => commands::validate_deck::run(&ctx, &command, deck)
+                        .await
+                        .map_err(DiscordError::SerinityError),
+                    None => Err(DiscordError::InvalidArgument),
+                },
+                "odds" => match commands::odds::options(&command) {
+                    Some(odds) => commands::odds::run(&ctx, &command, odds)
+                        .await
+                        .map_err(DiscordError::SerinityError),
+                    None => Err(DiscordError::InvalidArgument),
+                },
+                "randomize" => {
+                    let database = type_map_keys::CardDatabase::get(&ctx.data).await;
+                    let mut options = commands::randomize::options(&command, &database);
+                    let mut no_collection = false;
+                    if bool_option(&command, "owned") {
+                        let collections = type_map_keys::Collections::get(&ctx.data).await;
+                        let owned = collections
+                            .read()
+                            .await
+                            .get(&command.user.id.get())
+                            .cloned()
+                            .unwrap_or_default();
+                        no_collection = owned.is_empty();
+                        options.restrict_to_packs(owned);
+                    }
+                    let result = if no_collection {
+                        commands::randomize::no_collection(&ctx, &command).await
+                    } else {
+                        commands::randomize::run(&ctx, &command, options).await
+                    };
+                    result.map_err(DiscordError::SerinityError)
                 }
+                "collection" => commands::collection::run(&ctx, &command)
+                    .await
+                    .map_err(DiscordError::SerinityError),
+                "campaign" => commands::campaign::run(&ctx, &command)
+                    .await
+                    .map_err(DiscordError::SerinityError),
+                "game" => commands::game::run(&ctx, &command)
+                    .await
+                    .map_err(DiscordError::SerinityError),
+                "karen" => commands::karen::run(&ctx, &command)
+                    .await
+                    .map_err(DiscordError::SerinityError),
+                "reindex-rules" => commands::reindex_rules::run(&ctx, &command)
+                    .await
+                    .map_err(DiscordError::SerinityError),
                 _ => Err(DiscordError::NoSuchCommand(
                     command.data.name.as_str().to_string(),
                 )),
@@ -81,85 +184,90 @@ impl EventHandler for Handler {
             if let Err(err) = result {
                 tracing::error!("Error with the slash command: {}", err);
             }
+        } else if let Interaction::Component(component) = interaction {
+            let result = match component.data.custom_id.as_str() {
+                commands::randomize::REROLL_BUTTON_ID => {
+                    commands::randomize::reroll(&ctx, &component)
+                        .await
+                        .map_err(DiscordError::SerinityError)
+                }
+                custom_id if custom_id.starts_with(answers::ANSWER_PREFIX) => {
+                    answers::press(&ctx, &component)
+                        .await
+                        .map_err(DiscordError::SerinityError)
+                }
+                custom_id if custom_id.starts_with(game::ACTION_PREFIX) => {
+                    commands::game::press(&ctx, &component)
+                        .await
+                        .map_err(DiscordError::SerinityError)
+                }
+                custom_id => Err(DiscordError::NoSuchCommand(custom_id.to_string())),
+            };
+
+            if let Err(err) = result {
+                tracing::error!("Error with the component interaction: {}", err);
+            }
         }
     }
 
+    async fn reaction_add(&self, ctx: Context, reaction: Reaction) {
+        feedback::react(&ctx, &reaction, true).await;
+    }
+
+    async fn reaction_remove(&self, ctx: Context, reaction: Reaction) {
+        feedback::react(&ctx, &reaction, false).await;
+    }
+
     async fn message(&self, ctx: Context, msg: SerenityMessage) {
         if msg.author.bot {
             return;
         }
 
-        if let Some(referenced_message) = &msg.referenced_message {
-            if referenced_message.author.id == ctx.cache.current_user().id {
-                tracing::info!("Query Reply");
-                let conversations_lock = type_map_keys::ConversationHistory::get(&ctx.data).await;
-                let mut conversations = conversations_lock.read().await;
-                let original_message_id = get_original_message_id(&ctx, &msg).await.unwrap();
-
-                tracing::info!("Query Reply {original_message_id}");
-                if let Some(conversation_vec) = conversations.get(&original_message_id.get()) {
-                    let mut conversation_copy = conversation_vec.clone();
-                    tracing::info!("Query Reply {original_message_id}: Found conversation history");
-                    conversation_copy.push(HerokuMiaMessage::User {
-                        content: msg.content.clone(),
-                    });
-
-                    let conversation_arc = Arc::new(Mutex::new(conversation_copy));
-                    tracing::debug!("Query Reply {original_message_id}: {:?}", conversation_arc);
-
-                    let mut stream = commands::query::agents_call(
-                        &type_map_keys::HerokuMiaClient::get(&ctx.data).await,
-                        type_map_keys::AgentTools::get(&ctx.data).await,
-                        &type_map_keys::InferenceModelId::get(&ctx.data).await,
-                        Arc::clone(&conversation_arc),
-                    )
-                    .await;
-
-                    let mut last_message = msg;
-
-                    while let Some(message_result) = stream.next().await {
-                        match message_result {
-                            Ok(message) => {
-                                tracing::info!(
-                                    "Query Reply {original_message_id}: Received streamed message"
-                                );
-                                if !message.is_empty() {
-                                    match last_message.reply(&ctx.http, message).await {
-                                        Ok(new_msg) => last_message = new_msg,
-                                        Err(e) => tracing::error!(
-                                            "Query Reply {original_message_id}: Error sending message: {:?}",
-                                            e
-                                        ),
-                                    }
-                                }
-                            }
-                            Err(e) => {
-                                tracing::error!(
-                                    "Query Reply {original_message_id}: Heroku MIA Error during agent call: {:?}",
-                                    e
-                                );
-                                if let Err(e) = last_message
-                                    .reply(&ctx.http, "Error communicating with inference service.")
-                                    .await
-                                {
-                                    tracing::error!(
-                                        "Query Reply {original_message_id}: Error sending error message: {:?}",
-                                        e
-                                    );
-                                }
-                                break;
-                            }
-                        }
-                    }
-                    {
-                        let conversations_lock =
-                            type_map_keys::ConversationHistory::get(&ctx.data).await;
-                        let mut conversations = conversations_lock.write().await;
-                        let final_conversation = conversation_arc.lock().await;
-                        conversations.insert(original_message_id.get(), final_conversation.clone());
-                    }
+        let conversations_lock = type_map_keys::ConversationHistory::get(&ctx.data).await;
+
+        // Any message posted in a conversation thread continues that conversation.
+        let thread_id = msg.channel_id;
+        if conversations_lock.read().await.contains(thread_id.get()) {
+            tracing::info!("Thread Reply {thread_id}: Found conversation history");
+            commands::query::follow_up(&ctx, msg, thread_id.get(), ReplyTarget::Thread(thread_id))
+                .await;
+            return;
+        }
+
+        if let Some(referenced_message) = &msg.referenced_message
+            && referenced_message.author.id == ctx.cache.current_user().id
+        {
+            tracing::info!("Query Reply");
+            match find_conversation(&ctx, &msg).await {
+                Ok(Some(conversation_key)) => {
+                    tracing::info!("Query Reply {conversation_key}: Found conversation history");
+                    let target = ReplyTarget::Chain(Box::new(msg.clone()));
+                    commands::query::follow_up(&ctx, msg, conversation_key, target).await;
+                }
+                Ok(None) => tracing::info!("Query Reply: No conversation found"),
+                Err(e) => {
+                    tracing::error!("Query Reply: Error resolving the conversation: {:?}", e);
                 }
             }
         }
     }
 }
+
+fn string_option<'a>(command: &'a CommandInteraction, name: &str) -> Option<&'a str> {
+    command
+        .data
+        .options
+        .iter()
+        .find(|option| option.name == name)
+        .and_then(|option| option.value.as_str())
+}
+
+fn bool_option(command: &CommandInteraction, name: &str) -> bool {
+    command
+        .data
+        .options
+        .iter()
+        .find(|option| option.name == name)
+        .and_then(|option| option.value.as_bool())
+        .unwrap_or(false)
+}
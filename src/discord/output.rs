@@ -0,0 +1,248 @@
+use serenity::all::{CreateAttachment, CreateEmbed};
+
+use crate::{
+    cards::linker::CardLinker,
+    discord::markdown::{
+        Block, MAX_DISCORD_MESSAGE_LENGTH, Table, extract_tables, split_message,
+        strip_inline_markdown, truncate,
+    },
+};
+
+/// Answers that would take more messages than this are attached as a file instead.
+const MAX_INLINE_CHUNKS: usize = 3;
+/// Tables wider than this wrap badly in a code block on mobile, so they become embeds.
+const MAX_CODE_BLOCK_WIDTH: usize = 60;
+const MAX_EMBEDS_PER_MESSAGE: usize = 10;
+const MAX_EMBED_FIELDS: usize = 25;
+const MAX_EMBED_TITLE_LENGTH: usize = 256;
+const MAX_EMBED_FIELD_NAME_LENGTH: usize = 256;
+const MAX_EMBED_FIELD_VALUE_LENGTH: usize = 1024;
+const MAX_EMBED_LENGTH: usize = 6000;
+const MAX_SUMMARY_LENGTH: usize = 300;
+const ATTACHMENT_NAME: &str = "answer.md";
+/// Discord rejects empty embed field names and values.
+const EMPTY_FIELD: &str = "\u{200b}";
+
+/// How an agent message is posted to Discord.
+#[derive(Debug)]
+pub(crate) enum Output {
+    /// Markdown to stream inline, interleaved with embeds rendered from its tables.
+    Text(Vec<Part>),
+    /// A short summary message with the full answer attached as Markdown.
+    Attachment {
+        summary: String,
+        file: CreateAttachment,
+    },
+}
+
+/// A run of an [`Output::Text`], posted in order.
+#[derive(Debug)]
+pub(crate) enum Part {
+    Markdown(String),
+    /// Table embeds to post as one message, with their total length, which Discord also caps.
+    Embeds {
+        embeds: Vec<CreateEmbed>,
+        length: usize,
+    },
+}
+
+/// Post-processes an agent message for Discord: card names outside tables link to MarvelCDB,
+/// tables become aligned code blocks or embed fields, and answers longer than
+/// [`MAX_INLINE_CHUNKS`] messages become a file attachment.
+pub(crate) fn render(message: &str, linker: &CardLinker) -> Output {
+    let mut parts = Vec::new();
+    let mut content = Vec::new();
+
+    for block in extract_tables(message) {
+        let markdown = match block {
+            Block::Text(text) => linker.link(&text),
+            Block::Table(table) => {
+                let embed = (table.width() > MAX_CODE_BLOCK_WIDTH)
+                    .then(|| table_embed(&table))
+                    .flatten();
+                match embed {
+                    Some((embed, embed_length)) => {
+                        match parts.last_mut() {
+                            Some(Part::Embeds { embeds, length })
+                                if embeds.len() < MAX_EMBEDS_PER_MESSAGE
+                                    && *length + embed_length <= MAX_EMBED_LENGTH =>
+                            {
+                                embeds.push(embed);
+                                *length += embed_length;
+                            }
+                            _ => parts.push(Part::Embeds {
+                                embeds: vec![embed],
+                                length: embed_length,
+                            }),
+                        }
+                        continue;
+                    }
+                    None => table.to_code_block(),
+                }
+            }
+        };
+
+        content.push(markdown.clone());
+        match parts.last_mut() {
+            Some(Part::Markdown(text)) => {
+                text.push('\n');
+                text.push_str(&markdown);
+            }
+            _ => parts.push(Part::Markdown(markdown)),
+        }
+    }
+    let content = content.join("\n");
+
+    if split_message(&content, MAX_DISCORD_MESSAGE_LENGTH).len() > MAX_INLINE_CHUNKS {
+        return Output::Attachment {
+            summary: summarize(&content),
+            file: CreateAttachment::bytes(message.as_bytes().to_vec(), ATTACHMENT_NAME),
+        };
+    }
+
+    Output::Text(parts)
+}
+
+/// Renders a table as an embed with a field per row, with its length, if it fits within
+/// Discord's limits.
+fn table_embed(table: &Table) -> Option<(CreateEmbed, usize)> {
+    if table.rows.is_empty() || table.rows.len() > MAX_EMBED_FIELDS {
+        return None;
+    }
+
+    let title = truncate(
+        &strip_inline_markdown(&table.header.join(" / ")),
+        MAX_EMBED_TITLE_LENGTH,
+    );
+    let mut length = title.chars().count();
+    let mut fields = Vec::with_capacity(table.rows.len());
+
+    for row in &table.rows {
+        let name = truncate(&strip_inline_markdown(&row[0]), MAX_EMBED_FIELD_NAME_LENGTH);
+        let value = table.header[1..]
+            .iter()
+            .zip(&row[1..])
+            .filter(|(_, cell)| !cell.is_empty())
+            .map(|(header, cell)| format!("**{}:** {cell}", strip_inline_markdown(header)))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let value = truncate(&value, MAX_EMBED_FIELD_VALUE_LENGTH);
+
+        length += name.chars().count() + value.chars().count();
+        fields.push((non_empty(name), non_empty(value), table.header.len() <= 3));
+    }
+
+    (length <= MAX_EMBED_LENGTH).then(|| (CreateEmbed::new().title(title).fields(fields), length))
+}
+
+/// The first paragraph of an answer, to post alongside the attached full answer.
+fn summarize(content: &str) -> String {
+    let first_paragraph = content
+        .split("\n\n")
+        .map(str::trim)
+        .find(|paragraph| !paragraph.is_empty() && !paragraph.starts_with("```"))
+        .unwrap_or_default();
+
+    format!(
+        "{}\n\n*The full answer is attached as `{ATTACHMENT_NAME}`.*",
+        truncate(first_paragraph, MAX_SUMMARY_LENGTH)
+    )
+}
+
+fn non_empty(text: String) -> String {
+    if text.is_empty() {
+        EMPTY_FIELD.to_string()
+    } else {
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_narrow_table_becomes_code_block() {
+        let output = render(
+            "| Card | Cost |\n|---|---|\n| Haymaker | 2 |",
+            &CardLinker::default(),
+        );
+
+        match output {
+            Output::Text(parts) => match parts.as_slice() {
+                [Part::Markdown(content)] => assert!(content.starts_with("```\nCard")),
+                parts => panic!("Unexpected parts {parts:?}"),
+            },
+            Output::Attachment { .. } => panic!("Unexpected attachment"),
+        }
+    }
+
+    #[test]
+    fn test_wide_table_becomes_embed() {
+        let text = "Gains a tough status card and readies when the villain attacks you";
+        let message = format!(
+            "Options:\n| Card | Text |\n|---|---|\n| Unflappable | {text} |\n| Armored Vest | {text} |\nPick one."
+        );
+
+        match render(&message, &CardLinker::default()) {
+            Output::Text(parts) => match parts.as_slice() {
+                [
+                    Part::Markdown(content),
+                    Part::Embeds { embeds, .. },
+                    Part::Markdown(outro),
+                ] => {
+                    assert_eq!(content, "Options:");
+                    assert_eq!(embeds.len(), 1);
+                    assert_eq!(outro, "Pick one.");
+                }
+                parts => panic!("Unexpected parts {parts:?}"),
+            },
+            Output::Attachment { .. } => panic!("Unexpected attachment"),
+        }
+    }
+
+    #[test]
+    fn test_large_tables_split_across_messages() {
+        let text =
+            "Gains a tough status card and readies when the villain attacks you. ".repeat(14);
+        let rows = (0..4)
+            .map(|row| format!("| Card {row} | {text} |"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        // Back to back, so only their total length keeps them apart. The second header has no
+        // outer pipes, or the first table would take it as a row.
+        let message =
+            format!("| Card | Text |\n|---|---|\n{rows}\nCard | Text | Cost\n---|---|---\n{rows}");
+
+        match render(&message, &CardLinker::default()) {
+            Output::Text(parts) => match parts.as_slice() {
+                [
+                    Part::Embeds {
+                        embeds: first,
+                        length,
+                    },
+                    Part::Embeds { embeds: second, .. },
+                ] => {
+                    assert_eq!((first.len(), second.len()), (1, 1));
+                    assert!(*length > MAX_EMBED_LENGTH / 2);
+                }
+                parts => panic!("Unexpected parts {parts:?}"),
+            },
+            Output::Attachment { .. } => panic!("Unexpected attachment"),
+        }
+    }
+
+    #[test]
+    fn test_long_answer_becomes_attachment() {
+        let paragraph = "All work and no play makes Jack a dull boy. ".repeat(40);
+        let message = format!("Short intro.\n\n{}", [paragraph.as_str(); 5].join("\n\n"));
+
+        match render(&message, &CardLinker::default()) {
+            Output::Attachment { summary, .. } => {
+                assert!(summary.starts_with("Short intro.\n\n"));
+                assert!(summary.ends_with("*The full answer is attached as `answer.md`.*"));
+            }
+            Output::Text { .. } => panic!("Expected an attachment"),
+        }
+    }
+}
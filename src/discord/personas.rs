@@ -0,0 +1,66 @@
+use serenity::all::{Channel, ChannelId, Context};
+
+use crate::{
+    discord::type_map_keys,
+    heroku_mia::agents::AgentTool,
+    personas::{self, Persona},
+    tools::LocalTools,
+};
+
+/// What agent calls in a channel run with: its persona, filled in from the bot's settings.
+pub(crate) struct AgentSettings {
+    pub system_prompt: String,
+    pub model_id: String,
+    pub temperature: Option<f32>,
+    pub tools: Vec<AgentTool>,
+    pub local_tools: LocalTools,
+}
+
+/// The persona set for a channel or thread, falling back to the thread's parent channel.
+pub(crate) async fn persona(ctx: &Context, channel_id: ChannelId) -> Persona {
+    let settings = type_map_keys::Settings::get(&ctx.data).await;
+    if settings.read().await.personas.is_empty() {
+        return Persona::default();
+    }
+
+    // Only threads have a parent worth falling back to; a channel's parent is its category.
+    let parent_id = match channel_id.to_channel(ctx).await {
+        Ok(Channel::Guild(channel)) if channel.thread_metadata.is_some() => channel.parent_id,
+        Ok(_) => None,
+        Err(e) => {
+            tracing::error!("Persona: Error fetching channel {channel_id}: {:?}", e);
+            None
+        }
+    };
+
+    personas::resolve(
+        &settings.read().await.personas,
+        channel_id.get(),
+        parent_id.map(ChannelId::get),
+    )
+}
+
+pub(crate) async fn agent_settings(ctx: &Context, channel_id: ChannelId) -> AgentSettings {
+    let persona = persona(ctx, channel_id).await;
+    let policy = persona.tools.unwrap_or_default();
+
+    AgentSettings {
+        system_prompt: match persona.system_prompt {
+            Some(system_prompt) => system_prompt,
+            None => type_map_keys::SystemPrompt::get(&ctx.data).await,
+        },
+        model_id: match persona.model_id {
+            Some(model_id) => model_id,
+            None => type_map_keys::InferenceModelId::get(&ctx.data).await,
+        },
+        temperature: persona.temperature,
+        tools: type_map_keys::AgentTools::get(&ctx.data)
+            .await
+            .into_iter()
+            .filter(|tool| policy.allows(tool.name()))
+            .collect(),
+        local_tools: type_map_keys::LocalTools::get(&ctx.data)
+            .await
+            .filter(|name| policy.allows(name)),
+    }
+}
@@ -0,0 +1,74 @@
+use serenity::all::{
+    CommandInteraction, Context, EditInteractionResponse, Message as SerenityMessage, ReactionType,
+};
+
+use crate::{
+    discord::type_map_keys,
+    queue::{Permit, Ticket},
+};
+
+const QUEUED_REACTION: &str = "⏳";
+
+/// Joins the inference queue.
+pub(crate) async fn start(ctx: &Context) -> Result<Permit, Ticket> {
+    let queue = type_map_keys::InferenceQueue::get(&ctx.data).await;
+    let start = queue.start();
+    if start.is_err() {
+        tracing::info!(
+            "Queue: {} running, {} queued",
+            queue.running(),
+            queue.queued()
+        );
+    }
+
+    start
+}
+
+/// `content` with the request's place in the queue, if it is queued.
+pub(crate) fn with_position(content: &str, start: &Result<Permit, Ticket>) -> String {
+    match start {
+        Ok(_) => content.to_string(),
+        Err(ticket) => format!(
+            "{content}\n⏳ You're number {} in the queue. This message will update when your request starts.",
+            ticket.position()
+        ),
+    }
+}
+
+/// Waits for a command's turn, then edits its response back to `content`.
+pub(crate) async fn wait_for_command(
+    ctx: &Context,
+    command: &CommandInteraction,
+    start: Result<Permit, Ticket>,
+    content: &str,
+) -> Result<Permit, serenity::Error> {
+    match start {
+        Ok(permit) => Ok(permit),
+        Err(ticket) => {
+            let permit = ticket.wait().await;
+            command
+                .edit_response(&ctx.http, EditInteractionResponse::new().content(content))
+                .await?;
+            Ok(permit)
+        }
+    }
+}
+
+/// Waits for a follow-up's turn, marking the message while it is queued.
+pub(crate) async fn wait_for_message(ctx: &Context, msg: &SerenityMessage) -> Permit {
+    let ticket = match start(ctx).await {
+        Ok(permit) => return permit,
+        Err(ticket) => ticket,
+    };
+
+    let reaction = ReactionType::Unicode(QUEUED_REACTION.to_string());
+    if let Err(e) = msg.react(&ctx.http, reaction.clone()).await {
+        tracing::error!("Queue: Error marking a queued message: {:?}", e);
+    }
+    let permit = ticket.wait().await;
+    if let Err(e) = msg.delete_reaction(&ctx.http, None, reaction).await {
+        tracing::error!("Queue: Error unmarking a queued message: {:?}", e);
+    }
+
+    permit
+}
@@ -0,0 +1,237 @@
+use serenity::all::{
+    ChannelId, Context, CreateActionRow, CreateMessage, EditMessage, Message as SerenityMessage,
+    MessageId,
+};
+use std::time::Duration;
+use tokio::time::Instant;
+
+use crate::discord::markdown::{MAX_DISCORD_MESSAGE_LENGTH, split_message};
+
+/// Discord allows roughly 5 message edits per 5 seconds in a channel, so leave some headroom.
+const MIN_EDIT_INTERVAL: Duration = Duration::from_millis(1200);
+
+/// Where the messages of a conversation get posted.
+pub(crate) enum ReplyTarget {
+    /// Reply to the previous message, building a chain of Discord replies.
+    Chain(Box<SerenityMessage>),
+    /// Post into the thread the conversation is keyed by.
+    Thread(ChannelId),
+}
+
+impl ReplyTarget {
+    /// The channel or thread replies are posted in.
+    pub fn channel_id(&self) -> ChannelId {
+        match self {
+            ReplyTarget::Chain(last_message) => last_message.channel_id,
+            ReplyTarget::Thread(thread_id) => *thread_id,
+        }
+    }
+
+    async fn send(
+        &mut self,
+        ctx: &Context,
+        message: CreateMessage,
+    ) -> Result<SerenityMessage, serenity::Error> {
+        match self {
+            ReplyTarget::Chain(last_message) => {
+                let message = last_message
+                    .channel_id
+                    .send_message(&ctx.http, message.reference_message(&**last_message))
+                    .await?;
+                **last_message = message.clone();
+                Ok(message)
+            }
+            ReplyTarget::Thread(thread_id) => thread_id.send_message(&ctx.http, message).await,
+        }
+    }
+}
+
+/// Renders streamed text into a single Discord message that is edited in place as content
+/// arrives, rolling over to a new message only once the 2000 character limit is reached.
+///
+/// Edits are throttled to [`MIN_EDIT_INTERVAL`]. Text pushed in between is kept pending until
+/// [`StreamingMessage::edit_due`] resolves and [`StreamingMessage::flush`] is called, or until
+/// [`StreamingMessage::finish`].
+pub(crate) struct StreamingMessage {
+    target: ReplyTarget,
+    current: Option<SerenityMessage>,
+    content: String,
+    rendered: String,
+    last_edit: Option<Instant>,
+    sent: Vec<MessageId>,
+    components: Vec<CreateActionRow>,
+}
+
+impl StreamingMessage {
+    pub fn new(target: ReplyTarget) -> Self {
+        Self {
+            target,
+            current: None,
+            content: String::new(),
+            rendered: String::new(),
+            last_edit: None,
+            sent: Vec::new(),
+            components: Vec::new(),
+        }
+    }
+
+    /// Appends a delta to the message being rendered.
+    pub async fn push(&mut self, ctx: &Context, delta: &str) -> Result<(), serenity::Error> {
+        self.content.push_str(delta);
+
+        if self.content.chars().count() > MAX_DISCORD_MESSAGE_LENGTH {
+            self.roll_over(ctx).await
+        } else if self.next_edit_at() <= Instant::now() {
+            self.flush(ctx).await
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Appends a complete paragraph, such as a whole streamed agent message.
+    pub async fn push_paragraph(
+        &mut self,
+        ctx: &Context,
+        paragraph: &str,
+    ) -> Result<(), serenity::Error> {
+        if self.content.trim().is_empty() {
+            self.push(ctx, paragraph).await
+        } else {
+            self.push(ctx, &format!("\n\n{paragraph}")).await
+        }
+    }
+
+    /// Whether pushed content hasn't made it to Discord yet.
+    pub fn has_pending(&self) -> bool {
+        self.content.trim() != self.rendered
+    }
+
+    /// Resolves once the rate limit allows the next edit.
+    pub async fn edit_due(&self) {
+        tokio::time::sleep_until(self.next_edit_at()).await;
+    }
+
+    /// Renders any pending content into the current message.
+    pub async fn flush(&mut self, ctx: &Context) -> Result<(), serenity::Error> {
+        let content = self.content.trim().to_string();
+        self.render(ctx, content).await
+    }
+
+    /// Finalizes the message being rendered and posts `message` on its own, such as embeds or
+    /// an attachment. Content pushed afterwards continues in a new message.
+    pub async fn push_message(
+        &mut self,
+        ctx: &Context,
+        message: CreateMessage,
+    ) -> Result<(), serenity::Error> {
+        self.settle(ctx).await?;
+        self.current = None;
+        self.content.clear();
+        self.rendered.clear();
+        self.send(ctx, message).await?;
+
+        Ok(())
+    }
+
+    /// Shows buttons under the latest message, and under each later one instead as they are
+    /// posted. No rows removes them.
+    pub async fn set_components(
+        &mut self,
+        ctx: &Context,
+        components: Vec<CreateActionRow>,
+    ) -> Result<(), serenity::Error> {
+        self.components = components.clone();
+        if let Some(last) = self.sent.last() {
+            self.target
+                .channel_id()
+                .edit_message(ctx, *last, EditMessage::new().components(components))
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Flushes the remaining content, waiting out the edit rate limit if needed.
+    pub async fn finish(&mut self, ctx: &Context) -> Result<(), serenity::Error> {
+        self.settle(ctx).await
+    }
+
+    /// Posts a message carrying the buttons, moving them off the previous message.
+    async fn send(
+        &mut self,
+        ctx: &Context,
+        message: CreateMessage,
+    ) -> Result<SerenityMessage, serenity::Error> {
+        let message = self
+            .target
+            .send(ctx, message.components(self.components.clone()))
+            .await?;
+        if !self.components.is_empty()
+            && let Some(previous) = self.sent.last()
+        {
+            self.target
+                .channel_id()
+                .edit_message(ctx, *previous, EditMessage::new().components(Vec::new()))
+                .await?;
+        }
+        self.sent.push(message.id);
+
+        Ok(message)
+    }
+
+    /// The messages posted so far, in order.
+    pub fn sent(&self) -> &[MessageId] {
+        &self.sent
+    }
+
+    async fn settle(&mut self, ctx: &Context) -> Result<(), serenity::Error> {
+        if self.has_pending() {
+            self.edit_due().await;
+            self.flush(ctx).await?;
+        }
+
+        Ok(())
+    }
+
+    fn next_edit_at(&self) -> Instant {
+        self.last_edit
+            .map_or_else(Instant::now, |last_edit| last_edit + MIN_EDIT_INTERVAL)
+    }
+
+    /// Finalizes every full chunk and continues with the remainder in a new message.
+    async fn roll_over(&mut self, ctx: &Context) -> Result<(), serenity::Error> {
+        let mut chunks = split_message(&self.content, MAX_DISCORD_MESSAGE_LENGTH);
+        let remainder = chunks.pop().unwrap_or_default();
+
+        for chunk in chunks {
+            self.render(ctx, chunk).await?;
+            self.current = None;
+            self.rendered.clear();
+        }
+
+        self.content = remainder;
+        self.flush(ctx).await
+    }
+
+    async fn render(&mut self, ctx: &Context, content: String) -> Result<(), serenity::Error> {
+        if content.is_empty() || content == self.rendered {
+            return Ok(());
+        }
+
+        match &mut self.current {
+            Some(message) => {
+                message
+                    .edit(ctx, EditMessage::new().content(content.clone()))
+                    .await?
+            }
+            None => {
+                let message = CreateMessage::new().content(content.clone());
+                self.current = Some(self.send(ctx, message).await?);
+            }
+        }
+        self.rendered = content;
+        self.last_edit = Some(Instant::now());
+
+        Ok(())
+    }
+}
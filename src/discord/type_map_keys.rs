@@ -1,19 +1,22 @@
 use serenity::{
-    model::prelude::GuildId as SerenityGuildId,
-    prelude::{RwLock, TypeMap, TypeMapKey},
+    model::prelude::{GuildId as SerenityGuildId, RoleId},
+    prelude::{Mutex, RwLock, TypeMap, TypeMapKey},
 };
-use std::{collections::HashMap, sync::Arc};
+use std::{path::PathBuf, sync::Arc};
 
-use crate::heroku_mia::types::Message;
+use crate::{
+    conversations::Conversations,
+    storage::{JsonStore, JsonlStore},
+};
 
 pub(crate) struct ConversationHistory;
 
 impl TypeMapKey for ConversationHistory {
-    type Value = Arc<RwLock<HashMap<u64, Vec<Message>>>>;
+    type Value = Arc<RwLock<Conversations>>;
 }
 
 impl ConversationHistory {
-    pub async fn get(data: &Arc<RwLock<TypeMap>>) -> Arc<RwLock<HashMap<u64, Vec<Message>>>> {
+    pub async fn get(data: &Arc<RwLock<TypeMap>>) -> Arc<RwLock<Conversations>> {
         let data = data.read().await;
         data.get::<Self>()
             .expect("Expected ConversationHistory in TypeMap")
@@ -21,6 +24,22 @@ impl ConversationHistory {
     }
 }
 
+/// Locks that make the turns of each conversation run one at a time.
+pub(crate) struct ConversationLocks;
+
+impl TypeMapKey for ConversationLocks {
+    type Value = Arc<crate::locks::KeyedLocks>;
+}
+
+impl ConversationLocks {
+    pub async fn get(data: &Arc<RwLock<TypeMap>>) -> Arc<crate::locks::KeyedLocks> {
+        let data = data.read().await;
+        data.get::<Self>()
+            .expect("Expected ConversationLocks in TypeMap")
+            .clone()
+    }
+}
+
 pub(crate) struct GuildId;
 
 impl TypeMapKey for GuildId {
@@ -34,10 +53,11 @@ impl GuildId {
     }
 }
 
+/// The model agent calls use, which `/karen model set` can change.
 pub(crate) struct InferenceModelId;
 
 impl TypeMapKey for InferenceModelId {
-    type Value = String;
+    type Value = Arc<RwLock<String>>;
 }
 
 impl InferenceModelId {
@@ -45,8 +65,46 @@ impl InferenceModelId {
         let data = data.read().await;
         data.get::<Self>()
             .expect("Expected InferenceModelId")
+            .read()
+            .await
             .clone()
     }
+
+    pub async fn set(data: &Arc<RwLock<TypeMap>>, model_id: String) {
+        let data = data.read().await;
+        *data
+            .get::<Self>()
+            .expect("Expected InferenceModelId")
+            .write()
+            .await = model_id;
+    }
+}
+
+/// The system prompt new conversations start with, which `/karen prompt set` can change.
+pub(crate) struct SystemPrompt;
+
+impl TypeMapKey for SystemPrompt {
+    type Value = Arc<RwLock<String>>;
+}
+
+impl SystemPrompt {
+    pub async fn get(data: &Arc<RwLock<TypeMap>>) -> String {
+        let data = data.read().await;
+        data.get::<Self>()
+            .expect("Expected SystemPrompt")
+            .read()
+            .await
+            .clone()
+    }
+
+    pub async fn set(data: &Arc<RwLock<TypeMap>>, prompt: String) {
+        let data = data.read().await;
+        *data
+            .get::<Self>()
+            .expect("Expected SystemPrompt")
+            .write()
+            .await = prompt;
+    }
 }
 
 pub(crate) struct HerokuMiaClient;
@@ -64,15 +122,266 @@ impl HerokuMiaClient {
     }
 }
 
+/// The MCP tools agent calls may use, which `/karen tools reload` can refresh.
 pub(crate) struct AgentTools;
 
 impl TypeMapKey for AgentTools {
-    type Value = Vec<crate::heroku_mia::agents::AgentTool>;
+    type Value = Arc<RwLock<Vec<crate::heroku_mia::agents::AgentTool>>>;
 }
 
 impl AgentTools {
     pub async fn get(data: &Arc<RwLock<TypeMap>>) -> Vec<crate::heroku_mia::agents::AgentTool> {
         let data = data.read().await;
-        data.get::<Self>().expect("Expected AgentTools").clone()
+        data.get::<Self>()
+            .expect("Expected AgentTools")
+            .read()
+            .await
+            .clone()
+    }
+
+    pub async fn set(
+        data: &Arc<RwLock<TypeMap>>,
+        tools: Vec<crate::heroku_mia::agents::AgentTool>,
+    ) {
+        let data = data.read().await;
+        *data
+            .get::<Self>()
+            .expect("Expected AgentTools")
+            .write()
+            .await = tools;
+    }
+}
+
+pub(crate) struct CardImageDir;
+
+impl TypeMapKey for CardImageDir {
+    type Value = Option<PathBuf>;
+}
+
+impl CardImageDir {
+    pub async fn get(data: &Arc<RwLock<TypeMap>>) -> Option<PathBuf> {
+        let data = data.read().await;
+        data.get::<Self>().expect("Expected CardImageDir").clone()
+    }
+}
+
+pub(crate) struct LocalTools;
+
+impl TypeMapKey for LocalTools {
+    type Value = crate::tools::LocalTools;
+}
+
+impl LocalTools {
+    pub async fn get(data: &Arc<RwLock<TypeMap>>) -> crate::tools::LocalTools {
+        let data = data.read().await;
+        data.get::<Self>().expect("Expected LocalTools").clone()
+    }
+}
+
+pub(crate) struct CardDatabase;
+
+impl TypeMapKey for CardDatabase {
+    type Value = Arc<crate::cards::CardDatabase>;
+}
+
+impl CardDatabase {
+    pub async fn get(data: &Arc<RwLock<TypeMap>>) -> Arc<crate::cards::CardDatabase> {
+        let data = data.read().await;
+        data.get::<Self>().expect("Expected CardDatabase").clone()
+    }
+}
+
+pub(crate) struct CardLinker;
+
+impl TypeMapKey for CardLinker {
+    type Value = Arc<crate::cards::linker::CardLinker>;
+}
+
+impl CardLinker {
+    pub async fn get(data: &Arc<RwLock<TypeMap>>) -> Arc<crate::cards::linker::CardLinker> {
+        let data = data.read().await;
+        data.get::<Self>().expect("Expected CardLinker").clone()
+    }
+}
+
+pub(crate) struct MarvelCdbClient;
+
+impl TypeMapKey for MarvelCdbClient {
+    type Value = crate::cards::marvelcdb::Client;
+}
+
+impl MarvelCdbClient {
+    pub async fn get(data: &Arc<RwLock<TypeMap>>) -> crate::cards::marvelcdb::Client {
+        let data = data.read().await;
+        data.get::<Self>()
+            .expect("Expected MarvelCdbClient")
+            .clone()
+    }
+}
+
+/// The options of the latest `/randomize` messages, by message id, so their setups can be
+/// rerolled.
+pub(crate) struct Randomizations;
+
+impl TypeMapKey for Randomizations {
+    type Value = Arc<RwLock<crate::cards::randomizer::RecentSetups>>;
+}
+
+impl Randomizations {
+    pub async fn get(
+        data: &Arc<RwLock<TypeMap>>,
+    ) -> Arc<RwLock<crate::cards::randomizer::RecentSetups>> {
+        let data = data.read().await;
+        data.get::<Self>().expect("Expected Randomizations").clone()
+    }
+}
+
+pub(crate) struct Collections;
+
+impl TypeMapKey for Collections {
+    type Value = Arc<JsonStore<crate::cards::collection::Collections>>;
+}
+
+impl Collections {
+    pub async fn get(
+        data: &Arc<RwLock<TypeMap>>,
+    ) -> Arc<JsonStore<crate::cards::collection::Collections>> {
+        let data = data.read().await;
+        data.get::<Self>().expect("Expected Collections").clone()
+    }
+}
+
+pub(crate) struct CampaignLogs;
+
+impl TypeMapKey for CampaignLogs {
+    type Value = Arc<JsonStore<crate::campaign::CampaignLogs>>;
+}
+
+impl CampaignLogs {
+    pub async fn get(data: &Arc<RwLock<TypeMap>>) -> Arc<JsonStore<crate::campaign::CampaignLogs>> {
+        let data = data.read().await;
+        data.get::<Self>().expect("Expected CampaignLogs").clone()
+    }
+}
+
+pub(crate) struct Games;
+
+impl TypeMapKey for Games {
+    type Value = Arc<RwLock<crate::game::Games>>;
+}
+
+impl Games {
+    pub async fn get(data: &Arc<RwLock<TypeMap>>) -> Arc<RwLock<crate::game::Games>> {
+        let data = data.read().await;
+        data.get::<Self>().expect("Expected Games").clone()
+    }
+}
+
+/// The rules retriever, when rules documents and an embedding model are configured.
+pub(crate) struct Rules;
+
+impl TypeMapKey for Rules {
+    type Value = Option<Arc<crate::rules::Retriever>>;
+}
+
+impl Rules {
+    pub async fn get(data: &Arc<RwLock<TypeMap>>) -> Option<Arc<crate::rules::Retriever>> {
+        let data = data.read().await;
+        data.get::<Self>().expect("Expected Rules").clone()
+    }
+}
+
+pub(crate) struct RateLimiter;
+
+impl TypeMapKey for RateLimiter {
+    type Value = Arc<Mutex<crate::limits::RateLimiter>>;
+}
+
+impl RateLimiter {
+    pub async fn get(data: &Arc<RwLock<TypeMap>>) -> Arc<Mutex<crate::limits::RateLimiter>> {
+        let data = data.read().await;
+        data.get::<Self>().expect("Expected RateLimiter").clone()
+    }
+}
+
+/// Tokens spent today by each user, for their daily quotas.
+pub(crate) struct Usage;
+
+impl TypeMapKey for Usage {
+    type Value = Arc<JsonStore<crate::limits::DailyUsage>>;
+}
+
+impl Usage {
+    pub async fn get(data: &Arc<RwLock<TypeMap>>) -> Arc<JsonStore<crate::limits::DailyUsage>> {
+        let data = data.read().await;
+        data.get::<Self>().expect("Expected Usage").clone()
+    }
+}
+
+pub(crate) struct InferenceQueue;
+
+impl TypeMapKey for InferenceQueue {
+    type Value = Arc<crate::queue::InferenceQueue>;
+}
+
+impl InferenceQueue {
+    pub async fn get(data: &Arc<RwLock<TypeMap>>) -> Arc<crate::queue::InferenceQueue> {
+        let data = data.read().await;
+        data.get::<Self>().expect("Expected InferenceQueue").clone()
+    }
+}
+
+pub(crate) struct Settings;
+
+impl TypeMapKey for Settings {
+    type Value = Arc<JsonStore<crate::settings::Settings>>;
+}
+
+impl Settings {
+    pub async fn get(data: &Arc<RwLock<TypeMap>>) -> Arc<JsonStore<crate::settings::Settings>> {
+        let data = data.read().await;
+        data.get::<Self>().expect("Expected Settings").clone()
+    }
+}
+
+/// Roles that may use admin commands besides members who can manage the server.
+pub(crate) struct AdminRoles;
+
+impl TypeMapKey for AdminRoles {
+    type Value = Vec<RoleId>;
+}
+
+impl AdminRoles {
+    pub async fn get(data: &Arc<RwLock<TypeMap>>) -> Vec<RoleId> {
+        let data = data.read().await;
+        data.get::<Self>().expect("Expected AdminRoles").clone()
+    }
+}
+
+/// Ratings of answers, from 👍 and 👎 reactions.
+pub(crate) struct Feedback;
+
+impl TypeMapKey for Feedback {
+    type Value = Arc<JsonlStore<crate::feedback::FeedbackLog>>;
+}
+
+impl Feedback {
+    pub async fn get(data: &Arc<RwLock<TypeMap>>) -> Arc<JsonlStore<crate::feedback::FeedbackLog>> {
+        let data = data.read().await;
+        data.get::<Self>().expect("Expected Feedback").clone()
+    }
+}
+
+/// Answers being written and the buttons under each conversation's latest answer.
+pub(crate) struct Answers;
+
+impl TypeMapKey for Answers {
+    type Value = Arc<Mutex<crate::discord::answers::Answers>>;
+}
+
+impl Answers {
+    pub async fn get(data: &Arc<RwLock<TypeMap>>) -> Arc<Mutex<crate::discord::answers::Answers>> {
+        let data = data.read().await;
+        data.get::<Self>().expect("Expected Answers").clone()
     }
 }
@@ -0,0 +1,207 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{heroku_mia::types::Message, storage::Journal};
+
+/// How a user rated an answer.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Vote {
+    Up,
+    Down,
+}
+
+impl Vote {
+    /// 👍 or 👎, in any skin tone.
+    pub fn from_emoji(emoji: &str) -> Option<Self> {
+        if emoji.starts_with('👍') {
+            Some(Vote::Up)
+        } else if emoji.starts_with('👎') {
+            Some(Vote::Down)
+        } else {
+            None
+        }
+    }
+}
+
+/// An answer the bot posted, with what it was asked.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Turn {
+    pub conversation_id: u64,
+    /// The Discord messages the answer was posted in.
+    pub message_ids: Vec<u64>,
+    pub model_id: String,
+    /// The conversation sent to the model, ending with the user's message.
+    pub prompt: Vec<Message>,
+    /// The tool calls, tool results and answers of the turn.
+    pub response: Vec<Message>,
+}
+
+/// A rating of a turn, as exported for evaluating prompts and models.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Feedback {
+    pub conversation_id: u64,
+    /// The first message of the rated answer, which identifies the turn.
+    pub turn_id: u64,
+    /// The message the reaction was added to.
+    pub message_id: u64,
+    pub user_id: u64,
+    pub vote: Vote,
+    /// Unix time of the reaction.
+    pub rated_at: u64,
+    pub model_id: String,
+    pub prompt: Vec<Message>,
+    pub response: Vec<Message>,
+}
+
+impl Feedback {
+    pub fn new(turn: &Turn, message_id: u64, user_id: u64, vote: Vote, rated_at: u64) -> Self {
+        Self {
+            conversation_id: turn.conversation_id,
+            turn_id: turn.message_ids.first().copied().unwrap_or(message_id),
+            message_id,
+            user_id,
+            vote,
+            rated_at,
+            model_id: turn.model_id.clone(),
+            prompt: turn.prompt.clone(),
+            response: turn.response.clone(),
+        }
+    }
+}
+
+/// A line of the feedback log: a rating, or a reaction that was taken back.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum FeedbackEvent {
+    Rated(Feedback),
+    Retracted {
+        turn_id: u64,
+        user_id: u64,
+        vote: Vote,
+        /// Unix time the reaction was removed.
+        retracted_at: u64,
+    },
+}
+
+/// Every rating collected, one per user and turn.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FeedbackLog {
+    pub entries: Vec<Feedback>,
+}
+
+impl FeedbackLog {
+    /// Adds a rating, replacing the user's earlier rating of the same turn.
+    pub fn record(&mut self, feedback: Feedback) {
+        self.entries
+            .retain(|entry| entry.turn_id != feedback.turn_id || entry.user_id != feedback.user_id);
+        self.entries.push(feedback);
+    }
+
+    /// Removes a rating whose reaction was taken back.
+    pub fn retract(&mut self, turn_id: u64, user_id: u64, vote: Vote) {
+        self.entries.retain(|entry| {
+            entry.turn_id != turn_id || entry.user_id != user_id || entry.vote != vote
+        });
+    }
+
+    pub fn count(&self, vote: Vote) -> usize {
+        self.entries
+            .iter()
+            .filter(|entry| entry.vote == vote)
+            .count()
+    }
+
+    /// The ratings as JSON Lines, optionally only those with one vote.
+    pub fn to_jsonl(&self, vote: Option<Vote>) -> Result<String, serde_json::Error> {
+        let mut jsonl = String::new();
+        for entry in self
+            .entries
+            .iter()
+            .filter(|entry| vote.is_none_or(|vote| entry.vote == vote))
+        {
+            jsonl.push_str(&serde_json::to_string(entry)?);
+            jsonl.push('\n');
+        }
+
+        Ok(jsonl)
+    }
+}
+
+impl Journal for FeedbackLog {
+    type Entry = FeedbackEvent;
+
+    fn apply(&mut self, event: FeedbackEvent) {
+        match event {
+            FeedbackEvent::Rated(feedback) => self.record(feedback),
+            FeedbackEvent::Retracted {
+                turn_id,
+                user_id,
+                vote,
+                ..
+            } => self.retract(turn_id, user_id, vote),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn turn(conversation_id: u64, message_ids: Vec<u64>) -> Turn {
+        Turn {
+            conversation_id,
+            message_ids,
+            model_id: "claude-4-sonnet".to_string(),
+            prompt: vec![Message::User {
+                content: "Who is Black Cat?".to_string(),
+            }],
+            response: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_feedback_log() {
+        let turn = turn(1, vec![10, 11]);
+        let mut log = FeedbackLog::default();
+        log.record(Feedback::new(&turn, 11, 5, Vote::Up, 0));
+        log.record(Feedback::new(&turn, 10, 5, Vote::Down, 1));
+        log.record(Feedback::new(&turn, 10, 6, Vote::Down, 2));
+        assert_eq!((log.count(Vote::Up), log.count(Vote::Down)), (0, 2));
+
+        log.retract(10, 6, Vote::Up);
+        assert_eq!(log.entries.len(), 2);
+        log.retract(10, 6, Vote::Down);
+        assert_eq!(log.entries.len(), 1);
+
+        let jsonl = log.to_jsonl(Some(Vote::Down)).unwrap();
+        assert_eq!(jsonl.lines().count(), 1);
+        assert!(jsonl.contains(r#""vote":"down""#));
+        assert_eq!(log.to_jsonl(Some(Vote::Up)).unwrap(), "");
+    }
+
+    #[test]
+    fn test_feedback_events() {
+        let turn = turn(1, vec![10]);
+        let rated = FeedbackEvent::Rated(Feedback::new(&turn, 10, 5, Vote::Up, 0));
+        let line = serde_json::to_string(&rated).unwrap();
+        assert!(line.starts_with(r#"{"event":"rated","conversation_id":1"#));
+        assert_eq!(serde_json::from_str::<FeedbackEvent>(&line).unwrap(), rated);
+
+        let mut log = FeedbackLog::default();
+        log.apply(rated);
+        log.apply(FeedbackEvent::Retracted {
+            turn_id: 10,
+            user_id: 5,
+            vote: Vote::Up,
+            retracted_at: 1,
+        });
+        assert!(log.entries.is_empty());
+    }
+
+    #[test]
+    fn test_vote_from_emoji() {
+        assert_eq!(Vote::from_emoji("👍🏽"), Some(Vote::Up));
+        assert_eq!(Vote::from_emoji("👎"), Some(Vote::Down));
+        assert_eq!(Vote::from_emoji("❤️"), None);
+    }
+}
@@ -0,0 +1,293 @@
+use std::collections::HashMap;
+use thiserror::Error;
+
+pub const MAX_HEROES: usize = 4;
+pub const MAX_SIDE_SCHEMES: usize = 5;
+
+/// Live games by the id of the channel or thread they're tracked in.
+pub type Games = HashMap<u64, GameState>;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum GameError {
+    #[error("Heroes are given as `Name:HP`, separated by commas, not `{0}`")]
+    InvalidHero(String),
+    #[error("Between 1 and {MAX_HEROES} heroes can be tracked")]
+    HeroCount,
+    #[error("At most {MAX_SIDE_SCHEMES} side schemes can be tracked")]
+    TooManySideSchemes,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hero {
+    pub name: String,
+    pub hp: u32,
+    pub max_hp: u32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SideScheme {
+    /// Identifies the scheme in button custom ids, which outlive its position in the list.
+    pub id: u32,
+    pub name: String,
+    pub threat: u32,
+}
+
+/// The state of a game in progress, as shown on its tracker message.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameState {
+    pub villain: String,
+    pub villain_hp: u32,
+    pub main_scheme: String,
+    pub threat: u32,
+    /// The threat at which the main scheme advances, if known.
+    pub max_threat: Option<u32>,
+    pub acceleration: u32,
+    pub heroes: Vec<Hero>,
+    pub side_schemes: Vec<SideScheme>,
+    next_side_scheme_id: u32,
+}
+
+/// A tracker button press, encoded in the button's custom id.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    Villain(i32),
+    Threat(i32),
+    Acceleration(i32),
+    Hero(usize, i32),
+    /// A side scheme by id.
+    SideScheme(u32, i32),
+}
+
+pub const ACTION_PREFIX: &str = "game:";
+
+impl Action {
+    pub fn custom_id(&self) -> String {
+        match self {
+            Action::Villain(delta) => format!("{ACTION_PREFIX}villain:{delta}"),
+            Action::Threat(delta) => format!("{ACTION_PREFIX}threat:{delta}"),
+            Action::Acceleration(delta) => format!("{ACTION_PREFIX}acceleration:{delta}"),
+            Action::Hero(index, delta) => format!("{ACTION_PREFIX}hero:{index}:{delta}"),
+            Action::SideScheme(id, delta) => format!("{ACTION_PREFIX}side:{id}:{delta}"),
+        }
+    }
+
+    pub fn parse(custom_id: &str) -> Option<Self> {
+        let parts: Vec<&str> = custom_id.strip_prefix(ACTION_PREFIX)?.split(':').collect();
+        match parts.as_slice() {
+            ["villain", delta] => Some(Action::Villain(delta.parse().ok()?)),
+            ["threat", delta] => Some(Action::Threat(delta.parse().ok()?)),
+            ["acceleration", delta] => Some(Action::Acceleration(delta.parse().ok()?)),
+            ["hero", index, delta] => Some(Action::Hero(index.parse().ok()?, delta.parse().ok()?)),
+            ["side", id, delta] => Some(Action::SideScheme(id.parse().ok()?, delta.parse().ok()?)),
+            _ => None,
+        }
+    }
+}
+
+impl GameState {
+    pub fn new(
+        villain: &str,
+        villain_hp: u32,
+        main_scheme: &str,
+        threat: u32,
+        max_threat: Option<u32>,
+        heroes: Vec<Hero>,
+    ) -> Result<Self, GameError> {
+        if heroes.is_empty() || heroes.len() > MAX_HEROES {
+            return Err(GameError::HeroCount);
+        }
+
+        Ok(Self {
+            villain: villain.to_string(),
+            villain_hp,
+            main_scheme: main_scheme.to_string(),
+            threat,
+            max_threat,
+            acceleration: 0,
+            heroes,
+            side_schemes: Vec::new(),
+            next_side_scheme_id: 0,
+        })
+    }
+
+    pub fn add_side_scheme(&mut self, name: &str, threat: u32) -> Result<(), GameError> {
+        if self.side_schemes.len() >= MAX_SIDE_SCHEMES {
+            return Err(GameError::TooManySideSchemes);
+        }
+        self.side_schemes.push(SideScheme {
+            id: self.next_side_scheme_id,
+            name: name.to_string(),
+            threat,
+        });
+        self.next_side_scheme_id += 1;
+        Ok(())
+    }
+
+    /// Applies a button press. Counters don't go below zero, heroes don't heal above their
+    /// maximum and side schemes without threat are defeated.
+    pub fn apply(&mut self, action: Action) {
+        match action {
+            Action::Villain(delta) => self.villain_hp = add(self.villain_hp, delta),
+            Action::Threat(delta) => self.threat = add(self.threat, delta),
+            Action::Acceleration(delta) => self.acceleration = add(self.acceleration, delta),
+            Action::Hero(index, delta) => {
+                if let Some(hero) = self.heroes.get_mut(index) {
+                    hero.hp = add(hero.hp, delta).min(hero.max_hp);
+                }
+            }
+            Action::SideScheme(id, delta) => {
+                if let Some(scheme) = self.side_schemes.iter_mut().find(|scheme| scheme.id == id) {
+                    scheme.threat = add(scheme.threat, delta);
+                    if scheme.threat == 0 {
+                        self.side_schemes.retain(|scheme| scheme.id != id);
+                    }
+                }
+            }
+        }
+    }
+
+    /// The state as plain text, to give the model as context.
+    pub fn describe(&self) -> String {
+        let mut lines = vec![
+            format!("Villain: {}, {} HP", self.villain, self.villain_hp),
+            format!(
+                "Main scheme: {}, {} threat{}",
+                self.main_scheme,
+                self.threat,
+                self.max_threat
+                    .map(|max| format!(" of {max}"))
+                    .unwrap_or_default()
+            ),
+            format!("Acceleration: {}", self.acceleration),
+        ];
+        for hero in &self.heroes {
+            lines.push(format!(
+                "Hero {}: {}/{} HP",
+                hero.name, hero.hp, hero.max_hp
+            ));
+        }
+        for scheme in &self.side_schemes {
+            lines.push(format!(
+                "Side scheme {}: {} threat",
+                scheme.name, scheme.threat
+            ));
+        }
+
+        lines.join("\n")
+    }
+}
+
+/// Parses `Spider-Man:10, Hulk:18` into heroes at full hit points.
+pub fn parse_heroes(text: &str) -> Result<Vec<Hero>, GameError> {
+    text.split(',')
+        .map(str::trim)
+        .filter(|hero| !hero.is_empty())
+        .map(|hero| {
+            let (name, hp) = hero
+                .rsplit_once(':')
+                .ok_or_else(|| GameError::InvalidHero(hero.to_string()))?;
+            let hp = hp
+                .trim()
+                .parse()
+                .map_err(|_| GameError::InvalidHero(hero.to_string()))?;
+            Ok(Hero {
+                name: name.trim().to_string(),
+                hp,
+                max_hp: hp,
+            })
+        })
+        .collect()
+}
+
+fn add(value: u32, delta: i32) -> u32 {
+    value.saturating_add_signed(delta)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn game() -> GameState {
+        GameState::new(
+            "Rhino",
+            14,
+            "The Break-In!",
+            0,
+            Some(7),
+            parse_heroes("Spider-Man:10, Hulk:18").unwrap(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_actions_round_trip() {
+        for action in [
+            Action::Villain(-5),
+            Action::Threat(1),
+            Action::Acceleration(-1),
+            Action::Hero(3, 1),
+            Action::SideScheme(0, -1),
+        ] {
+            assert_eq!(Action::parse(&action.custom_id()), Some(action));
+        }
+        assert_eq!(Action::parse("randomize:reroll"), None);
+    }
+
+    #[test]
+    fn test_apply() {
+        let mut game = game();
+        game.add_side_scheme("Breakin' & Takin'", 1).unwrap();
+
+        game.apply(Action::Villain(-5));
+        game.apply(Action::Threat(-1));
+        game.apply(Action::Hero(0, -3));
+        game.apply(Action::Hero(1, 1));
+        game.apply(Action::SideScheme(0, -1));
+
+        assert_eq!(game.villain_hp, 9);
+        assert_eq!(game.threat, 0);
+        assert_eq!(game.heroes[0].hp, 7);
+        assert_eq!(game.heroes[1].hp, 18);
+        assert!(game.side_schemes.is_empty());
+    }
+
+    #[test]
+    fn test_side_scheme_ids_outlive_defeated_schemes() {
+        let mut game = game();
+        game.add_side_scheme("Breakin' & Takin'", 1).unwrap();
+        game.add_side_scheme("Crowd Control", 4).unwrap();
+
+        game.apply(Action::SideScheme(0, -1));
+        game.apply(Action::SideScheme(0, -1));
+        game.apply(Action::SideScheme(1, -1));
+
+        assert_eq!(game.side_schemes.len(), 1);
+        assert_eq!(game.side_schemes[0].name, "Crowd Control");
+        assert_eq!(game.side_schemes[0].threat, 3);
+    }
+
+    #[test]
+    fn test_describe() {
+        let mut game = game();
+        game.add_side_scheme("Crowd Control", 4).unwrap();
+
+        assert_eq!(
+            game.describe(),
+            "Villain: Rhino, 14 HP\n\
+             Main scheme: The Break-In!, 0 threat of 7\n\
+             Acceleration: 0\n\
+             Hero Spider-Man: 10/10 HP\n\
+             Hero Hulk: 18/18 HP\n\
+             Side scheme Crowd Control: 4 threat"
+        );
+    }
+
+    #[test]
+    fn test_parse_heroes() {
+        assert_eq!(
+            parse_heroes("Hulk"),
+            Err(GameError::InvalidHero("Hulk".to_string()))
+        );
+        assert_eq!(parse_heroes("Ant-Man: 9").unwrap()[0].max_hp, 9);
+    }
+}
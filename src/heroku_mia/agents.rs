@@ -50,6 +50,7 @@ impl AgentRequestBuilder {
         self
     }
 
+    #[allow(dead_code)]
     pub fn stop(mut self, stop: Vec<String>) -> Self {
         self.stop = Some(stop);
         self
@@ -65,6 +66,7 @@ impl AgentRequestBuilder {
         self
     }
 
+    #[allow(dead_code)]
     pub fn top_p(mut self, top_p: f32) -> Self {
         self.top_p = Some(top_p);
         self
@@ -103,6 +105,10 @@ impl AgentTool {
     pub fn builder(r#type: AgentToolType, name: impl Into<String>) -> AgentToolBuilder {
         AgentToolBuilder::new(r#type, name.into())
     }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
 }
 
 pub struct AgentToolBuilder {
@@ -122,11 +128,13 @@ impl AgentToolBuilder {
         }
     }
 
+    #[allow(dead_code)]
     pub fn description(mut self, description: String) -> Self {
         self.description = Some(description);
         self
     }
 
+    #[allow(dead_code)]
     pub fn runtime_params(mut self, runtime_params: HerokuToolRuntimeParams) -> Self {
         self.runtime_params = Some(runtime_params);
         self
@@ -145,6 +153,7 @@ impl AgentToolBuilder {
 #[derive(Serialize, Debug, Clone)]
 #[serde(rename_all = "snake_case")]
 pub enum AgentToolType {
+    #[allow(dead_code)]
     HerokuTool,
     Mcp,
 }
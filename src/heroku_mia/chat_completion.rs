@@ -25,6 +25,7 @@ pub struct ChatCompletionRequest {
 }
 
 #[derive(Debug)]
+#[allow(dead_code)]
 pub enum ToolChoice {
     None,
     Auto,
@@ -52,6 +53,15 @@ pub struct ChatCompletionTool {
     function: FunctionDefinition,
 }
 
+impl ChatCompletionTool {
+    pub fn function(function: FunctionDefinition) -> Self {
+        Self {
+            r#type: "function".to_string(),
+            function,
+        }
+    }
+}
+
 #[derive(Serialize, Debug)]
 pub struct FunctionDefinition {
     pub name: String,
@@ -98,6 +108,7 @@ impl ChatCompletionRequestBuilder {
         }
     }
 
+    #[allow(dead_code)]
     pub fn extended_thinking(mut self, extended_thinking: ExtendedThinking) -> Self {
         self.extended_thinking = Some(extended_thinking);
         self
@@ -108,16 +119,19 @@ impl ChatCompletionRequestBuilder {
         self
     }
 
+    #[allow(dead_code)]
     pub fn stop(mut self, stop: Vec<String>) -> Self {
         self.stop = Some(stop);
         self
     }
 
+    #[allow(dead_code)]
     pub fn stream(mut self, stream: bool) -> Self {
         self.stream = Some(stream);
         self
     }
 
+    #[allow(dead_code)]
     pub fn temperature(mut self, temperature: f32) -> Self {
         self.temperature = Some(temperature);
         self
@@ -133,6 +147,7 @@ impl ChatCompletionRequestBuilder {
         self
     }
 
+    #[allow(dead_code)]
     pub fn top_p(mut self, top_p: f32) -> Self {
         self.top_p = Some(top_p);
         self
@@ -1,7 +1,4 @@
-use futures::{
-    Stream, StreamExt,
-    stream::{self, BoxStream},
-};
+use futures::{Stream, StreamExt, stream};
 use reqwest::Client as ReqwestClient;
 use reqwest_eventsource::{Event, EventSource};
 use std::pin::Pin;
@@ -10,10 +7,12 @@ use thiserror::Error;
 use super::{
     agents::{AgentRequest, CompletionObject},
     chat_completion::{ChatCompletionRequest, ChatCompletionResponse},
+    embeddings::{EmbeddingRequest, EmbeddingResponse},
     mcp_servers::McpServerResponse,
 };
 
 #[derive(Error, Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum HerokuMiaError {
     #[error("Network error: {0}")]
     ReqwestError(#[from] reqwest::Error),
@@ -128,6 +127,31 @@ impl Client {
         }
     }
 
+    pub async fn embeddings(
+        &self,
+        request_body: &EmbeddingRequest,
+    ) -> Result<EmbeddingResponse, HerokuMiaError> {
+        let response = self
+            .reqwest_client
+            .post(format!("{}/v1/embeddings", self.inference_url))
+            .header("Authorization", format!("Bearer {}", self.inference_key))
+            .header("Content-Type", "application/json")
+            .json(request_body)
+            .send()
+            .await?;
+
+        if response.status().is_success() {
+            let response_body = response.json::<EmbeddingResponse>().await?;
+            Ok(response_body)
+        } else {
+            let error_text = response
+                .text()
+                .await
+                .unwrap_or_else(|_| "Unknown API error".to_string());
+            Err(HerokuMiaError::ApiCallError(error_text))
+        }
+    }
+
     pub async fn list_mcp_servers(&self) -> Result<Vec<McpServerResponse>, HerokuMiaError> {
         let response = self
             .reqwest_client
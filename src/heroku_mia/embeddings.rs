@@ -0,0 +1,100 @@
+use serde::{Deserialize, Serialize};
+
+use super::types::Usage;
+
+#[derive(Serialize, Debug)]
+pub struct EmbeddingRequest {
+    model: String,
+    input: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    input_type: Option<InputType>,
+}
+
+/// What the embedded text is used for, which Cohere models embed differently.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum InputType {
+    SearchDocument,
+    SearchQuery,
+}
+
+impl EmbeddingRequest {
+    pub fn new(model: impl Into<String>, input: Vec<String>) -> Self {
+        Self {
+            model: model.into(),
+            input,
+            input_type: None,
+        }
+    }
+
+    pub fn input_type(mut self, input_type: InputType) -> Self {
+        self.input_type = Some(input_type);
+        self
+    }
+}
+
+#[derive(Deserialize, PartialEq, Debug)]
+pub struct EmbeddingResponse {
+    pub object: String,
+    pub data: Vec<Embedding>,
+    pub model: String,
+    pub usage: Option<Usage>,
+}
+
+#[derive(Deserialize, PartialEq, Debug)]
+pub struct Embedding {
+    pub object: String,
+    pub index: usize,
+    pub embedding: Vec<f32>,
+}
+
+impl EmbeddingResponse {
+    /// The embeddings in the order of the request's input.
+    pub fn into_embeddings(mut self) -> Vec<Vec<f32>> {
+        self.data.sort_by_key(|embedding| embedding.index);
+        self.data
+            .into_iter()
+            .map(|embedding| embedding.embedding)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_embedding_request_serialization() {
+        let request = EmbeddingRequest::new("cohere-embed-multilingual", vec!["Stun".to_string()])
+            .input_type(InputType::SearchQuery);
+
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            json!({
+                "model": "cohere-embed-multilingual",
+                "input": ["Stun"],
+                "input_type": "search_query"
+            })
+        );
+    }
+
+    #[test]
+    fn test_embedding_response_order() {
+        let response: EmbeddingResponse = serde_json::from_value(json!({
+            "object": "list",
+            "data": [
+                { "object": "embedding", "index": 1, "embedding": [0.5, 0.5] },
+                { "object": "embedding", "index": 0, "embedding": [1.0, 0.0] }
+            ],
+            "model": "cohere-embed-multilingual",
+            "usage": { "prompt_tokens": 4, "total_tokens": 4 }
+        }))
+        .unwrap();
+
+        assert_eq!(
+            response.into_embeddings(),
+            vec![vec![1.0, 0.0], vec![0.5, 0.5]]
+        );
+    }
+}
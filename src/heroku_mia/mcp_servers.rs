@@ -43,11 +43,11 @@ where
 
     match value {
         Some(v) => {
-            if v.is_object() && v.as_object().map_or(false, |obj| obj.is_empty()) {
+            if v.is_object() && v.as_object().is_some_and(|obj| obj.is_empty()) {
                 Ok(None)
             } else {
                 Annotations::deserialize(v)
-                    .map(|a| Some(a))
+                    .map(Some)
                     .map_err(serde::de::Error::custom)
             }
         }
@@ -127,10 +127,10 @@ mod tests {
         assert_eq!(tool.input_schema, json!({}));
         let annotations = tool.annotations.as_ref().unwrap();
         assert_eq!(annotations.title, Some("My Tool".to_string()));
-        assert_eq!(annotations.read_only_hint, false);
-        assert_eq!(annotations.destructive_hint, false);
-        assert_eq!(annotations.idempotent_hint, true);
-        assert_eq!(annotations.open_world_hint, false);
+        assert!(!annotations.read_only_hint);
+        assert!(!annotations.destructive_hint);
+        assert!(annotations.idempotent_hint);
+        assert!(!annotations.open_world_hint);
     }
 
     #[test]
@@ -1,6 +1,7 @@
 pub mod agents;
 pub mod chat_completion;
 pub mod client;
+pub mod embeddings;
 pub mod mcp_servers;
 pub mod types;
 
@@ -2,6 +2,7 @@ use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[allow(dead_code)]
 pub enum Role {
     #[serde(rename = "system")]
     System,
@@ -36,15 +37,15 @@ pub enum Message {
 
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 pub struct ToolCall {
-    id: String,
-    r#type: String, // always "function"
-    function: FunctionCall,
+    pub id: String,
+    pub r#type: String, // always "function"
+    pub function: FunctionCall,
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 pub struct FunctionCall {
-    name: String,
-    arguments: serde_json::Value,
+    pub name: String,
+    pub arguments: serde_json::Value,
 }
 
 #[derive(Serialize, PartialEq, Debug, Clone)]
@@ -66,6 +67,7 @@ pub struct Choice {
 
 #[derive(Deserialize, PartialEq, Debug, Clone)]
 #[serde(rename_all = "snake_case")]
+#[allow(dead_code)]
 pub enum FinishReason {
     Stop,
     Length,
@@ -0,0 +1,362 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// A bucket of `capacity` requests that refills over `period`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BucketConfig {
+    pub capacity: u32,
+    pub period: Duration,
+}
+
+impl BucketConfig {
+    /// Parses `requests/seconds`, e.g. `5/60` for five requests a minute.
+    pub fn parse(text: &str) -> Option<Self> {
+        let (capacity, seconds) = text.trim().split_once('/')?;
+        let capacity: u32 = capacity.trim().parse().ok()?;
+        let seconds: u64 = seconds.trim().parse().ok()?;
+        (capacity > 0 && seconds > 0).then(|| Self {
+            capacity,
+            period: Duration::from_secs(seconds),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct TokenBucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    fn full(config: &BucketConfig, now: Instant) -> Self {
+        Self {
+            tokens: config.capacity as f64,
+            updated: now,
+        }
+    }
+
+    fn refill(&mut self, config: &BucketConfig, now: Instant) {
+        let rate = config.capacity as f64 / config.period.as_secs_f64();
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * rate).min(config.capacity as f64);
+        self.updated = now;
+    }
+
+    /// Whether the bucket has refilled, so forgetting it changes nothing.
+    fn is_full(&self, config: &BucketConfig, now: Instant) -> bool {
+        let mut bucket = *self;
+        bucket.refill(config, now);
+        bucket.tokens >= config.capacity as f64
+    }
+
+    /// How long until a request can be taken, if it can't be now.
+    fn wait(&self, config: &BucketConfig) -> Option<Duration> {
+        let rate = config.capacity as f64 / config.period.as_secs_f64();
+        (self.tokens < 1.0).then(|| Duration::from_secs_f64((1.0 - self.tokens) / rate))
+    }
+}
+
+/// Which limit a request ran into.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Scope {
+    User,
+    Channel,
+    Guild,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LimitsConfig {
+    pub user: BucketConfig,
+    pub channel: BucketConfig,
+    pub guild: BucketConfig,
+    /// Tokens a user may spend a day, unless one of their roles allows more. Unlimited if unset.
+    pub daily_tokens: Option<u64>,
+    /// Daily tokens by role id.
+    pub role_tokens: HashMap<u64, u64>,
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        Self {
+            user: BucketConfig {
+                capacity: 5,
+                period: Duration::from_secs(60),
+            },
+            channel: BucketConfig {
+                capacity: 15,
+                period: Duration::from_secs(60),
+            },
+            guild: BucketConfig {
+                capacity: 60,
+                period: Duration::from_secs(60),
+            },
+            daily_tokens: None,
+            role_tokens: HashMap::new(),
+        }
+    }
+}
+
+impl LimitsConfig {
+    /// The daily token quota of a member with these roles: the largest of their roles' quotas,
+    /// or the default quota.
+    pub fn quota(&self, roles: &[u64]) -> Option<u64> {
+        roles
+            .iter()
+            .filter_map(|role| self.role_tokens.get(role))
+            .max()
+            .copied()
+            .or(self.daily_tokens)
+    }
+}
+
+/// Parses `role_id:tokens` pairs separated by commas.
+pub fn parse_role_tokens(text: &str) -> Option<HashMap<u64, u64>> {
+    text.split(',')
+        .map(str::trim)
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (role, tokens) = pair.split_once(':')?;
+            Some((role.trim().parse().ok()?, tokens.trim().parse().ok()?))
+        })
+        .collect()
+}
+
+/// Why a request was turned away.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Limited {
+    /// Too many requests; one can be made again after the wait.
+    Rate { scope: Scope, wait: Duration },
+    /// The daily token quota is spent until the next UTC day starts.
+    Quota { quota: u64 },
+}
+
+/// Token buckets for requests per user, per channel and per guild. Buckets that have refilled
+/// are dropped, as a new bucket starts out full.
+#[derive(Debug)]
+pub struct RateLimiter {
+    config: LimitsConfig,
+    users: HashMap<u64, TokenBucket>,
+    channels: HashMap<u64, TokenBucket>,
+    guilds: HashMap<u64, TokenBucket>,
+}
+
+impl RateLimiter {
+    pub fn new(config: LimitsConfig) -> Self {
+        Self {
+            config,
+            users: HashMap::new(),
+            channels: HashMap::new(),
+            guilds: HashMap::new(),
+        }
+    }
+
+    pub fn config(&self) -> &LimitsConfig {
+        &self.config
+    }
+
+    /// Takes a request from the user's, the channel's and the guild's buckets, or from none of
+    /// them if any is empty. Direct messages have no guild bucket.
+    pub fn check(
+        &mut self,
+        user_id: u64,
+        channel_id: u64,
+        guild_id: Option<u64>,
+        now: Instant,
+    ) -> Result<(), Limited> {
+        self.evict_full(now);
+
+        let config = &self.config;
+        let user = self
+            .users
+            .entry(user_id)
+            .or_insert_with(|| TokenBucket::full(&config.user, now));
+        let channel = self
+            .channels
+            .entry(channel_id)
+            .or_insert_with(|| TokenBucket::full(&config.channel, now));
+        let guild = guild_id.map(|guild_id| {
+            self.guilds
+                .entry(guild_id)
+                .or_insert_with(|| TokenBucket::full(&config.guild, now))
+        });
+
+        let mut buckets = vec![
+            (Scope::User, user, &config.user),
+            (Scope::Channel, channel, &config.channel),
+        ];
+        buckets.extend(guild.map(|guild| (Scope::Guild, guild, &config.guild)));
+        for (scope, bucket, config) in &mut buckets {
+            bucket.refill(config, now);
+            if let Some(wait) = bucket.wait(config) {
+                return Err(Limited::Rate {
+                    scope: *scope,
+                    wait,
+                });
+            }
+        }
+        for (_, bucket, _) in &mut buckets {
+            bucket.tokens -= 1.0;
+        }
+
+        Ok(())
+    }
+
+    fn evict_full(&mut self, now: Instant) {
+        let config = &self.config;
+        self.users
+            .retain(|_, bucket| !bucket.is_full(&config.user, now));
+        self.channels
+            .retain(|_, bucket| !bucket.is_full(&config.channel, now));
+        self.guilds
+            .retain(|_, bucket| !bucket.is_full(&config.guild, now));
+    }
+}
+
+/// Tokens spent today, by user.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct DailyUsage {
+    /// Days since the Unix epoch, in UTC.
+    pub day: u64,
+    pub tokens: HashMap<u64, u64>,
+}
+
+impl DailyUsage {
+    pub fn used(&self, user_id: u64, today: u64) -> u64 {
+        if self.day == today {
+            self.tokens.get(&user_id).copied().unwrap_or_default()
+        } else {
+            0
+        }
+    }
+
+    pub fn add(&mut self, user_id: u64, tokens: u64, today: u64) {
+        if self.day != today {
+            self.day = today;
+            self.tokens.clear();
+        }
+        *self.tokens.entry(user_id).or_default() += tokens;
+    }
+
+    pub fn check(&self, user_id: u64, quota: Option<u64>, today: u64) -> Result<(), Limited> {
+        match quota {
+            Some(quota) if self.used(user_id, today) >= quota => Err(Limited::Quota { quota }),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Days since the Unix epoch, in UTC.
+pub fn today() -> u64 {
+    unix_now() / SECONDS_PER_DAY
+}
+
+/// The Unix time the next UTC day starts.
+pub fn tomorrow() -> u64 {
+    (today() + 1) * SECONDS_PER_DAY
+}
+
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> LimitsConfig {
+        LimitsConfig {
+            user: BucketConfig::parse("2/60").unwrap(),
+            channel: BucketConfig::parse("3/60").unwrap(),
+            guild: BucketConfig::parse("10/60").unwrap(),
+            daily_tokens: Some(1000),
+            role_tokens: parse_role_tokens("1:5000, 2:0").unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_rate_limits() {
+        let mut limiter = RateLimiter::new(config());
+        let now = Instant::now();
+
+        assert_eq!(limiter.check(1, 10, Some(100), now), Ok(()));
+        assert_eq!(limiter.check(1, 10, Some(100), now), Ok(()));
+        assert!(matches!(
+            limiter.check(1, 10, Some(100), now),
+            Err(Limited::Rate {
+                scope: Scope::User,
+                wait
+            }) if wait.as_secs_f64().round() == 30.0
+        ));
+        assert_eq!(limiter.check(2, 10, Some(100), now), Ok(()));
+        assert!(matches!(
+            limiter.check(3, 10, Some(100), now),
+            Err(Limited::Rate {
+                scope: Scope::Channel,
+                ..
+            })
+        ));
+        // A request turned away by the channel doesn't use up the user's bucket.
+        assert_eq!(limiter.check(3, 11, Some(100), now), Ok(()));
+        assert_eq!(
+            limiter.check(1, 11, Some(100), now + Duration::from_secs(31)),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_guild_buckets() {
+        let mut limiter = RateLimiter::new(LimitsConfig {
+            user: BucketConfig::parse("10/60").unwrap(),
+            channel: BucketConfig::parse("10/60").unwrap(),
+            guild: BucketConfig::parse("1/60").unwrap(),
+            ..config()
+        });
+        let now = Instant::now();
+
+        assert_eq!(limiter.check(1, 10, Some(100), now), Ok(()));
+        assert!(matches!(
+            limiter.check(1, 10, Some(100), now),
+            Err(Limited::Rate {
+                scope: Scope::Guild,
+                ..
+            })
+        ));
+        assert_eq!(limiter.check(1, 20, Some(200), now), Ok(()));
+        assert_eq!(limiter.check(1, 30, None, now), Ok(()));
+
+        // Once refilled, buckets are forgotten.
+        limiter
+            .check(2, 40, None, now + Duration::from_secs(60))
+            .unwrap();
+        assert!(limiter.guilds.is_empty());
+        assert_eq!(limiter.users.keys().collect::<Vec<_>>(), vec![&2]);
+    }
+
+    #[test]
+    fn test_quotas() {
+        let config = config();
+        assert_eq!(config.quota(&[]), Some(1000));
+        assert_eq!(config.quota(&[1, 2]), Some(5000));
+        assert_eq!(config.quota(&[2]), Some(0));
+        assert_eq!(parse_role_tokens("1:x"), None);
+
+        let mut usage = DailyUsage::default();
+        usage.add(1, 1200, 100);
+        assert_eq!(
+            usage.check(1, config.quota(&[]), 100),
+            Err(Limited::Quota { quota: 1000 })
+        );
+        assert_eq!(usage.check(1, config.quota(&[1]), 100), Ok(()));
+        assert_eq!(usage.check(1, config.quota(&[]), 101), Ok(()));
+
+        usage.add(2, 10, 101);
+        assert_eq!(usage.used(1, 101), 0);
+    }
+}
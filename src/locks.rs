@@ -0,0 +1,63 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+use tokio::sync::{Mutex as AsyncMutex, OwnedMutexGuard};
+
+/// Held while working on a key; dropping it lets the next waiter for the key go ahead.
+pub type KeyGuard = OwnedMutexGuard<()>;
+
+/// One lock per key, so work on different keys runs concurrently while work on the same key
+/// runs one at a time, in the order it asked for the lock.
+#[derive(Debug, Default)]
+pub struct KeyedLocks {
+    locks: Mutex<HashMap<u64, Arc<AsyncMutex<()>>>>,
+}
+
+impl KeyedLocks {
+    pub async fn lock(&self, key: u64) -> KeyGuard {
+        let lock = {
+            let mut locks = self.locks.lock().expect("Lock map poisoned");
+            // Guards and waiters hold a reference, so the other locks are free to forget.
+            locks.retain(|_, lock| Arc::strong_count(lock) > 1);
+            Arc::clone(locks.entry(key).or_default())
+        };
+
+        lock.lock_owned().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_keyed_locks() {
+        let locks = Arc::new(KeyedLocks::default());
+        let order = Arc::new(Mutex::new(Vec::new()));
+
+        let first = locks.lock(1).await;
+        let _other_key = tokio::time::timeout(Duration::from_millis(10), locks.lock(2))
+            .await
+            .expect("Another key isn't blocked");
+
+        let mut waiting = Vec::new();
+        for turn in 0..3 {
+            let (locks, order) = (Arc::clone(&locks), Arc::clone(&order));
+            waiting.push(tokio::spawn(async move {
+                let _guard = locks.lock(1).await;
+                order.lock().unwrap().push(turn);
+            }));
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        assert!(order.lock().unwrap().is_empty());
+
+        drop(first);
+        for task in waiting {
+            task.await.unwrap();
+        }
+        assert_eq!(*order.lock().unwrap(), vec![0, 1, 2]);
+        assert_eq!(locks.locks.lock().unwrap().len(), 2);
+    }
+}
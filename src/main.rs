@@ -1,14 +1,53 @@
-use crate::heroku_mia::{
-    Client,
-    agents::{AgentTool, AgentToolType},
+use crate::{
+    campaign::CampaignLogs,
+    cards::{CardDatabase, collection::Collections, linker::CardLinker, randomizer::RecentSetups},
+    conversations::Conversations,
+    discord::DEFAULT_SYSTEM_PROMPT,
+    feedback::FeedbackLog,
+    heroku_mia::Client,
+    limits::{BucketConfig, DailyUsage, LimitsConfig, RateLimiter},
+    queue::InferenceQueue,
+    rules::{Retriever, RulesIndex},
+    settings::Settings,
+    storage::{JsonStore, JsonlStore},
+    tools::{
+        LocalTools, card_search::CardSearch, draw_odds::DrawOddsTool, validate_deck::ValidateDeck,
+    },
+};
+use serenity::{
+    all::{ApplicationId, RoleId},
+    model::prelude::GuildId,
+    prelude::*,
+};
+use std::{
+    collections::HashMap,
+    env,
+    path::{Path, PathBuf},
+    sync::Arc,
 };
-use serenity::{all::ApplicationId, model::prelude::GuildId, prelude::*};
-use std::{collections::HashMap, env, sync::Arc};
 use tracing::instrument;
 use tracing_subscriber::{self, EnvFilter};
 
+mod campaign;
+mod cards;
+mod conversations;
 mod discord;
+mod feedback;
+mod game;
 mod heroku_mia;
+mod limits;
+mod locks;
+mod personas;
+mod queue;
+mod rules;
+mod settings;
+mod storage;
+mod tools;
+
+/// Agent calls that can stream at once unless `MAX_CONCURRENT_INFERENCE` says otherwise.
+const DEFAULT_MAX_CONCURRENT_INFERENCE: usize = 4;
+/// `/randomize` setups that can still be rerolled.
+const MAX_REROLLABLE_SETUPS: usize = 1000;
 
 #[tokio::main]
 #[instrument]
@@ -43,40 +82,188 @@ async fn main() -> anyhow::Result<()> {
             .expect("application id is not a valid id"),
     );
 
-    let conversation_history = Arc::new(RwLock::new(HashMap::new()));
-
-    let heroku_mia_client = Client::new(inference_url, inference_key);
-    let tools: Vec<AgentTool>;
-
-    match heroku_mia_client.list_mcp_servers().await {
-        Ok(servers) => {
-            tools = servers
-                .into_iter()
-                .flat_map(|server| {
-                    server.tools.into_iter().map(|tool| {
-                        AgentTool::builder(AgentToolType::Mcp, tool.namespaced_name).build()
-                    })
+    let admin_roles: Vec<RoleId> = env::var("ADMIN_ROLE_IDS")
+        .map(|roles| {
+            roles
+                .split(',')
+                .map(|role| {
+                    RoleId::new(
+                        role.trim()
+                            .parse()
+                            .expect("ADMIN_ROLE_IDS must be role ids separated by commas"),
+                    )
                 })
-                .collect();
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let card_image_dir = env::var("CARD_IMAGE_DIR").ok().map(PathBuf::from);
+    match &card_image_dir {
+        Some(card_image_dir) => tracing::info!("CARD_IMAGE_DIR: {}", card_image_dir.display()),
+        None => tracing::info!("CARD_IMAGE_DIR not set, card embeds won't have images"),
+    }
+
+    let data_dir = env::var("DATA_DIR").ok().map(PathBuf::from);
+    match &data_dir {
+        Some(data_dir) => tracing::info!("DATA_DIR: {}", data_dir.display()),
+        None => tracing::info!("DATA_DIR not set, user data won't outlive the process"),
+    }
+    let collections: JsonStore<Collections> =
+        JsonStore::open(data_dir.as_deref(), "collections.json")?;
+    let campaign_logs: JsonStore<CampaignLogs> =
+        JsonStore::open(data_dir.as_deref(), "campaigns.json")?;
+    let usage: JsonStore<DailyUsage> = JsonStore::open(data_dir.as_deref(), "usage.json")?;
+    let settings: JsonStore<Settings> = JsonStore::open(data_dir.as_deref(), "settings.json")?;
+    let feedback: JsonlStore<FeedbackLog> =
+        JsonlStore::open(data_dir.as_deref(), "feedback.jsonl")?;
+    let (inference_model_id, system_prompt) = {
+        let settings = settings.read().await;
+        if let Some(model_id) = &settings.model_id {
+            tracing::info!("Using the model set with /karen: {model_id}");
+        }
+        (
+            settings.model_id.clone().unwrap_or(inference_model_id),
+            settings
+                .system_prompt
+                .clone()
+                .unwrap_or_else(|| DEFAULT_SYSTEM_PROMPT.to_string()),
+        )
+    };
+
+    let limits_config = limits_config();
+    tracing::info!("Limits: {:?}", limits_config);
+    let max_concurrent_inference = match env::var("MAX_CONCURRENT_INFERENCE") {
+        Ok(limit) => limit
+            .parse()
+            .ok()
+            .filter(|limit| *limit > 0)
+            .expect("MAX_CONCURRENT_INFERENCE must be a positive number"),
+        Err(_) => DEFAULT_MAX_CONCURRENT_INFERENCE,
+    };
+    tracing::info!("MAX_CONCURRENT_INFERENCE: {max_concurrent_inference}");
+
+    let card_database = match env::var("CARD_DATA_DIR") {
+        Ok(card_data_dir) => match CardDatabase::load(Path::new(&card_data_dir)) {
+            Ok(card_database) => {
+                tracing::info!(
+                    "Loaded {} cards from CARD_DATA_DIR: {}",
+                    card_database.len(),
+                    card_data_dir
+                );
+                card_database
+            }
+            Err(e) => {
+                tracing::error!("Error loading the card database: {e}");
+                return Err(e.into());
+            }
+        },
+        Err(_) => {
+            tracing::info!("CARD_DATA_DIR not set, the local card database is empty");
+            CardDatabase::default()
+        }
+    };
+    let card_database = Arc::new(card_database);
+    let card_linker = Arc::new(CardLinker::new(&card_database));
+
+    let mut local_tools = LocalTools::default().with(DrawOddsTool);
+    if !card_database.is_empty() {
+        local_tools = local_tools
+            .with(CardSearch::new(Arc::clone(&card_database)))
+            .with(ValidateDeck::new(Arc::clone(&card_database)));
+    }
+    tracing::info!("Local tools: {}", local_tools.names().join(", "));
+
+    let rules = match (
+        env::var("RULES_DIR"),
+        env::var("EMBEDDING_URL"),
+        env::var("EMBEDDING_KEY"),
+        env::var("EMBEDDING_MODEL_ID"),
+    ) {
+        (Ok(rules_dir), Ok(embedding_url), Ok(embedding_key), Ok(embedding_model_id)) => {
+            tracing::info!("RULES_DIR: {}", rules_dir);
+            tracing::info!("EMBEDDING_MODEL_ID: {}", embedding_model_id);
+            let index: JsonStore<RulesIndex> =
+                JsonStore::open(data_dir.as_deref(), "rules_index.json")?;
+            let retriever = Arc::new(Retriever::new(
+                Client::new(embedding_url, embedding_key),
+                embedding_model_id,
+                PathBuf::from(rules_dir),
+                index,
+            ));
+            if retriever.is_stale().await {
+                let retriever = Arc::clone(&retriever);
+                tokio::spawn(async move {
+                    match retriever.reindex().await {
+                        Ok(count) => tracing::info!("Indexed {count} rules passages"),
+                        Err(e) => tracing::error!("Error indexing the rules: {e}"),
+                    }
+                });
+            }
+            Some(retriever)
         }
+        _ => {
+            tracing::info!(
+                "RULES_DIR or EMBEDDING_URL, EMBEDDING_KEY and EMBEDDING_MODEL_ID not set, answers won't cite the rules"
+            );
+            None
+        }
+    };
+
+    let conversation_history = Arc::new(RwLock::new(Conversations::default()));
+
+    let heroku_mia_client = Client::new(inference_url, inference_key);
+    let tools = match tools::mcp_tools(&heroku_mia_client).await {
+        Ok(tools) => tools,
         Err(e) => {
             tracing::error!("Heroku MIA Error listing MCP servers: {e}");
-            return Err(e)?;
+            return Err(e.into());
         }
-    }
+    };
 
-    let mut discord_client =
-        serenity::Client::builder(discord_token, GatewayIntents::GUILD_MESSAGES)
-            .event_handler(discord::Handler {})
-            .await
-            .expect("Error creating discord client.");
+    // MESSAGE_CONTENT is needed to read follow-ups in conversation threads, which don't
+    // mention the bot. GUILD_MESSAGE_REACTIONS brings the 👍 and 👎 rating answers.
+    let intents = GatewayIntents::GUILD_MESSAGES
+        | GatewayIntents::MESSAGE_CONTENT
+        | GatewayIntents::GUILD_MESSAGE_REACTIONS;
+    let mut discord_client = serenity::Client::builder(discord_token, intents)
+        .application_id(application_id)
+        .event_handler(discord::Handler {})
+        .await
+        .expect("Error creating discord client.");
     {
         let mut data = discord_client.data.write().await;
         data.insert::<discord::type_map_keys::ConversationHistory>(conversation_history);
+        data.insert::<discord::type_map_keys::ConversationLocks>(Arc::default());
         data.insert::<discord::type_map_keys::GuildId>(guild_id);
         data.insert::<discord::type_map_keys::HerokuMiaClient>(heroku_mia_client);
-        data.insert::<discord::type_map_keys::InferenceModelId>(inference_model_id);
-        data.insert::<discord::type_map_keys::AgentTools>(tools);
+        data.insert::<discord::type_map_keys::InferenceModelId>(Arc::new(RwLock::new(
+            inference_model_id,
+        )));
+        data.insert::<discord::type_map_keys::SystemPrompt>(Arc::new(RwLock::new(system_prompt)));
+        data.insert::<discord::type_map_keys::AgentTools>(Arc::new(RwLock::new(tools)));
+        data.insert::<discord::type_map_keys::CardImageDir>(card_image_dir);
+        data.insert::<discord::type_map_keys::CardDatabase>(card_database);
+        data.insert::<discord::type_map_keys::CardLinker>(card_linker);
+        data.insert::<discord::type_map_keys::LocalTools>(local_tools);
+        data.insert::<discord::type_map_keys::Randomizations>(Arc::new(RwLock::new(
+            RecentSetups::new(MAX_REROLLABLE_SETUPS),
+        )));
+        data.insert::<discord::type_map_keys::Collections>(Arc::new(collections));
+        data.insert::<discord::type_map_keys::CampaignLogs>(Arc::new(campaign_logs));
+        data.insert::<discord::type_map_keys::Games>(Arc::new(RwLock::new(HashMap::new())));
+        data.insert::<discord::type_map_keys::MarvelCdbClient>(cards::marvelcdb::Client::default());
+        data.insert::<discord::type_map_keys::Rules>(rules);
+        data.insert::<discord::type_map_keys::RateLimiter>(Arc::new(Mutex::new(RateLimiter::new(
+            limits_config,
+        ))));
+        data.insert::<discord::type_map_keys::Usage>(Arc::new(usage));
+        data.insert::<discord::type_map_keys::Settings>(Arc::new(settings));
+        data.insert::<discord::type_map_keys::AdminRoles>(admin_roles);
+        data.insert::<discord::type_map_keys::InferenceQueue>(Arc::new(InferenceQueue::new(
+            max_concurrent_inference,
+        )));
+        data.insert::<discord::type_map_keys::Feedback>(Arc::new(feedback));
+        data.insert::<discord::type_map_keys::Answers>(Arc::default());
     }
 
     if let Err(err) = discord_client.start().await {
@@ -85,3 +272,33 @@ async fn main() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+/// Reads the rate limits, as `requests/seconds`, from `RATE_LIMIT_USER`, `RATE_LIMIT_CHANNEL` and
+/// `RATE_LIMIT_GUILD`, and the daily token quotas from `DAILY_TOKEN_QUOTA` and
+/// `ROLE_TOKEN_QUOTAS`, as `role_id:tokens` pairs separated by commas.
+fn limits_config() -> LimitsConfig {
+    let defaults = LimitsConfig::default();
+    let bucket = |name: &str, default: BucketConfig| match env::var(name) {
+        Ok(value) => BucketConfig::parse(&value)
+            .unwrap_or_else(|| panic!("{name} must be requests/seconds, e.g. 5/60")),
+        Err(_) => default,
+    };
+
+    LimitsConfig {
+        user: bucket("RATE_LIMIT_USER", defaults.user),
+        channel: bucket("RATE_LIMIT_CHANNEL", defaults.channel),
+        guild: bucket("RATE_LIMIT_GUILD", defaults.guild),
+        daily_tokens: env::var("DAILY_TOKEN_QUOTA").ok().map(|quota| {
+            quota
+                .parse()
+                .expect("DAILY_TOKEN_QUOTA must be a number of tokens")
+        }),
+        role_tokens: env::var("ROLE_TOKEN_QUOTAS")
+            .ok()
+            .map(|quotas| {
+                limits::parse_role_tokens(&quotas)
+                    .expect("ROLE_TOKEN_QUOTAS must be role_id:tokens pairs separated by commas")
+            })
+            .unwrap_or_default(),
+    }
+}
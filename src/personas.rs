@@ -0,0 +1,142 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fmt};
+
+/// Which tools agent calls may use.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolPolicy {
+    #[default]
+    All,
+    None,
+    /// Only the tools with these names.
+    Only(Vec<String>),
+}
+
+impl ToolPolicy {
+    /// Parses `all`, `none` or tool names separated by commas.
+    pub fn parse(text: &str) -> Self {
+        match text.trim().to_lowercase().as_str() {
+            "all" | "" => ToolPolicy::All,
+            "none" => ToolPolicy::None,
+            _ => ToolPolicy::Only(
+                text.split(',')
+                    .map(str::trim)
+                    .filter(|name| !name.is_empty())
+                    .map(str::to_string)
+                    .collect(),
+            ),
+        }
+    }
+
+    pub fn allows(&self, name: &str) -> bool {
+        match self {
+            ToolPolicy::All => true,
+            ToolPolicy::None => false,
+            ToolPolicy::Only(names) => names.iter().any(|allowed| allowed == name),
+        }
+    }
+}
+
+impl fmt::Display for ToolPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ToolPolicy::All => f.write_str("all tools"),
+            ToolPolicy::None => f.write_str("no tools"),
+            ToolPolicy::Only(names) => write!(
+                f,
+                "only {}",
+                names
+                    .iter()
+                    .map(|name| format!("`{name}`"))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+    }
+}
+
+/// How the bot answers in a channel or thread. Unset fields fall back to the parent channel's
+/// persona, then to the bot's settings.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Persona {
+    #[serde(default)]
+    pub system_prompt: Option<String>,
+    #[serde(default)]
+    pub model_id: Option<String>,
+    #[serde(default)]
+    pub temperature: Option<f32>,
+    #[serde(default)]
+    pub tools: Option<ToolPolicy>,
+}
+
+impl Persona {
+    /// This persona, with the fields it leaves unset taken from `fallback`.
+    pub fn or(self, fallback: &Persona) -> Persona {
+        Persona {
+            system_prompt: self
+                .system_prompt
+                .or_else(|| fallback.system_prompt.clone()),
+            model_id: self.model_id.or_else(|| fallback.model_id.clone()),
+            temperature: self.temperature.or(fallback.temperature),
+            tools: self.tools.or_else(|| fallback.tools.clone()),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Persona::default()
+    }
+}
+
+/// Personas by channel or thread id.
+pub type Personas = HashMap<u64, Persona>;
+
+/// The persona of a channel, or of a thread with its parent channel's as the fallback.
+pub fn resolve(personas: &Personas, channel_id: u64, parent_id: Option<u64>) -> Persona {
+    let own = personas.get(&channel_id).cloned().unwrap_or_default();
+    match parent_id.and_then(|parent_id| personas.get(&parent_id)) {
+        Some(parent) => own.or(parent),
+        None => own,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve() {
+        let personas = Personas::from([
+            (
+                1,
+                Persona {
+                    system_prompt: Some("Cite the rules.".to_string()),
+                    temperature: Some(0.2),
+                    tools: Some(ToolPolicy::None),
+                    ..Persona::default()
+                },
+            ),
+            (
+                2,
+                Persona {
+                    temperature: Some(0.9),
+                    ..Persona::default()
+                },
+            ),
+        ]);
+
+        let persona = resolve(&personas, 2, Some(1));
+        assert_eq!(persona.system_prompt.as_deref(), Some("Cite the rules."));
+        assert_eq!(persona.temperature, Some(0.9));
+        assert_eq!(persona.tools, Some(ToolPolicy::None));
+        assert!(resolve(&personas, 3, None).is_empty());
+    }
+
+    #[test]
+    fn test_tool_policy() {
+        assert_eq!(ToolPolicy::parse(" None "), ToolPolicy::None);
+        let policy = ToolPolicy::parse("card_search, draw_odds");
+        assert!(policy.allows("draw_odds"));
+        assert!(!policy.allows("validate_deck"));
+        assert_eq!(policy.to_string(), "only `card_search`, `draw_odds`");
+    }
+}
@@ -0,0 +1,159 @@
+use futures::FutureExt;
+use std::{
+    collections::BTreeSet,
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+};
+use tokio::sync::{AcquireError, OwnedSemaphorePermit, Semaphore};
+
+/// Held while a request runs inference; dropping it lets the next request in the queue start.
+pub type Permit = OwnedSemaphorePermit;
+
+type Acquire = Pin<Box<dyn Future<Output = Result<Permit, AcquireError>> + Send>>;
+
+/// Limits how many requests run inference at once. Requests over the limit wait first come,
+/// first served.
+#[derive(Debug)]
+pub struct InferenceQueue {
+    limit: usize,
+    semaphore: Arc<Semaphore>,
+    waiting: Arc<Mutex<Waiting>>,
+}
+
+#[derive(Debug, Default)]
+struct Waiting {
+    tickets: BTreeSet<u64>,
+    next_ticket: u64,
+}
+
+/// A place in the queue, held from the moment the request is queued.
+pub struct Ticket {
+    number: u64,
+    acquire: Acquire,
+    waiting: Arc<Mutex<Waiting>>,
+}
+
+impl InferenceQueue {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            semaphore: Arc::new(Semaphore::new(limit)),
+            waiting: Arc::default(),
+        }
+    }
+
+    /// Starts a request right away if there is capacity, or else queues it.
+    pub fn start(&self) -> Result<Permit, Ticket> {
+        // Polling the acquisition once joins the semaphore's first come, first served waiters
+        // right away, in the same order as the tickets.
+        let mut waiting = self.waiting.lock().expect("Queue lock poisoned");
+        let mut acquire: Acquire = Box::pin(Arc::clone(&self.semaphore).acquire_owned());
+        if let Some(permit) = (&mut acquire).now_or_never() {
+            return Ok(permit.expect("The inference semaphore is never closed"));
+        }
+
+        let number = waiting.next_ticket;
+        waiting.next_ticket += 1;
+        waiting.tickets.insert(number);
+        Err(Ticket {
+            number,
+            acquire,
+            waiting: Arc::clone(&self.waiting),
+        })
+    }
+
+    /// Requests running inference.
+    pub fn running(&self) -> usize {
+        self.limit - self.semaphore.available_permits()
+    }
+
+    pub fn queued(&self) -> usize {
+        self.waiting
+            .lock()
+            .expect("Queue lock poisoned")
+            .tickets
+            .len()
+    }
+}
+
+impl Ticket {
+    /// The place in the queue, counting from 1.
+    pub fn position(&self) -> usize {
+        self.waiting
+            .lock()
+            .expect("Queue lock poisoned")
+            .tickets
+            .range(..self.number)
+            .count()
+            + 1
+    }
+
+    /// Waits for the request's turn.
+    pub async fn wait(mut self) -> Permit {
+        (&mut self.acquire)
+            .await
+            .expect("The inference semaphore is never closed")
+    }
+}
+
+impl fmt::Debug for Ticket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Ticket")
+            .field("number", &self.number)
+            .finish()
+    }
+}
+
+impl Drop for Ticket {
+    fn drop(&mut self) {
+        self.waiting
+            .lock()
+            .expect("Queue lock poisoned")
+            .tickets
+            .remove(&self.number);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_queue() {
+        let queue = InferenceQueue::new(1);
+
+        let permit = queue.start().unwrap();
+        let second = queue.start().unwrap_err();
+        let third = queue.start().unwrap_err();
+        assert_eq!((second.position(), third.position()), (1, 2));
+        assert_eq!((queue.running(), queue.queued()), (1, 2));
+
+        drop(second);
+        assert_eq!(third.position(), 1);
+
+        let waiting = tokio::spawn(third.wait());
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert!(!waiting.is_finished());
+        drop(permit);
+        let _permit = waiting.await.unwrap();
+        assert_eq!((queue.running(), queue.queued()), (1, 0));
+    }
+
+    #[tokio::test]
+    async fn test_queued_tickets_go_first() {
+        let queue = InferenceQueue::new(1);
+
+        let permit = queue.start().unwrap();
+        let queued = queue.start().unwrap_err();
+        drop(permit);
+        // The freed permit belongs to the queued ticket, even before it waits.
+        let newcomer = queue.start().unwrap_err();
+        assert_eq!(newcomer.position(), 2);
+
+        let _permit = queued.wait().await;
+        assert_eq!((queue.running(), queue.queued()), (1, 1));
+    }
+}
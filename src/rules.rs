@@ -0,0 +1,383 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+
+use crate::{
+    heroku_mia::{
+        Client,
+        client::HerokuMiaError,
+        embeddings::{EmbeddingRequest, InputType},
+    },
+    storage::{JsonStore, StorageError},
+};
+
+/// Passages longer than this are split at paragraph breaks.
+const MAX_PASSAGE_CHARS: usize = 1500;
+/// Most texts the embeddings endpoint accepts in one request.
+const EMBEDDING_BATCH_SIZE: usize = 96;
+/// Passages less similar to the question than this aren't worth the context tokens.
+const MIN_SIMILARITY: f32 = 0.2;
+
+#[derive(Error, Debug)]
+#[allow(clippy::enum_variant_names)]
+pub enum RulesError {
+    #[error("IO error with {0}: {1}")]
+    IoError(PathBuf, io::Error),
+    #[error("Heroku MIA error: {0}")]
+    HerokuMiaError(Box<HerokuMiaError>),
+    #[error("Storage error: {0}")]
+    StorageError(#[from] StorageError),
+    #[error("Expected {expected} embeddings, got {actual}")]
+    EmbeddingCountError { expected: usize, actual: usize },
+}
+
+impl From<HerokuMiaError> for RulesError {
+    fn from(e: HerokuMiaError) -> Self {
+        RulesError::HerokuMiaError(Box::new(e))
+    }
+}
+
+/// A section, or part of a section, of a rules document.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Passage {
+    /// The document, e.g. `Rules Reference`.
+    pub source: String,
+    /// The path of headings the passage is under, e.g. `Attack > Overkill`.
+    pub section: String,
+    pub text: String,
+}
+
+impl Passage {
+    /// How the model is asked to cite the passage.
+    pub fn citation(&self) -> String {
+        if self.section.is_empty() {
+            format!("[{}]", self.source)
+        } else {
+            format!("[{} § {}]", self.source, self.section)
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct IndexedPassage {
+    pub passage: Passage,
+    pub embedding: Vec<f32>,
+}
+
+/// The embedded passages of the rules documents, with the model that embedded them.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct RulesIndex {
+    pub model: String,
+    pub passages: Vec<IndexedPassage>,
+}
+
+impl RulesIndex {
+    /// The `k` passages most similar to the query.
+    pub fn search(&self, query: &[f32], k: usize) -> Vec<&Passage> {
+        let mut scored: Vec<(f32, &Passage)> = self
+            .passages
+            .iter()
+            .map(|indexed| {
+                (
+                    cosine_similarity(query, &indexed.embedding),
+                    &indexed.passage,
+                )
+            })
+            .filter(|(similarity, _)| *similarity >= MIN_SIMILARITY)
+            .collect();
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+        scored
+            .into_iter()
+            .take(k)
+            .map(|(_, passage)| passage)
+            .collect()
+    }
+}
+
+/// Retrieves rules passages for questions, from an index of the documents in a directory.
+#[derive(Debug)]
+pub struct Retriever {
+    client: Client,
+    model: String,
+    dir: PathBuf,
+    index: JsonStore<RulesIndex>,
+}
+
+impl Retriever {
+    pub fn new(client: Client, model: String, dir: PathBuf, index: JsonStore<RulesIndex>) -> Self {
+        Self {
+            client,
+            model,
+            dir,
+            index,
+        }
+    }
+
+    /// Whether the index is empty or was embedded with another model.
+    pub async fn is_stale(&self) -> bool {
+        let index = self.index.read().await;
+        index.passages.is_empty() || index.model != self.model
+    }
+
+    /// Chunks and embeds the documents again, replacing the index. Returns the number of
+    /// passages indexed.
+    pub async fn reindex(&self) -> Result<usize, RulesError> {
+        let passages = load_documents(&self.dir)?;
+
+        let mut indexed = Vec::with_capacity(passages.len());
+        for batch in passages.chunks(EMBEDDING_BATCH_SIZE) {
+            let input = batch
+                .iter()
+                .map(|passage| format!("{}\n{}", passage.citation(), passage.text))
+                .collect();
+            let embeddings = self.embed(input, InputType::SearchDocument).await?;
+            if embeddings.len() != batch.len() {
+                return Err(RulesError::EmbeddingCountError {
+                    expected: batch.len(),
+                    actual: embeddings.len(),
+                });
+            }
+            indexed.extend(
+                batch
+                    .iter()
+                    .cloned()
+                    .zip(embeddings)
+                    .map(|(passage, embedding)| IndexedPassage { passage, embedding }),
+            );
+        }
+
+        let count = indexed.len();
+        let model = self.model.clone();
+        self.index
+            .update(|index| {
+                *index = RulesIndex {
+                    model,
+                    passages: indexed,
+                }
+            })
+            .await?;
+
+        Ok(count)
+    }
+
+    /// The `k` passages most relevant to `question`.
+    pub async fn retrieve(&self, question: &str, k: usize) -> Result<Vec<Passage>, RulesError> {
+        if self.index.read().await.passages.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let query = self
+            .embed(vec![question.to_string()], InputType::SearchQuery)
+            .await?;
+        let Some(query) = query.first() else {
+            return Ok(Vec::new());
+        };
+
+        Ok(self
+            .index
+            .read()
+            .await
+            .search(query, k)
+            .into_iter()
+            .cloned()
+            .collect())
+    }
+
+    async fn embed(
+        &self,
+        input: Vec<String>,
+        input_type: InputType,
+    ) -> Result<Vec<Vec<f32>>, RulesError> {
+        let request = EmbeddingRequest::new(&self.model, input).input_type(input_type);
+        Ok(self.client.embeddings(&request).await?.into_embeddings())
+    }
+}
+
+/// Passages as system context, asking the model to cite them.
+pub fn context(passages: &[Passage]) -> String {
+    let mut context = "These passages from the rules reference and FAQ may answer the question. \
+        When you rely on one, cite it as it is labelled, e.g. [Rules Reference § Attack]."
+        .to_string();
+    for passage in passages {
+        context.push_str(&format!("\n\n{}\n{}", passage.citation(), passage.text));
+    }
+
+    context
+}
+
+/// Chunks the Markdown and text documents in `dir`, named after their files.
+pub fn load_documents(dir: &Path) -> Result<Vec<Passage>, RulesError> {
+    let entries = fs::read_dir(dir).map_err(|e| RulesError::IoError(dir.to_path_buf(), e))?;
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "md" || extension == "txt")
+        })
+        .collect();
+    paths.sort();
+
+    let mut passages = Vec::new();
+    for path in paths {
+        let text = fs::read_to_string(&path).map_err(|e| RulesError::IoError(path.clone(), e))?;
+        let source = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().replace(['_', '-'], " "))
+            .unwrap_or_default();
+        passages.extend(chunk(&source, &text));
+    }
+
+    Ok(passages)
+}
+
+/// Splits a document into passages at its Markdown headings, and long sections further at
+/// paragraph breaks.
+pub fn chunk(source: &str, text: &str) -> Vec<Passage> {
+    let mut passages = Vec::new();
+    let mut headings: Vec<(usize, String)> = Vec::new();
+    let mut body = String::new();
+
+    for line in text.lines() {
+        if let Some((level, heading)) = heading(line) {
+            push_section(&mut passages, source, &headings, &body);
+            body.clear();
+            headings.retain(|(other, _)| *other < level);
+            headings.push((level, heading.to_string()));
+        } else {
+            body.push_str(line);
+            body.push('\n');
+        }
+    }
+    push_section(&mut passages, source, &headings, &body);
+
+    passages
+}
+
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    let heading = line[level..].strip_prefix(' ')?.trim();
+    (level > 0 && !heading.is_empty()).then_some((level, heading))
+}
+
+fn push_section(
+    passages: &mut Vec<Passage>,
+    source: &str,
+    headings: &[(usize, String)],
+    body: &str,
+) {
+    let section = headings
+        .iter()
+        .map(|(_, heading)| heading.as_str())
+        .collect::<Vec<_>>()
+        .join(" > ");
+
+    let mut text = String::new();
+    for paragraph in body
+        .split("\n\n")
+        .map(str::trim)
+        .filter(|paragraph| !paragraph.is_empty())
+    {
+        if !text.is_empty() && text.len() + paragraph.len() > MAX_PASSAGE_CHARS {
+            passages.push(Passage {
+                source: source.to_string(),
+                section: section.clone(),
+                text: std::mem::take(&mut text),
+            });
+        }
+        if !text.is_empty() {
+            text.push_str("\n\n");
+        }
+        text.push_str(paragraph);
+    }
+    if !text.is_empty() {
+        passages.push(Passage {
+            source: source.to_string(),
+            section,
+            text,
+        });
+    }
+}
+
+fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norms = norm(a) * norm(b);
+    if norms == 0.0 { 0.0 } else { dot / norms }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunk() {
+        let text = "Introduction.\n\n\
+                    # Attack\n\
+                    An attack deals damage.\n\n\
+                    ## Overkill\n\
+                    Excess damage is dealt to the hero.\n\
+                    # Stunned\n\
+                    The next activation is prevented.\n";
+
+        let passages = chunk("Rules Reference", text);
+        let sections: Vec<(&str, &str)> = passages
+            .iter()
+            .map(|passage| (passage.section.as_str(), passage.text.as_str()))
+            .collect();
+        assert_eq!(
+            sections,
+            vec![
+                ("", "Introduction."),
+                ("Attack", "An attack deals damage."),
+                ("Attack > Overkill", "Excess damage is dealt to the hero."),
+                ("Stunned", "The next activation is prevented."),
+            ]
+        );
+        assert_eq!(
+            passages[2].citation(),
+            "[Rules Reference § Attack > Overkill]"
+        );
+        assert_eq!(passages[0].citation(), "[Rules Reference]");
+    }
+
+    #[test]
+    fn test_chunk_splits_long_sections() {
+        let paragraph = "x".repeat(MAX_PASSAGE_CHARS / 2 + 1);
+        let text = format!("# Long\n{paragraph}\n\n{paragraph}\n\n{paragraph}");
+
+        let passages = chunk("FAQ", &text);
+        assert_eq!(passages.len(), 3);
+        assert!(passages.iter().all(|passage| passage.section == "Long"));
+    }
+
+    #[test]
+    fn test_search() {
+        let passage = |section: &str, embedding: Vec<f32>| IndexedPassage {
+            passage: Passage {
+                source: "Rules Reference".to_string(),
+                section: section.to_string(),
+                text: String::new(),
+            },
+            embedding,
+        };
+        let index = RulesIndex {
+            model: "test".to_string(),
+            passages: vec![
+                passage("Attack", vec![1.0, 0.0]),
+                passage("Thwart", vec![0.0, 1.0]),
+                passage("Retaliate", vec![0.8, 0.6]),
+            ],
+        };
+
+        let sections: Vec<&str> = index
+            .search(&[1.0, 0.1], 3)
+            .iter()
+            .map(|passage| passage.section.as_str())
+            .collect();
+        assert_eq!(sections, vec!["Attack", "Retaliate"]);
+    }
+}
@@ -0,0 +1,15 @@
+use serde::{Deserialize, Serialize};
+
+use crate::personas::Personas;
+
+/// Settings changed at runtime with `/karen`, which take the place of the environment and the
+/// built-in defaults.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Settings {
+    #[serde(default)]
+    pub model_id: Option<String>,
+    #[serde(default)]
+    pub system_prompt: Option<String>,
+    #[serde(default)]
+    pub personas: Personas,
+}
@@ -0,0 +1,253 @@
+use serde::{Serialize, de::DeserializeOwned};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+use tokio::{
+    io::AsyncWriteExt,
+    sync::{RwLock, RwLockReadGuard},
+};
+
+#[derive(Error, Debug)]
+#[allow(clippy::enum_variant_names)]
+pub enum StorageError {
+    #[error("IO error with {0}: {1}")]
+    IoError(PathBuf, io::Error),
+    #[error("JSON error with {0}: {1}")]
+    JsonError(PathBuf, serde_json::Error),
+}
+
+/// State that is kept in memory and saved to a JSON file after every update. Without a file it
+/// only lives as long as the process.
+#[derive(Debug, Default)]
+pub struct JsonStore<T> {
+    path: Option<PathBuf>,
+    data: RwLock<T>,
+}
+
+impl<T: Serialize + DeserializeOwned + Default + Clone> JsonStore<T> {
+    /// Opens `name` in `dir`, starting empty if the file doesn't exist yet.
+    pub fn open(dir: Option<&Path>, name: &str) -> Result<Self, StorageError> {
+        let Some(dir) = dir else {
+            return Ok(Self::in_memory());
+        };
+
+        let path = dir.join(name);
+        let data = match fs::read_to_string(&path) {
+            Ok(json) => {
+                serde_json::from_str(&json).map_err(|e| StorageError::JsonError(path.clone(), e))?
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => T::default(),
+            Err(e) => return Err(StorageError::IoError(path, e)),
+        };
+
+        Ok(Self {
+            path: Some(path),
+            data: RwLock::new(data),
+        })
+    }
+
+    pub fn in_memory() -> Self {
+        Self {
+            path: None,
+            data: RwLock::new(T::default()),
+        }
+    }
+
+    pub async fn read(&self) -> RwLockReadGuard<'_, T> {
+        self.data.read().await
+    }
+
+    /// Applies `update` to a copy of the state and saves it, keeping the change only once it's
+    /// saved. The file is replaced atomically, so a crash can't leave it half written.
+    pub async fn update<R>(&self, update: impl FnOnce(&mut T) -> R) -> Result<R, StorageError> {
+        let mut data = self.data.write().await;
+        let mut updated = data.clone();
+        let result = update(&mut updated);
+
+        if let Some(path) = &self.path {
+            let json = serde_json::to_vec_pretty(&updated)
+                .map_err(|e| StorageError::JsonError(path.clone(), e))?;
+            let temp_path = path.with_extension("json.tmp");
+            if let Some(dir) = path.parent() {
+                tokio::fs::create_dir_all(dir)
+                    .await
+                    .map_err(|e| StorageError::IoError(dir.to_path_buf(), e))?;
+            }
+            tokio::fs::write(&temp_path, json)
+                .await
+                .map_err(|e| StorageError::IoError(temp_path.clone(), e))?;
+            tokio::fs::rename(&temp_path, path)
+                .await
+                .map_err(|e| StorageError::IoError(path.clone(), e))?;
+        }
+        *data = updated;
+
+        Ok(result)
+    }
+}
+
+/// State built up from entries that are never rewritten, such as a log of events.
+pub trait Journal: Default {
+    type Entry: Serialize + DeserializeOwned;
+
+    fn apply(&mut self, entry: Self::Entry);
+}
+
+/// State that is kept in memory and saved by appending each entry as a line of a JSON Lines
+/// file, so saving doesn't grow with the state. Without a file it only lives as long as the
+/// process.
+#[derive(Debug, Default)]
+pub struct JsonlStore<T> {
+    path: Option<PathBuf>,
+    data: RwLock<T>,
+}
+
+impl<T: Journal> JsonlStore<T> {
+    /// Opens `name` in `dir` and replays its entries, starting empty if the file doesn't exist
+    /// yet. Lines that don't parse, such as one cut short by a crash, are skipped.
+    pub fn open(dir: Option<&Path>, name: &str) -> Result<Self, StorageError> {
+        let Some(dir) = dir else {
+            return Ok(Self::in_memory());
+        };
+
+        let path = dir.join(name);
+        let mut data = T::default();
+        match fs::read_to_string(&path) {
+            Ok(jsonl) => {
+                for (number, line) in jsonl.lines().enumerate() {
+                    match serde_json::from_str(line) {
+                        Ok(entry) => data.apply(entry),
+                        Err(e) => tracing::warn!(
+                            "Skipping line {} of {}: {e}",
+                            number + 1,
+                            path.display()
+                        ),
+                    }
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(StorageError::IoError(path, e)),
+        }
+
+        Ok(Self {
+            path: Some(path),
+            data: RwLock::new(data),
+        })
+    }
+
+    pub fn in_memory() -> Self {
+        Self {
+            path: None,
+            data: RwLock::new(T::default()),
+        }
+    }
+
+    pub async fn read(&self) -> RwLockReadGuard<'_, T> {
+        self.data.read().await
+    }
+
+    /// Saves an entry and applies it, keeping it only once it's saved.
+    pub async fn append(&self, entry: T::Entry) -> Result<(), StorageError> {
+        let mut data = self.data.write().await;
+
+        if let Some(path) = &self.path {
+            let mut line =
+                serde_json::to_vec(&entry).map_err(|e| StorageError::JsonError(path.clone(), e))?;
+            line.push(b'\n');
+            if let Some(dir) = path.parent() {
+                tokio::fs::create_dir_all(dir)
+                    .await
+                    .map_err(|e| StorageError::IoError(dir.to_path_buf(), e))?;
+            }
+            let mut file = tokio::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .await
+                .map_err(|e| StorageError::IoError(path.clone(), e))?;
+            file.write_all(&line)
+                .await
+                .map_err(|e| StorageError::IoError(path.clone(), e))?;
+            file.flush()
+                .await
+                .map_err(|e| StorageError::IoError(path.clone(), e))?;
+        }
+        data.apply(entry);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[tokio::test]
+    async fn test_update_persists() {
+        let dir = std::env::temp_dir().join(format!("karen-storage-{}", std::process::id()));
+        let store = JsonStore::<HashMap<u64, Vec<String>>>::open(Some(&dir), "test.json").unwrap();
+        store
+            .update(|data| data.insert(1, vec!["core".to_string()]))
+            .await
+            .unwrap();
+
+        let reopened =
+            JsonStore::<HashMap<u64, Vec<String>>>::open(Some(&dir), "test.json").unwrap();
+        assert_eq!(
+            reopened.read().await.get(&1),
+            Some(&vec!["core".to_string()])
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_failed_update_is_discarded() {
+        let dir = std::env::temp_dir().join(format!("karen-storage-fail-{}", std::process::id()));
+        let store = JsonStore::<HashMap<u64, Vec<String>>>::open(Some(&dir), "test.json").unwrap();
+        // Saving fails once the store's directory is a file.
+        fs::write(&dir, "").unwrap();
+
+        let result = store
+            .update(|data| data.insert(1, vec!["core".to_string()]))
+            .await;
+        assert!(result.is_err());
+        assert!(store.read().await.is_empty());
+
+        fs::remove_file(dir).unwrap();
+    }
+
+    #[derive(Default)]
+    struct Total(u64);
+
+    impl Journal for Total {
+        type Entry = u64;
+
+        fn apply(&mut self, entry: u64) {
+            self.0 += entry;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_append_persists() {
+        let dir = std::env::temp_dir().join(format!("karen-storage-jsonl-{}", std::process::id()));
+        let store = JsonlStore::<Total>::open(Some(&dir), "test.jsonl").unwrap();
+        store.append(2).await.unwrap();
+        store.append(3).await.unwrap();
+        assert_eq!(store.read().await.0, 5);
+
+        // A line cut short by a crash is skipped.
+        let path = dir.join("test.jsonl");
+        let mut jsonl = fs::read_to_string(&path).unwrap();
+        jsonl.push_str("4\n\"");
+        fs::write(&path, jsonl).unwrap();
+
+        let reopened = JsonlStore::<Total>::open(Some(&dir), "test.jsonl").unwrap();
+        assert_eq!(reopened.read().await.0, 9);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
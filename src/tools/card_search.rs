@@ -0,0 +1,69 @@
+use serde_json::{Value, json};
+use std::sync::Arc;
+
+use super::{LocalTool, ToolError};
+use crate::cards::{CardDatabase, database::CardQuery};
+
+const DEFAULT_LIMIT: usize = 10;
+const MAX_LIMIT: usize = 25;
+
+/// Looks up cards in the local card database.
+pub struct CardSearch {
+    database: Arc<CardDatabase>,
+}
+
+impl CardSearch {
+    pub fn new(database: Arc<CardDatabase>) -> Self {
+        Self { database }
+    }
+}
+
+impl LocalTool for CardSearch {
+    fn name(&self) -> &'static str {
+        "card_search"
+    }
+
+    fn description(&self) -> &'static str {
+        "Looks up official Marvel Champions cards in the local card database by code, name, trait, set, pack or aspect. Filters combine. Returns the card data, including the official card text."
+    }
+
+    fn properties(&self) -> Value {
+        json!({
+            "code": { "type": "string", "description": "Card code, e.g. 01089" },
+            "name": { "type": "string", "description": "Card name or part of it" },
+            "trait": { "type": "string", "description": "Card trait, e.g. Avenger" },
+            "set_code": { "type": "string", "description": "Card set code, e.g. spider_man" },
+            "pack_code": { "type": "string", "description": "Pack code, e.g. core" },
+            "aspect": {
+                "type": "string",
+                "description": "Aspect or faction code",
+                "enum": ["aggression", "justice", "leadership", "protection", "pool", "basic", "hero", "encounter", "campaign"]
+            },
+            "limit": { "type": "integer", "description": "Maximum number of cards to return" }
+        })
+    }
+
+    fn call(&self, arguments: Value) -> Result<Value, ToolError> {
+        let limit = arguments
+            .get("limit")
+            .and_then(Value::as_u64)
+            .map_or(DEFAULT_LIMIT, |limit| (limit as usize).clamp(1, MAX_LIMIT));
+        let query: CardQuery = serde_json::from_value(arguments)
+            .map_err(|e| ToolError::InvalidArguments(e.to_string()))?;
+        if query.is_empty() {
+            return Err(ToolError::InvalidArguments(
+                "At least one filter is required".to_string(),
+            ));
+        }
+
+        let cards = self.database.search(&query);
+        Ok(json!({
+            "count": cards.len(),
+            "cards": cards
+                .iter()
+                .take(limit)
+                .map(|card| self.database.card_json(card))
+                .collect::<Vec<_>>(),
+        }))
+    }
+}
@@ -0,0 +1,57 @@
+use serde_json::{Value, json};
+
+use super::{LocalTool, ToolError};
+use crate::cards::odds::DrawOdds;
+
+/// Calculates draw probabilities, which models are unreliable at.
+pub struct DrawOddsTool;
+
+impl LocalTool for DrawOddsTool {
+    fn name(&self) -> &'static str {
+        "draw_odds"
+    }
+
+    fn description(&self) -> &'static str {
+        "Calculates the exact probability of drawing at least a number of copies of one or more kinds of cards, in the opening hand or by a later turn, optionally after a mulligan. Always use this instead of doing the arithmetic yourself."
+    }
+
+    fn properties(&self) -> Value {
+        json!({
+            "deck_size": { "type": "integer", "description": "Cards in the deck, 40 by default, at most 60" },
+            "hand_size": { "type": "integer", "description": "Hero hand size, 5 by default" },
+            "categories": {
+                "type": "array",
+                "description": "Kinds of cards that all have to be drawn, at most 2",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "copies": { "type": "integer", "description": "Copies in the deck" },
+                        "need": { "type": "integer", "description": "Copies needed, 1 by default" }
+                    },
+                    "required": ["copies"]
+                }
+            },
+            "turns": { "type": "integer", "description": "Turns after the opening hand, each redrawing to hand size. 0 for the opening hand" },
+            "cards_per_turn": { "type": "integer", "description": "Cards drawn each turn, the hand size by default" },
+            "mulligan": { "type": "boolean", "description": "Whether the opening hand is mulliganed, redrawing every other card once" }
+        })
+    }
+
+    fn required(&self) -> Vec<String> {
+        vec!["categories".to_string()]
+    }
+
+    fn call(&self, arguments: Value) -> Result<Value, ToolError> {
+        let odds: DrawOdds = serde_json::from_value(arguments)
+            .map_err(|e| ToolError::InvalidArguments(e.to_string()))?;
+        let probability = odds
+            .probability()
+            .map_err(|e| ToolError::InvalidArguments(e.to_string()))?;
+
+        Ok(json!({
+            "probability": probability,
+            "percent": format!("{:.1}%", probability * 100.0),
+            "cards_seen": odds.cards_seen(),
+        }))
+    }
+}
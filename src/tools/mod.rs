@@ -0,0 +1,181 @@
+use serde_json::{Value, json};
+use std::sync::Arc;
+use thiserror::Error;
+
+use crate::heroku_mia::{
+    Client,
+    agents::{AgentTool, AgentToolType},
+    chat_completion::{
+        ChatCompletionRequest, ChatCompletionTool, FunctionDefinition, FunctionParameters,
+        ToolChoice,
+    },
+    client::HerokuMiaError,
+    types::Message,
+};
+
+pub mod card_search;
+pub mod draw_odds;
+pub mod validate_deck;
+
+/// Upper bound on chat completion round trips spent calling local tools before an agent call.
+const MAX_LOCAL_TOOL_ROUNDS: usize = 3;
+
+#[derive(Error, Debug)]
+pub enum ToolError {
+    #[error("No such tool {0}")]
+    NoSuchTool(String),
+    #[error("Invalid arguments: {0}")]
+    InvalidArguments(String),
+}
+
+/// A tool that runs in the bot's process instead of on an MCP server.
+pub trait LocalTool: Send + Sync {
+    fn name(&self) -> &'static str;
+    fn description(&self) -> &'static str;
+    /// The JSON schema `properties` of the tool's arguments object.
+    fn properties(&self) -> Value;
+    fn required(&self) -> Vec<String> {
+        Vec::new()
+    }
+    fn call(&self, arguments: Value) -> Result<Value, ToolError>;
+}
+
+/// The local tools offered to the model.
+#[derive(Clone, Default)]
+pub struct LocalTools {
+    tools: Vec<Arc<dyn LocalTool>>,
+}
+
+impl LocalTools {
+    pub fn with(mut self, tool: impl LocalTool + 'static) -> Self {
+        self.tools.push(Arc::new(tool));
+        self
+    }
+
+    /// The tools whose names `keep` accepts.
+    pub fn filter(&self, keep: impl Fn(&str) -> bool) -> Self {
+        Self {
+            tools: self
+                .tools
+                .iter()
+                .filter(|tool| keep(tool.name()))
+                .cloned()
+                .collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tools.is_empty()
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        self.tools.iter().map(|tool| tool.name()).collect()
+    }
+
+    pub fn definitions(&self) -> Vec<ChatCompletionTool> {
+        self.tools
+            .iter()
+            .map(|tool| {
+                let required = tool.required();
+                ChatCompletionTool::function(FunctionDefinition {
+                    name: tool.name().to_string(),
+                    description: Some(tool.description().to_string()),
+                    parameters: Some(FunctionParameters {
+                        r#type: "object".to_string(),
+                        properties: tool.properties(),
+                        required: (!required.is_empty()).then_some(required),
+                    }),
+                })
+            })
+            .collect()
+    }
+
+    /// Calls a tool. Arguments may be a JSON object or a string holding one.
+    pub fn call(&self, name: &str, arguments: &Value) -> Result<Value, ToolError> {
+        let tool = self
+            .tools
+            .iter()
+            .find(|tool| tool.name() == name)
+            .ok_or_else(|| ToolError::NoSuchTool(name.to_string()))?;
+
+        let arguments = match arguments {
+            Value::String(arguments) if arguments.trim().is_empty() => json!({}),
+            Value::String(arguments) => serde_json::from_str(arguments)
+                .map_err(|e| ToolError::InvalidArguments(e.to_string()))?,
+            Value::Null => json!({}),
+            arguments => arguments.clone(),
+        };
+
+        tool.call(arguments)
+    }
+}
+
+/// The tools of the MCP servers attached to the inference service, for agent calls.
+pub async fn mcp_tools(client: &Client) -> Result<Vec<AgentTool>, HerokuMiaError> {
+    Ok(client
+        .list_mcp_servers()
+        .await?
+        .into_iter()
+        .flat_map(|server| {
+            server
+                .tools
+                .into_iter()
+                .map(|tool| AgentTool::builder(AgentToolType::Mcp, tool.namespaced_name).build())
+        })
+        .collect())
+}
+
+/// Lets the model call local tools through chat completion function calling, since the agents
+/// endpoint only runs MCP and Heroku tools. Returns the assistant tool calls and their results,
+/// to be added to the conversation ahead of the agent call, and the tokens the round trips used.
+/// An answer given without calling a tool is dropped, as the agent call answers the prompt.
+pub async fn resolve(
+    client: &Client,
+    inference_model_id: &str,
+    messages: &[Message],
+    tools: &LocalTools,
+) -> Result<(Vec<Message>, u64), HerokuMiaError> {
+    let mut tool_messages = Vec::new();
+    let mut tokens = 0;
+
+    for _ in 0..MAX_LOCAL_TOOL_ROUNDS {
+        let request = ChatCompletionRequest::builder(
+            inference_model_id,
+            [messages, tool_messages.as_slice()].concat(),
+        )
+        .max_tokens(1024)
+        .tools(tools.definitions())
+        .tool_choice(ToolChoice::Auto)
+        .build();
+        let response = client.chat_completion(&request).await?;
+        tokens += u64::from(response.usage.total_tokens.unwrap_or_default());
+
+        let Some(choice) = response.choices.into_iter().next() else {
+            break;
+        };
+        let tool_calls = match &choice.message {
+            Message::Assistant {
+                tool_calls: Some(tool_calls),
+                ..
+            } if !tool_calls.is_empty() => tool_calls.clone(),
+            _ => break,
+        };
+
+        tool_messages.push(choice.message);
+        for tool_call in tool_calls {
+            tracing::info!("Local Tool: Calling {}", tool_call.function.name);
+            let content = tools
+                .call(&tool_call.function.name, &tool_call.function.arguments)
+                .unwrap_or_else(|e| {
+                    tracing::error!("Local Tool: {} failed: {e}", tool_call.function.name);
+                    json!({ "error": e.to_string() })
+                });
+            tool_messages.push(Message::Tool {
+                content,
+                tool_call_id: tool_call.id,
+            });
+        }
+    }
+
+    Ok((tool_messages, tokens))
+}
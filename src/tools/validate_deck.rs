@@ -0,0 +1,76 @@
+use serde_json::{Value, json};
+use std::{collections::BTreeMap, sync::Arc};
+
+use super::{LocalTool, ToolError};
+use crate::cards::{CardDatabase, deck::Deck, validation};
+
+/// Checks a deck's legality against the deckbuilding rules.
+pub struct ValidateDeck {
+    database: Arc<CardDatabase>,
+}
+
+impl ValidateDeck {
+    pub fn new(database: Arc<CardDatabase>) -> Self {
+        Self { database }
+    }
+}
+
+impl LocalTool for ValidateDeck {
+    fn name(&self) -> &'static str {
+        "validate_deck"
+    }
+
+    fn description(&self) -> &'static str {
+        "Checks whether a Marvel Champions deck is legal: deck size, signature cards, aspects, copy limits and the ban list. Give either a pasted deck list, or a hero code with card codes and quantities. Always use this instead of guessing about deck legality."
+    }
+
+    fn properties(&self) -> Value {
+        json!({
+            "deck_list": { "type": "string", "description": "A deck list with one `2x Card Name` entry per line" },
+            "hero_code": { "type": "string", "description": "Hero card code, e.g. 01001a" },
+            "aspects": {
+                "type": "array",
+                "items": { "type": "string", "enum": ["aggression", "justice", "leadership", "protection", "pool"] },
+                "description": "The deck's aspects"
+            },
+            "slots": {
+                "type": "object",
+                "additionalProperties": { "type": "integer" },
+                "description": "Card codes mapped to their quantities, e.g. {\"01089\": 3}"
+            }
+        })
+    }
+
+    fn call(&self, arguments: Value) -> Result<Value, ToolError> {
+        let mut deck = match arguments.get("deck_list").and_then(Value::as_str) {
+            Some(list) => Deck::parse_list(list, &self.database),
+            None => Deck {
+                slots: serde_json::from_value::<BTreeMap<String, u32>>(
+                    arguments.get("slots").cloned().unwrap_or_else(|| json!({})),
+                )
+                .map_err(|e| ToolError::InvalidArguments(e.to_string()))?,
+                ..Default::default()
+            },
+        };
+        if let Some(hero_code) = arguments.get("hero_code").and_then(Value::as_str) {
+            deck.hero_code = Some(hero_code.to_string());
+        }
+        if let Some(aspects) = arguments.get("aspects") {
+            deck.aspects = serde_json::from_value(aspects.clone())
+                .map_err(|e| ToolError::InvalidArguments(e.to_string()))?;
+        }
+        if deck.slots.is_empty() {
+            return Err(ToolError::InvalidArguments(
+                "Either deck_list or slots is required".to_string(),
+            ));
+        }
+
+        let violations = validation::validate(&deck, &self.database);
+        Ok(json!({
+            "legal": violations.is_empty(),
+            "card_count": deck.card_count(),
+            "violations": violations.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            "unrecognized_entries": deck.unresolved,
+        }))
+    }
+}